@@ -0,0 +1,125 @@
+//! Compares direct `Rc`/`Arc` usage with the same operations through the family
+//! traits, which should compile to the same code.
+
+use cark_ref_counted::*;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::{
+    rc::{self, Rc},
+    sync::{self, Arc},
+};
+
+/// Benchmarks the pointer operations of the `R` family, under `name`.
+fn family<R: RefCountFamily>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("new", |b| b.iter(|| R::new(black_box(1u64))));
+    let p = R::new(1u64);
+    group.bench_function("clone", |b| b.iter(|| black_box(&p).clone()));
+    group.bench_function("deref", |b| b.iter(|| **black_box(&p)));
+    let mut unique = R::new(1u64);
+    group.bench_function("make_mut", |b| {
+        b.iter(|| *R::Pointer::make_mut(black_box(&mut unique)) += 1)
+    });
+    group.bench_function("downgrade_upgrade", |b| {
+        b.iter(|| R::Pointer::downgrade(black_box(&p)).upgrade())
+    });
+    group.finish();
+}
+
+fn direct_rc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Rc");
+    group.bench_function("new", |b| b.iter(|| Rc::new(black_box(1u64))));
+    let p = Rc::new(1u64);
+    group.bench_function("clone", |b| b.iter(|| black_box(&p).clone()));
+    group.bench_function("deref", |b| b.iter(|| **black_box(&p)));
+    let mut unique = Rc::new(1u64);
+    group.bench_function("make_mut", |b| {
+        b.iter(|| *Rc::make_mut(black_box(&mut unique)) += 1)
+    });
+    group.bench_function("downgrade_upgrade", |b| {
+        b.iter(|| rc::Weak::upgrade(&Rc::downgrade(black_box(&p))))
+    });
+    group.finish();
+}
+
+fn direct_arc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Arc");
+    group.bench_function("new", |b| b.iter(|| Arc::new(black_box(1u64))));
+    let p = Arc::new(1u64);
+    group.bench_function("clone", |b| b.iter(|| black_box(&p).clone()));
+    group.bench_function("deref", |b| b.iter(|| **black_box(&p)));
+    let mut unique = Arc::new(1u64);
+    group.bench_function("make_mut", |b| {
+        b.iter(|| *Arc::make_mut(black_box(&mut unique)) += 1)
+    });
+    group.bench_function("downgrade_upgrade", |b| {
+        b.iter(|| sync::Weak::upgrade(&Arc::downgrade(black_box(&p))))
+    });
+    group.finish();
+}
+
+fn families(c: &mut Criterion) {
+    family::<RcMark>(c, "RcMark");
+    family::<ArcMark>(c, "ArcMark");
+}
+
+/// A persistent list generic over the family, as downstream data structures are.
+enum List<R: RefCountFamily> {
+    Nil,
+    Cons(u64, R::Pointer<List<R>>),
+}
+
+impl<R: RefCountFamily> List<R> {
+    fn build(len: u64) -> R::Pointer<Self> {
+        (0..len).fold(R::new(List::Nil), |tail, i| R::new(List::Cons(i, tail)))
+    }
+
+    fn sum(mut list: &Self) -> u64 {
+        let mut sum = 0;
+        while let List::Cons(value, tail) = list {
+            sum += value;
+            list = tail;
+        }
+        sum
+    }
+}
+
+/// The same list, hard-wired to `Rc`.
+enum RcList {
+    Nil,
+    Cons(u64, Rc<RcList>),
+}
+
+impl RcList {
+    fn build(len: u64) -> Rc<Self> {
+        (0..len).fold(Rc::new(RcList::Nil), |tail, i| {
+            Rc::new(RcList::Cons(i, tail))
+        })
+    }
+
+    fn sum(mut list: &Self) -> u64 {
+        let mut sum = 0;
+        while let RcList::Cons(value, tail) = list {
+            sum += value;
+            list = tail;
+        }
+        sum
+    }
+}
+
+fn data_structure(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list");
+    group.bench_function("Rc/build", |b| b.iter(|| RcList::build(black_box(1000))));
+    group.bench_function("RcMark/build", |b| {
+        b.iter(|| List::<RcMark>::build(black_box(1000)))
+    });
+    let direct = RcList::build(1000);
+    let generic = List::<RcMark>::build(1000);
+    group.bench_function("Rc/sum", |b| b.iter(|| RcList::sum(black_box(&direct))));
+    group.bench_function("RcMark/sum", |b| {
+        b.iter(|| List::<RcMark>::sum(black_box(&generic)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, direct_rc, direct_arc, families, data_structure);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! The `derive` feature of `cark-ref-counted`. See `ref_counted_generic` there.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, DeriveInput, GenericParam, Generics, Ident, TypeParam,
+    TypeParamBound,
+};
+
+fn is_family_bound(bound: &TypeParamBound) -> bool {
+    match bound {
+        TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "RefCountFamily"),
+        _ => false,
+    }
+}
+
+fn family_param<'a>(generics: &'a mut Generics, name: Option<&Ident>) -> Option<&'a mut TypeParam> {
+    generics.type_params_mut().find(|param| match name {
+        Some(name) => param.ident == *name,
+        None => param.bounds.iter().any(is_family_bound),
+    })
+}
+
+/// The generics of the aliases: the item generics without bounds.
+fn alias_generics(generics: &Generics) -> Generics {
+    let mut generics = generics.clone();
+    generics.where_clause = None;
+    for param in generics.params.iter_mut() {
+        match param {
+            GenericParam::Lifetime(param) => {
+                param.colon_token = None;
+                param.bounds.clear();
+            }
+            GenericParam::Type(param) => {
+                param.colon_token = None;
+                param.bounds.clear();
+            }
+            GenericParam::Const(_) => {}
+        }
+    }
+    generics
+}
+
+#[proc_macro_attribute]
+pub fn ref_counted_generic(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(attr as Option<Ident>);
+    let mut input = parse_macro_input!(item as DeriveInput);
+    let Some(param) = family_param(&mut input.generics, name.as_ref()) else {
+        let message = match name {
+            Some(name) => format!("no type parameter named `{name}`"),
+            None => "expected a type parameter bounded by `RefCountFamily`, \
+                     or the parameter name as in `#[ref_counted_generic(RC)]`"
+                .to_owned(),
+        };
+        return syn::Error::new(Span::call_site(), message)
+            .to_compile_error()
+            .into();
+    };
+    if !param.bounds.iter().any(is_family_bound) {
+        param
+            .bounds
+            .push(parse_quote!(::cark_ref_counted::RefCountFamily));
+    }
+    let family = param.ident.clone();
+
+    let vis = &input.vis;
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let generics = alias_generics(&input.generics);
+    let pointer = format_ident!("{}Pointer", ident);
+    let weak = format_ident!("{}WeakPointer", ident);
+    let pointer_doc = format!("The strong pointer to a [`{ident}`] of the `{family}` family.");
+    let weak_doc = format!("The weak pointer to a [`{ident}`] of the `{family}` family.");
+    quote! {
+        #input
+
+        #[doc = #pointer_doc]
+        #vis type #pointer #generics =
+            <#family as ::cark_ref_counted::RefCountFamily>::Pointer<#ident #ty_generics>;
+
+        #[doc = #weak_doc]
+        #vis type #weak #generics =
+            <#family as ::cark_ref_counted::RefCountFamily>::WeakPointer<#ident #ty_generics>;
+    }
+    .into()
+}
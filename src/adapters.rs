@@ -0,0 +1,779 @@
+//! Building blocks for families wrapping another family.
+//!
+//! [WrapMark] is a [RefCountFamily] behaving like the family it wraps, and calling
+//! a [WrapHooks] implementation on each pointer operation. Logging, instrumentation
+//! and similar combinators only need to implement the hooks they care about.
+
+use crate::{lock::LockFamily, traits::*};
+use std::{
+    any::type_name,
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    pin::Pin,
+    ptr,
+};
+
+/// Callbacks of a [WrapMark], each receiving the address of the allocation and
+/// its [RefCounts] after the operation.
+///
+/// All hooks do nothing by default.
+pub trait WrapHooks {
+    /// An allocation of `size` bytes for a value is about to be made. Returning
+    /// `false` makes it fail: [TryNewFamily::try_new] returns an error, and the
+    /// other constructors panic.
+    fn on_alloc(_size: usize) -> bool {
+        true
+    }
+    /// A new allocation was made with a strong pointer to it.
+    fn on_new(_addr: *const (), _counts: RefCounts) {}
+    /// A `new_cyclic` construction started: the allocation only has a weak pointer.
+    fn on_cyclic_start(_addr: *const (), _counts: RefCounts) {}
+    /// Called after [WrapHooks::on_new] and [WrapHooks::on_cyclic_start], with the
+    /// type of the value in the new allocation.
+    fn on_type_name(_addr: *const (), _type_name: &'static str, _counts: RefCounts) {}
+    /// A `new_cyclic` construction ended with the first strong pointer.
+    fn on_cyclic_end(_addr: *const (), _counts: RefCounts) {}
+    fn on_clone(_addr: *const (), _counts: RefCounts) {}
+    fn on_drop(_addr: *const (), _counts: RefCounts) {}
+    fn on_downgrade(_addr: *const (), _counts: RefCounts) {}
+    fn on_clone_weak(_addr: *const (), _counts: RefCounts) {}
+    fn on_drop_weak(_addr: *const (), _counts: RefCounts) {}
+    /// An upgrade attempt, and whether it succeeded.
+    fn on_upgrade(_addr: *const (), _upgraded: bool, _counts: RefCounts) {}
+}
+
+/// The strong and weak counts of an allocation after an operation, as given to
+/// the [WrapHooks].
+///
+/// They are read from the wrapped pointers, so with a thread-safe family they
+/// may already include concurrent operations on the same allocation. In
+/// particular, two threads dropping the last two strong pointers may both be given
+/// a `strong` of 1: hooks needing to know the last drop must count the strong
+/// pointers themselves, as the hooks of `MeteredMark` do. As for
+/// [WeakPointer::weak_count], `weak` does not count the weak pointer shared by
+/// the strong pointers, and is 0 once no strong pointer is left.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RefCounts {
+    pub strong: usize,
+    pub weak: usize,
+}
+
+impl RefCounts {
+    fn of<P: RefCounted<T>, T: ?Sized>(pointer: &P) -> Self {
+        Self {
+            strong: P::strong_count(pointer),
+            weak: P::weak_count(pointer),
+        }
+    }
+
+    fn of_weak<P: WeakPointer<T>, T: ?Sized>(pointer: &P) -> Self {
+        Self {
+            strong: pointer.strong_count(),
+            weak: pointer.weak_count(),
+        }
+    }
+
+    /// The counts once a strong pointer is dropped from these.
+    fn without_strong(self) -> Self {
+        Self {
+            strong: self.strong.saturating_sub(1),
+            ..self
+        }
+    }
+}
+
+/// No hooks, for marks only differing from the family they wrap by their tag.
+impl WrapHooks for () {}
+
+/// A [RefCountFamily] behaving like `Mark`, and calling the `H` hooks.
+///
+/// `Tag` tells apart marks wrapping the same family with the same hooks, so that
+/// they can implement other traits differently, as `ParkingLotArcMark` does for
+/// [LockFamily].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// static CLONES: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct CountClones;
+///
+/// impl WrapHooks for CountClones {
+///     fn on_clone(_addr: *const (), _counts: RefCounts) {
+///         CLONES.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// type CountedMark = WrapMark<RcMark, CountClones>;
+/// let a = CountedMark::new(1);
+/// let _b = a.clone();
+/// assert_eq!(CLONES.load(Ordering::Relaxed), 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WrapMark<Mark: RefCountFamily, H: WrapHooks, Tag = ()>(PhantomData<(Mark, H, Tag)>);
+
+/// The strong pointer of [WrapMark].
+pub struct Wrap<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag = ()>(
+    ManuallyDrop<Mark::Pointer<T>>,
+    PhantomData<fn() -> (H, Tag)>,
+);
+
+/// The weak pointer of [WrapMark].
+pub struct WrapWeak<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag = ()>(
+    ManuallyDrop<Mark::WeakPointer<T>>,
+    PhantomData<fn() -> (H, Tag)>,
+);
+
+fn address<T: ?Sized>(ptr: *const T) -> *const () {
+    ptr as *const ()
+}
+
+/// Calls [WrapHooks::on_alloc], panicking if the allocation must fail.
+fn alloc<H: WrapHooks>(size: usize) {
+    assert!(H::on_alloc(size), "allocation of {size} bytes failed");
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, Tag> RefCountFamily for WrapMark<Mark, H, Tag> {
+    type Pointer<T: ?Sized> = Wrap<Mark, H, T, Tag>;
+    type WeakPointer<T: ?Sized> = WrapWeak<Mark, H, T, Tag>;
+    const IS_THREAD_SAFE: bool = Mark::IS_THREAD_SAFE;
+    fn new<T>(value: T) -> Self::Pointer<T> {
+        alloc::<H>(size_of::<T>());
+        Wrap::new_wrapped(Mark::new(value))
+    }
+    fn from_box<T: ?Sized>(value: Box<T>) -> Self::Pointer<T> {
+        alloc::<H>(size_of_val(&*value));
+        Wrap::new_wrapped(Mark::from_box(value))
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Self::Pointer<[T]> {
+        alloc::<H>(size_of_val(&*vec));
+        Wrap::new_wrapped(Mark::new_slice(vec))
+    }
+    fn new_slice_clone<T: Clone>(slice: &[T]) -> Self::Pointer<[T]> {
+        alloc::<H>(size_of_val(slice));
+        Wrap::new_wrapped(Mark::new_slice_clone(slice))
+    }
+    fn new_str(s: &str) -> Self::Pointer<str> {
+        alloc::<H>(s.len());
+        Wrap::new_wrapped(Mark::new_str(s))
+    }
+    fn from_string(s: String) -> Self::Pointer<str> {
+        alloc::<H>(s.len());
+        Wrap::new_wrapped(Mark::from_string(s))
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Self::Pointer<[T]> {
+        // Collected first, to know the size.
+        Self::new_slice(iter.into_iter().collect())
+    }
+    fn unsize_array<T, const N: usize>(
+        this: Wrap<Mark, H, [T; N], Tag>,
+    ) -> Wrap<Mark, H, [T], Tag> {
+        Wrap::from_pointer(Mark::unsize_array(Wrap::into_pointer(this)))
+    }
+}
+
+impl<Mark: FromUnsized<U>, H: WrapHooks, U: ?Sized, Tag> FromUnsized<U> for WrapMark<Mark, H, Tag> {
+    fn from_unsized(value: &U) -> Wrap<Mark, H, U, Tag> {
+        alloc::<H>(size_of_val(value));
+        Wrap::new_wrapped(Mark::from_unsized(value))
+    }
+}
+
+impl<Mark: TryNewFamily, H: WrapHooks, Tag> TryNewFamily for WrapMark<Mark, H, Tag> {
+    fn try_new<T>(value: T) -> Result<Wrap<Mark, H, T, Tag>, T> {
+        if !H::on_alloc(size_of::<T>()) {
+            return Err(value);
+        }
+        Mark::try_new(value).map(Wrap::new_wrapped)
+    }
+}
+
+impl<Mark: TryCyclicFamily, H: WrapHooks, Tag> TryCyclicFamily for WrapMark<Mark, H, Tag> {
+    fn try_new_cyclic<T, F, E>(data_fn: F) -> Result<Wrap<Mark, H, T, Tag>, E>
+    where
+        F: FnOnce(&WrapWeak<Mark, H, T, Tag>) -> Result<T, E>,
+    {
+        alloc::<H>(size_of::<T>());
+        Mark::try_new_cyclic(|weak| Wrap::start_cyclic(weak, data_fn)).map(Wrap::end_cyclic)
+    }
+}
+
+impl<Mark: UninitConstruct, H: WrapHooks, Tag> UninitConstruct for WrapMark<Mark, H, Tag> {
+    fn new_uninit<T>() -> Wrap<Mark, H, MaybeUninit<T>, Tag> {
+        alloc::<H>(size_of::<T>());
+        Wrap::new_wrapped(Mark::new_uninit())
+    }
+
+    fn new_uninit_slice<T>(len: usize) -> Wrap<Mark, H, [MaybeUninit<T>], Tag> {
+        alloc::<H>(size_of::<T>() * len);
+        Wrap::new_wrapped(Mark::new_uninit_slice(len))
+    }
+
+    fn new_zeroed<T>() -> Wrap<Mark, H, MaybeUninit<T>, Tag> {
+        alloc::<H>(size_of::<T>());
+        Wrap::new_wrapped(Mark::new_zeroed())
+    }
+
+    fn new_zeroed_slice<T>(len: usize) -> Wrap<Mark, H, [MaybeUninit<T>], Tag> {
+        alloc::<H>(size_of::<T>() * len);
+        Wrap::new_wrapped(Mark::new_zeroed_slice(len))
+    }
+
+    unsafe fn assume_init<T>(this: Wrap<Mark, H, MaybeUninit<T>, Tag>) -> Wrap<Mark, H, T, Tag> {
+        Wrap::from_pointer(Mark::assume_init(Wrap::into_pointer(this)))
+    }
+
+    unsafe fn assume_init_slice<T>(
+        this: Wrap<Mark, H, [MaybeUninit<T>], Tag>,
+    ) -> Wrap<Mark, H, [T], Tag> {
+        Wrap::from_pointer(Mark::assume_init_slice(Wrap::into_pointer(this)))
+    }
+}
+
+impl<Mark: LockFamily, H: WrapHooks> LockFamily for WrapMark<Mark, H> {
+    type Lock<T> = Mark::Lock<T>;
+    type ReadGuard<'a, T: 'a> = Mark::ReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = Mark::WriteGuard<'a, T>;
+
+    fn new_lock<T>(value: T) -> Mark::Lock<T> {
+        Mark::new_lock(value)
+    }
+
+    fn read<T>(lock: &Mark::Lock<T>) -> Mark::ReadGuard<'_, T> {
+        Mark::read(lock)
+    }
+
+    fn write<T>(lock: &Mark::Lock<T>) -> Mark::WriteGuard<'_, T> {
+        Mark::write(lock)
+    }
+
+    fn try_read<T>(lock: &Mark::Lock<T>) -> Option<Mark::ReadGuard<'_, T>> {
+        Mark::try_read(lock)
+    }
+
+    fn try_write<T>(lock: &Mark::Lock<T>) -> Option<Mark::WriteGuard<'_, T>> {
+        Mark::try_write(lock)
+    }
+
+    fn lock_get_mut<T>(lock: &mut Mark::Lock<T>) -> &mut T {
+        Mark::lock_get_mut(lock)
+    }
+
+    fn lock_into_inner<T>(lock: Mark::Lock<T>) -> T {
+        Mark::lock_into_inner(lock)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Wrap<Mark, H, T, Tag> {
+    /// Wraps a freshly allocated pointer, calling [WrapHooks::on_new].
+    fn new_wrapped(inner: Mark::Pointer<T>) -> Self {
+        let addr = address(Mark::Pointer::as_ptr(&inner));
+        let counts = RefCounts::of(&inner);
+        H::on_new(addr, counts);
+        H::on_type_name(addr, type_name::<T>(), counts);
+        Self::from_pointer(inner)
+    }
+
+    /// Wraps a pointer without calling any hook.
+    pub(crate) fn from_pointer(inner: Mark::Pointer<T>) -> Self {
+        Self(ManuallyDrop::new(inner), PhantomData)
+    }
+
+    /// Moves the inner pointer out without calling [WrapHooks::on_drop].
+    pub(crate) fn into_pointer(this: Self) -> Mark::Pointer<T> {
+        let this = ManuallyDrop::new(this);
+        // SAFETY: `this` is never dropped, so the pointer is moved out exactly once.
+        unsafe { ptr::read(&*this.0) }
+    }
+
+    fn addr(this: &Self) -> *const () {
+        address(Mark::Pointer::as_ptr(&this.0))
+    }
+
+    fn counts(this: &Self) -> RefCounts {
+        RefCounts::of(&*this.0)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T, Tag> Wrap<Mark, H, T, Tag> {
+    /// Calls the hooks of a cyclic construction starting with `weak`, then
+    /// `data_fn` with a wrapped clone of it.
+    fn start_cyclic<R>(
+        weak: &Mark::WeakPointer<T>,
+        data_fn: impl FnOnce(&WrapWeak<Mark, H, T, Tag>) -> R,
+    ) -> R {
+        // Only the weak pointer of new_cyclic exists, which the counts of
+        // the family do not report before the first strong pointer.
+        let counts = RefCounts { strong: 0, weak: 1 };
+        H::on_cyclic_start(address(weak.as_ptr()), counts);
+        H::on_type_name(address(weak.as_ptr()), type_name::<T>(), counts);
+        let weak = Unhooked(ManuallyDrop::new(WrapWeak::from_pointer(weak.clone())));
+        data_fn(&weak.0)
+    }
+
+    /// Wraps the first strong pointer of a cyclic construction.
+    fn end_cyclic(inner: Mark::Pointer<T>) -> Self {
+        let this = Self::from_pointer(inner);
+        H::on_cyclic_end(Self::addr(&this), Self::counts(&this));
+        this
+    }
+}
+
+/// The weak pointer given to the data function of a cyclic construction. It
+/// belongs to the construction, its clone must not call the hooks, even when the
+/// data function unwinds.
+struct Unhooked<Mark: RefCountFamily, H: WrapHooks, T, Tag>(
+    ManuallyDrop<WrapWeak<Mark, H, T, Tag>>,
+);
+
+impl<Mark: RefCountFamily, H: WrapHooks, T, Tag> Drop for Unhooked<Mark, H, T, Tag> {
+    fn drop(&mut self) {
+        // SAFETY: the weak pointer is not used after this.
+        let weak = unsafe { ManuallyDrop::take(&mut self.0) };
+        drop(WrapWeak::into_pointer(weak));
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> WrapWeak<Mark, H, T, Tag> {
+    fn from_pointer(inner: Mark::WeakPointer<T>) -> Self {
+        Self(ManuallyDrop::new(inner), PhantomData)
+    }
+
+    fn into_pointer(this: Self) -> Mark::WeakPointer<T> {
+        let this = ManuallyDrop::new(this);
+        // SAFETY: `this` is never dropped, so the pointer is moved out exactly once.
+        unsafe { ptr::read(&*this.0) }
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Deref for Wrap<Mark, H, T, Tag> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + fmt::Debug, Tag> fmt::Debug
+    for Wrap<Mark, H, T, Tag>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + fmt::Display, Tag> fmt::Display
+    for Wrap<Mark, H, T, Tag>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> fmt::Pointer for Wrap<Mark, H, T, Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&*self.0, f)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + PartialEq, Tag> PartialEq
+    for Wrap<Mark, H, T, Tag>
+{
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + Eq, Tag> Eq for Wrap<Mark, H, T, Tag> {}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + PartialOrd, Tag> PartialOrd
+    for Wrap<Mark, H, T, Tag>
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + Ord, Tag> Ord for Wrap<Mark, H, T, Tag> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized + Hash, Tag> Hash for Wrap<Mark, H, T, Tag> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        (**self).hash(state)
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Borrow<T> for Wrap<Mark, H, T, Tag> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> AsRef<T> for Wrap<Mark, H, T, Tag> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Clone for Wrap<Mark, H, T, Tag> {
+    fn clone(&self) -> Self {
+        let clone = Self::from_pointer((*self.0).clone());
+        H::on_clone(Self::addr(self), Self::counts(&clone));
+        clone
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Drop for Wrap<Mark, H, T, Tag> {
+    fn drop(&mut self) {
+        H::on_drop(Self::addr(self), Self::counts(self).without_strong());
+        // SAFETY: the pointer is not used after this.
+        unsafe { ManuallyDrop::drop(&mut self.0) }
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Clone for WrapWeak<Mark, H, T, Tag> {
+    fn clone(&self) -> Self {
+        let clone = Self::from_pointer((*self.0).clone());
+        H::on_clone_weak(address(self.0.as_ptr()), RefCounts::of_weak(&*clone.0));
+        clone
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> Drop for WrapWeak<Mark, H, T, Tag> {
+    fn drop(&mut self) {
+        let counts = RefCounts::of_weak(&*self.0);
+        let counts = RefCounts {
+            weak: counts.weak.saturating_sub(1),
+            ..counts
+        };
+        H::on_drop_weak(address(self.0.as_ptr()), counts);
+        // SAFETY: the pointer is not used after this.
+        unsafe { ManuallyDrop::drop(&mut self.0) }
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> RefCounted<T> for Wrap<Mark, H, T, Tag> {
+    type Mark = WrapMark<Mark, H, Tag>;
+    type WeakPointer = WrapWeak<Mark, H, T, Tag>;
+
+    fn as_ptr(this: &Self) -> *const T {
+        Mark::Pointer::as_ptr(&this.0)
+    }
+
+    fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&this.0, &other.0)
+    }
+
+    fn downgrade(this: &Self) -> WrapWeak<Mark, H, T, Tag> {
+        let weak = WrapWeak::from_pointer(Mark::Pointer::downgrade(&this.0));
+        H::on_downgrade(Self::addr(this), Self::counts(this));
+        weak
+    }
+
+    fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        let (before, counts) = (Self::addr(this), Self::counts(this));
+        Mark::Pointer::make_mut(&mut this.0);
+        let after = Self::addr(this);
+        if before != after {
+            // The value was moved or cloned into a new allocation.
+            H::on_drop(before, counts.without_strong());
+            let counts = Self::counts(this);
+            H::on_new(after, counts);
+            H::on_type_name(after, type_name::<T>(), counts);
+        }
+        Mark::Pointer::make_mut(&mut this.0)
+    }
+
+    unsafe fn increment_strong_count(ptr: *const T) {
+        Mark::Pointer::increment_strong_count(ptr);
+        // SAFETY: the caller owns a strong count, which is not dropped.
+        let inner = ManuallyDrop::new(Mark::Pointer::from_raw(ptr));
+        H::on_clone(address(ptr), RefCounts::of(&*inner));
+    }
+
+    unsafe fn decrement_strong_count(ptr: *const T) {
+        // SAFETY: the caller owns a strong count, which is not dropped here.
+        let inner = ManuallyDrop::new(Mark::Pointer::from_raw(ptr));
+        H::on_drop(address(ptr), RefCounts::of(&*inner).without_strong());
+        Mark::Pointer::decrement_strong_count(ptr)
+    }
+
+    fn into_raw(this: Self) -> *const T {
+        Mark::Pointer::into_raw(Self::into_pointer(this))
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        Self::from_pointer(Mark::Pointer::from_raw(ptr))
+    }
+
+    fn strong_count(this: &Self) -> usize {
+        Mark::Pointer::strong_count(&this.0)
+    }
+
+    fn weak_count(this: &Self) -> usize {
+        Mark::Pointer::weak_count(&this.0)
+    }
+
+    fn get_mut(this: &mut Self) -> Option<&mut T> {
+        Mark::Pointer::get_mut(&mut this.0)
+    }
+
+    unsafe fn get_mut_unchecked(this: &mut Self) -> &mut T {
+        Mark::Pointer::get_mut_unchecked(&mut this.0)
+    }
+
+    fn new_cyclic<F>(data_fn: F) -> Self
+    where
+        F: FnOnce(&WrapWeak<Mark, H, T, Tag>) -> T,
+        T: Sized,
+    {
+        alloc::<H>(size_of::<T>());
+        let inner = Mark::Pointer::new_cyclic(|weak| Self::start_cyclic(weak, data_fn));
+        Self::end_cyclic(inner)
+    }
+
+    fn pin(value: T) -> Pin<Self>
+    where
+        T: Sized,
+    {
+        // SAFETY: the value lives in a reference counted allocation and is never
+        // moved out of it through a pinned pointer.
+        unsafe { Pin::new_unchecked(WrapMark::<Mark, H, Tag>::new(value)) }
+    }
+
+    fn try_unwrap(this: Self) -> Result<T, Self>
+    where
+        T: Sized,
+    {
+        let (addr, counts) = (Self::addr(&this), Self::counts(&this));
+        match Mark::Pointer::try_unwrap(Self::into_pointer(this)) {
+            Ok(value) => {
+                H::on_drop(addr, counts.without_strong());
+                Ok(value)
+            }
+            Err(inner) => Err(Self::from_pointer(inner)),
+        }
+    }
+
+    fn into_inner(this: Self) -> Option<T>
+    where
+        T: Sized,
+    {
+        H::on_drop(Self::addr(&this), Self::counts(&this).without_strong());
+        Mark::Pointer::into_inner(Self::into_pointer(this))
+    }
+
+    fn unwrap_or_clone(this: Self) -> T
+    where
+        T: Clone,
+    {
+        H::on_drop(Self::addr(&this), Self::counts(&this).without_strong());
+        Mark::Pointer::unwrap_or_clone(Self::into_pointer(this))
+    }
+}
+
+impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> WeakPointer<T>
+    for WrapWeak<Mark, H, T, Tag>
+{
+    type StrongMark = WrapMark<Mark, H, Tag>;
+    type StrongPointer = Wrap<Mark, H, T, Tag>;
+
+    fn new() -> Self
+    where
+        T: Sized,
+    {
+        Self::from_pointer(Mark::WeakPointer::new())
+    }
+
+    fn as_ptr(&self) -> *const T {
+        self.0.as_ptr()
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        Self::from_pointer(Mark::WeakPointer::from_raw(ptr))
+    }
+
+    fn into_raw(self) -> *const T {
+        Self::into_pointer(self).into_raw()
+    }
+
+    fn upgrade(&self) -> Option<Wrap<Mark, H, T, Tag>> {
+        let upgraded = self.0.upgrade();
+        let counts = match &upgraded {
+            Some(pointer) => RefCounts::of(pointer),
+            None => RefCounts::of_weak(&*self.0),
+        };
+        H::on_upgrade(address(self.as_ptr()), upgraded.is_some(), counts);
+        upgraded.map(Wrap::from_pointer)
+    }
+
+    fn strong_count(&self) -> usize {
+        self.0.strong_count()
+    }
+
+    fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+
+    fn weak_count(&self) -> usize {
+        self.0.weak_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::cell::Cell;
+
+    thread_local! {
+        static LIVE: Cell<isize> = const { Cell::new(0) };
+        static LAST: Cell<RefCounts> = Cell::default();
+    }
+
+    /// Counts the strong pointers alive on this thread.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct CountLive;
+
+    impl WrapHooks for CountLive {
+        fn on_new(_addr: *const (), _counts: RefCounts) {
+            LIVE.with(|live| live.set(live.get() + 1));
+        }
+        fn on_cyclic_end(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_clone(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_upgrade(addr: *const (), upgraded: bool, counts: RefCounts) {
+            if upgraded {
+                Self::on_new(addr, counts)
+            }
+        }
+        fn on_drop(_addr: *const (), _counts: RefCounts) {
+            LIVE.with(|live| live.set(live.get() - 1));
+        }
+    }
+
+    #[test]
+    fn test_wrap_hooks() {
+        fn actual_test<Mark: RefCountFamily>() {
+            struct Node<Mark: RefCountFamily> {
+                me: <WrapMark<Mark, CountLive> as RefCountFamily>::WeakPointer<Self>,
+            }
+            let live = || LIVE.with(Cell::get);
+
+            let a = WrapMark::<Mark, CountLive>::new(1);
+            let b = a.clone();
+            let weak = Wrap::downgrade(&b);
+            assert_eq!(live(), 2);
+            let c = weak.upgrade().unwrap();
+            assert_eq!(live(), 3);
+            assert_eq!(Wrap::into_inner(a), None);
+            drop((b, c));
+            assert_eq!(live(), 0);
+            let node =
+                Wrap::<Mark, CountLive, Node<Mark>>::new_cyclic(|me| Node { me: me.clone() });
+            assert_eq!(live(), 1);
+            let other = node.me.upgrade().unwrap();
+            let node = Wrap::try_unwrap(node).err().unwrap();
+            assert_eq!(live(), 2);
+            drop((node, other));
+            assert_eq!(live(), 0);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_wrap_try_new_cyclic() {
+        fn actual_test<Mark: TryCyclicFamily>() {
+            struct Node<Mark: RefCountFamily> {
+                me: <WrapMark<Mark, CountLive> as RefCountFamily>::WeakPointer<Self>,
+            }
+            let live = || LIVE.with(Cell::get);
+
+            let node = WrapMark::<Mark, CountLive>::try_new_cyclic(|me| {
+                Ok::<_, ()>(Node::<Mark> { me: me.clone() })
+            })
+            .ok()
+            .unwrap();
+            assert_eq!(live(), 1);
+            assert!(node.me.upgrade().is_some());
+            drop(node);
+            assert_eq!(live(), 0);
+
+            let mut leaked = None;
+            let failed = WrapMark::<Mark, CountLive>::try_new_cyclic(|me| {
+                leaked = Some(me.clone());
+                Err::<Node<Mark>, _>("failed")
+            });
+            assert_eq!((failed.err(), live()), (Some("failed"), 0));
+            assert!(leaked.unwrap().upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        #[cfg(feature = "nightly")]
+        actual_test::<ArcMark>();
+    }
+
+    /// Keeps the counts given to the last hook on this thread.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct LastCounts;
+
+    impl WrapHooks for LastCounts {
+        fn on_new(_addr: *const (), counts: RefCounts) {
+            LAST.with(|last| last.set(counts));
+        }
+        fn on_clone(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_drop(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_downgrade(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_drop_weak(addr: *const (), counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+        fn on_upgrade(addr: *const (), _upgraded: bool, counts: RefCounts) {
+            Self::on_new(addr, counts)
+        }
+    }
+
+    #[test]
+    fn test_wrap_hooks_counts() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let last = || {
+                let RefCounts { strong, weak } = LAST.with(Cell::get);
+                (strong, weak)
+            };
+            let a = WrapMark::<Mark, LastCounts>::new(1);
+            assert_eq!(last(), (1, 0));
+            let b = a.clone();
+            assert_eq!(last(), (2, 0));
+            let weak = Wrap::downgrade(&a);
+            assert_eq!(last(), (2, 1));
+            drop(a);
+            assert_eq!(last(), (1, 1));
+            let c = weak.upgrade().unwrap();
+            assert_eq!(last(), (2, 1));
+            drop((b, c));
+            assert_eq!(last(), (0, 1));
+            drop(weak);
+            assert_eq!(last(), (0, 0));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{ArcMark, RcMark};
+use std::{alloc::Allocator, ops::Deref, rc, sync};
+
+/// The allocator aware counterpart of [crate::RefCountFamily].
+///
+/// The pointer types are parameterized by their allocator, so that both the
+/// choice of reference counting and the allocator can be left to the library user.
+///
+/// ```
+/// #![feature(allocator_api)]
+/// # use cark_ref_counted::*;
+/// # use std::alloc::{Allocator, System};
+/// struct Foo<R: RefCountFamilyIn, A: Allocator + Clone> {
+///     name: R::Pointer<String, A>,
+/// }
+/// let foo = Foo::<RcMark, _> {
+///     name: RcMark::new_in("John Doe".to_owned(), System),
+/// };
+/// assert_eq!(*foo.name, "John Doe");
+/// ```
+pub trait RefCountFamilyIn {
+    type Pointer<T: ?Sized, A: Allocator + Clone>: Deref<Target = T> + Clone;
+    type WeakPointer<T: ?Sized, A: Allocator + Clone>: Clone;
+    fn new_in<T, A: Allocator + Clone>(value: T, alloc: A) -> Self::Pointer<T, A>;
+    fn downgrade_in<T: ?Sized, A: Allocator + Clone>(
+        this: &Self::Pointer<T, A>,
+    ) -> Self::WeakPointer<T, A>;
+    fn upgrade_in<T: ?Sized, A: Allocator + Clone>(
+        weak: &Self::WeakPointer<T, A>,
+    ) -> Option<Self::Pointer<T, A>>;
+    fn allocator<T: ?Sized, A: Allocator + Clone>(this: &Self::Pointer<T, A>) -> &A;
+}
+
+impl RefCountFamilyIn for RcMark {
+    type Pointer<T: ?Sized, A: Allocator + Clone> = rc::Rc<T, A>;
+    type WeakPointer<T: ?Sized, A: Allocator + Clone> = rc::Weak<T, A>;
+
+    fn new_in<T, A: Allocator + Clone>(value: T, alloc: A) -> rc::Rc<T, A> {
+        rc::Rc::new_in(value, alloc)
+    }
+
+    fn downgrade_in<T: ?Sized, A: Allocator + Clone>(this: &rc::Rc<T, A>) -> rc::Weak<T, A> {
+        rc::Rc::downgrade(this)
+    }
+
+    fn upgrade_in<T: ?Sized, A: Allocator + Clone>(weak: &rc::Weak<T, A>) -> Option<rc::Rc<T, A>> {
+        weak.upgrade()
+    }
+
+    fn allocator<T: ?Sized, A: Allocator + Clone>(this: &rc::Rc<T, A>) -> &A {
+        rc::Rc::allocator(this)
+    }
+}
+
+impl RefCountFamilyIn for ArcMark {
+    type Pointer<T: ?Sized, A: Allocator + Clone> = sync::Arc<T, A>;
+    type WeakPointer<T: ?Sized, A: Allocator + Clone> = sync::Weak<T, A>;
+
+    fn new_in<T, A: Allocator + Clone>(value: T, alloc: A) -> sync::Arc<T, A> {
+        sync::Arc::new_in(value, alloc)
+    }
+
+    fn downgrade_in<T: ?Sized, A: Allocator + Clone>(this: &sync::Arc<T, A>) -> sync::Weak<T, A> {
+        sync::Arc::downgrade(this)
+    }
+
+    fn upgrade_in<T: ?Sized, A: Allocator + Clone>(
+        weak: &sync::Weak<T, A>,
+    ) -> Option<sync::Arc<T, A>> {
+        weak.upgrade()
+    }
+
+    fn allocator<T: ?Sized, A: Allocator + Clone>(this: &sync::Arc<T, A>) -> &A {
+        sync::Arc::allocator(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::System;
+
+    #[test]
+    fn test_new_in() {
+        fn actual_test<R: RefCountFamilyIn>() {
+            let x = R::new_in(3, System);
+            let weak = R::downgrade_in(&x);
+            let _: &System = R::allocator(&x);
+            assert_eq!(R::upgrade_in(&weak).as_deref(), Some(&3));
+            drop(x);
+            assert!(R::upgrade_in(&weak).is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,255 @@
+use crate::Downcast;
+use std::{
+    any::Any,
+    rc::{self, Rc},
+    sync::{self, Arc},
+};
+
+/// The object safe part of [crate::RefCounted], for pointers whose family is
+/// only known at runtime.
+///
+/// Its methods would shadow the ones of the pointed values, so it is not
+/// exported from the crate root: import it from this module where needed.
+pub trait DynRefCounted {
+    fn strong_count(&self) -> usize;
+    fn weak_count(&self) -> usize;
+    /// The address of the pointed value, without any fat pointer metadata.
+    fn addr(&self) -> *const ();
+    /// Whether the counts are atomic. See [crate::RefCountFamily::IS_THREAD_SAFE].
+    fn is_thread_safe(&self) -> bool;
+}
+
+impl<T: ?Sized> DynRefCounted for Rc<T> {
+    fn strong_count(&self) -> usize {
+        Rc::strong_count(self)
+    }
+
+    fn weak_count(&self) -> usize {
+        Rc::weak_count(self)
+    }
+
+    fn addr(&self) -> *const () {
+        Rc::as_ptr(self) as *const ()
+    }
+
+    fn is_thread_safe(&self) -> bool {
+        false
+    }
+}
+
+impl<T: ?Sized> DynRefCounted for Arc<T> {
+    fn strong_count(&self) -> usize {
+        Arc::strong_count(self)
+    }
+
+    fn weak_count(&self) -> usize {
+        Arc::weak_count(self)
+    }
+
+    fn addr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn is_thread_safe(&self) -> bool {
+        true
+    }
+}
+
+/// A type erased shared value, held by either an [Rc] or an [Arc].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn make(threaded: bool) -> AnyShared {
+///     if threaded {
+///         AnyShared::new_arc(5_i32)
+///     } else {
+///         AnyShared::new_rc(5_i32)
+///     }
+/// }
+/// let value = make(true);
+/// assert!(value.is_thread_safe());
+/// assert_eq!(value.downcast_ref::<i32>(), Some(&5));
+/// let weak = value.downgrade();
+/// assert_eq!(value.strong_count(), 1);
+/// assert!(weak.upgrade().is_some());
+/// let value = value.downcast::<std::rc::Rc<i32>>().err().unwrap();
+/// assert_eq!(*value.downcast::<std::sync::Arc<i32>>().ok().unwrap(), 5);
+/// ```
+#[derive(Clone)]
+pub enum AnyShared {
+    Rc(Rc<dyn Any>),
+    Arc(Arc<dyn Any + Send + Sync>),
+}
+
+/// The weak counterpart of [AnyShared].
+#[derive(Clone)]
+pub enum AnyWeak {
+    Rc(rc::Weak<dyn Any>),
+    Arc(sync::Weak<dyn Any + Send + Sync>),
+}
+
+impl AnyShared {
+    pub fn new_rc<T: Any>(value: T) -> Self {
+        Self::Rc(Rc::new(value))
+    }
+
+    pub fn new_arc<T: Any + Send + Sync>(value: T) -> Self {
+        Self::Arc(Arc::new(value))
+    }
+
+    pub fn downgrade(&self) -> AnyWeak {
+        match self {
+            Self::Rc(p) => AnyWeak::Rc(Rc::downgrade(p)),
+            Self::Arc(p) => AnyWeak::Arc(Arc::downgrade(p)),
+        }
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.downcast_ref::<T>().is_some()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            Self::Rc(p) => (**p).downcast_ref(),
+            Self::Arc(p) => (**p).downcast_ref(),
+        }
+    }
+
+    /// Returns the pointer to the value if it is a `Rc<T>` or an `Arc<T>` as asked,
+    /// or gives back `self`.
+    pub fn downcast<P: FromAnyShared>(self) -> Result<P, Self> {
+        P::from_any_shared(self)
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.is_thread_safe() == other.is_thread_safe() && self.addr() == other.addr()
+    }
+
+    pub fn strong_count(&self) -> usize {
+        match self {
+            Self::Rc(p) => Rc::strong_count(p),
+            Self::Arc(p) => Arc::strong_count(p),
+        }
+    }
+
+    pub fn weak_count(&self) -> usize {
+        match self {
+            Self::Rc(p) => Rc::weak_count(p),
+            Self::Arc(p) => Arc::weak_count(p),
+        }
+    }
+
+    /// The address of the value. See [DynRefCounted::addr].
+    pub fn addr(&self) -> *const () {
+        match self {
+            Self::Rc(p) => Rc::as_ptr(p) as *const (),
+            Self::Arc(p) => Arc::as_ptr(p) as *const (),
+        }
+    }
+
+    pub fn is_thread_safe(&self) -> bool {
+        matches!(self, Self::Arc(_))
+    }
+}
+
+impl DynRefCounted for AnyShared {
+    fn strong_count(&self) -> usize {
+        AnyShared::strong_count(self)
+    }
+
+    fn weak_count(&self) -> usize {
+        AnyShared::weak_count(self)
+    }
+
+    fn addr(&self) -> *const () {
+        AnyShared::addr(self)
+    }
+
+    fn is_thread_safe(&self) -> bool {
+        AnyShared::is_thread_safe(self)
+    }
+}
+
+/// The pointers [AnyShared::downcast] returns: `Rc<T>` and `Arc<T>`.
+pub trait FromAnyShared: Sized + sealed::Sealed {
+    fn from_any_shared(any: AnyShared) -> Result<Self, AnyShared>;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+impl<T: Any> sealed::Sealed for Rc<T> {}
+
+impl<T: Any> FromAnyShared for Rc<T> {
+    fn from_any_shared(any: AnyShared) -> Result<Self, AnyShared> {
+        match any {
+            AnyShared::Rc(p) => Downcast::downcast(p).map_err(AnyShared::Rc),
+            arc => Err(arc),
+        }
+    }
+}
+
+impl<T: Any> sealed::Sealed for Arc<T> {}
+
+impl<T: Any> FromAnyShared for Arc<T> {
+    fn from_any_shared(any: AnyShared) -> Result<Self, AnyShared> {
+        match any {
+            AnyShared::Arc(p) => Downcast::downcast(p).map_err(AnyShared::Arc),
+            rc => Err(rc),
+        }
+    }
+}
+
+impl AnyWeak {
+    pub fn upgrade(&self) -> Option<AnyShared> {
+        match self {
+            Self::Rc(w) => w.upgrade().map(AnyShared::Rc),
+            Self::Arc(w) => w.upgrade().map(AnyShared::Arc),
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        match self {
+            Self::Rc(w) => w.strong_count(),
+            Self::Arc(w) => w.strong_count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_shared() {
+        for value in [AnyShared::new_rc(3_i32), AnyShared::new_arc(3_i32)] {
+            let other = value.clone();
+            assert!(value.ptr_eq(&other));
+            assert_eq!(value.strong_count(), 2);
+            assert!(value.is::<i32>());
+            assert!(!value.is::<u32>());
+            let weak = value.downgrade();
+            assert_eq!(other.weak_count(), 1);
+            drop(other);
+            let threaded = value.is_thread_safe();
+            if threaded {
+                let value = value.downcast::<Rc<i32>>().unwrap_err();
+                assert_eq!(*value.downcast::<Arc<i32>>().ok().unwrap(), 3);
+            } else {
+                let value = value.downcast::<Rc<u32>>().unwrap_err();
+                assert_eq!(*value.downcast::<Rc<i32>>().ok().unwrap(), 3);
+            }
+            assert!(weak.upgrade().is_none());
+        }
+    }
+
+    #[test]
+    fn test_dyn_ref_counted() {
+        let pointers: Vec<Box<dyn DynRefCounted>> =
+            vec![Box::new(Rc::new(1)), Box::new(Arc::<str>::from("a"))];
+        assert!(!pointers[0].is_thread_safe());
+        assert!(pointers[1].is_thread_safe());
+        assert!(pointers.iter().all(|p| p.strong_count() == 1));
+    }
+}
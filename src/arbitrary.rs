@@ -0,0 +1,294 @@
+//! Generation of shared values for property tests, with
+//! [proptest](https://docs.rs/proptest) or [quickcheck](https://docs.rs/quickcheck).
+//!
+//! With the `proptest` and `quickcheck` features, [Shared] implements the
+//! `Arbitrary` trait of each framework when its value does, and so do:
+//! - [Aliased], a vector whose elements may be clones of earlier ones, to exercise
+//!   the paths where values are shared, like `make_mut` cloning them;
+//! - [SharedAndWeak], a shared value with a weak pointer downgraded from it.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! # #[cfg(feature = "proptest")] {
+//! use cark_ref_counted::arbitrary::Aliased;
+//! use proptest::prelude::*;
+//! proptest!(|(Aliased(mut values) in any::<Aliased<RcMark, u8>>())| {
+//!     let before: Vec<u8> = values.iter().map(|v| **v).collect();
+//!     if let Some(last) = values.last_mut() {
+//!         *last.make_mut() = 0;
+//!     }
+//!     // The aliases of the last value kept the old one.
+//!     let others = before.len().saturating_sub(1);
+//!     prop_assert!(values[..others].iter().zip(&before).all(|(v, b)| **v == *b));
+//! });
+//! # }
+//! ```
+
+use crate::{traits::*, Shared};
+use std::fmt;
+
+/// Shared values, some of which are clones of earlier ones in the vector.
+///
+/// When shrinking, the aliases turn into separate allocations.
+pub struct Aliased<Mark: RefCountFamily, T>(pub Vec<Shared<Mark, T>>);
+
+impl<Mark: RefCountFamily, T> Aliased<Mark, T> {
+    /// Returns, for each value, the index of the first value sharing its allocation.
+    pub fn aliases(&self) -> Vec<usize> {
+        self.0
+            .iter()
+            .map(|value| self.0.iter().position(|v| v.ptr_eq(value)).unwrap())
+            .collect()
+    }
+
+    /// Builds the vector from `(value, alias)` pairs: when `alias` is `Some(i)`
+    /// and `i` is lower than the index of the pair, the value is a clone of the
+    /// value at `i`.
+    fn from_pairs(pairs: impl IntoIterator<Item = (T, Option<usize>)>) -> Self {
+        let mut values: Vec<Shared<Mark, T>> = Vec::new();
+        for (value, alias) in pairs {
+            let value = match alias.and_then(|i| values.get(i)) {
+                Some(earlier) => earlier.clone(),
+                None => Shared::new(value),
+            };
+            values.push(value);
+        }
+        Self(values)
+    }
+}
+
+impl<Mark: RefCountFamily, T> Clone for Aliased<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: fmt::Debug> fmt::Debug for Aliased<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Aliased")
+            .field(&self.0)
+            .field(&self.aliases())
+            .finish()
+    }
+}
+
+/// A shared value with a weak pointer downgraded from it.
+pub struct SharedAndWeak<Mark: RefCountFamily, T: ?Sized>(
+    pub Shared<Mark, T>,
+    pub Mark::WeakPointer<T>,
+);
+
+impl<Mark: RefCountFamily, T: ?Sized> From<Shared<Mark, T>> for SharedAndWeak<Mark, T> {
+    fn from(shared: Shared<Mark, T>) -> Self {
+        let weak = shared.downgrade();
+        Self(shared, weak)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for SharedAndWeak<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + fmt::Debug> fmt::Debug for SharedAndWeak<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedAndWeak").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod proptest_impls {
+    use super::*;
+    use proptest::{
+        arbitrary::{any_with, Arbitrary},
+        collection::vec,
+        option,
+        sample::Index,
+        strategy::{BoxedStrategy, Strategy},
+    };
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary + 'static> Arbitrary for Shared<Mark, T> {
+        type Parameters = T::Parameters;
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(args: T::Parameters) -> Self::Strategy {
+            any_with::<T>(args).prop_map(Shared::new).boxed()
+        }
+    }
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary + 'static> Arbitrary for Aliased<Mark, T> {
+        type Parameters = T::Parameters;
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(args: T::Parameters) -> Self::Strategy {
+            let pair = (
+                any_with::<T>(args),
+                option::of(proptest::arbitrary::any::<Index>()),
+            );
+            vec(pair, 0..16)
+                .prop_map(|pairs| {
+                    let pairs = pairs.into_iter().enumerate().map(|(i, (value, alias))| {
+                        (value, alias.filter(|_| i > 0).map(|alias| alias.index(i)))
+                    });
+                    Aliased::from_pairs(pairs)
+                })
+                .boxed()
+        }
+    }
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary + 'static> Arbitrary for SharedAndWeak<Mark, T> {
+        type Parameters = T::Parameters;
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(args: T::Parameters) -> Self::Strategy {
+            any_with::<Shared<Mark, T>>(args)
+                .prop_map(SharedAndWeak::from)
+                .boxed()
+        }
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impls {
+    use super::*;
+    use quickcheck::{Arbitrary, Gen};
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary> Arbitrary for Shared<Mark, T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            Shared::new(T::arbitrary(g))
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            Box::new((**self).shrink().map(Shared::new))
+        }
+    }
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary> Arbitrary for Aliased<Mark, T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let len = usize::arbitrary(g) % (g.size() + 1);
+            let pairs: Vec<_> = (0..len)
+                .map(|i| {
+                    let alias = (i > 0 && bool::arbitrary(g)).then(|| usize::arbitrary(g) % i);
+                    (T::arbitrary(g), alias)
+                })
+                .collect();
+            Aliased::from_pairs(pairs)
+        }
+
+        /// Removes one value, or turns the aliases of a value into copies.
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            let this = self.clone();
+            let aliases = self.aliases();
+            let removed = (0..self.0.len()).map(move |i| {
+                let mut values = this.0.clone();
+                values.remove(i);
+                Aliased(values)
+            });
+            let this = self.clone();
+            let separated = (0..self.0.len())
+                .filter(move |&i| aliases[i] != i)
+                .map(move |i| {
+                    let mut values = this.0.clone();
+                    values[i] = Shared::new((*values[i]).clone());
+                    Aliased(values)
+                });
+            Box::new(removed.chain(separated))
+        }
+    }
+
+    impl<Mark: RefCountFamily + 'static, T: Arbitrary> Arbitrary for SharedAndWeak<Mark, T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            Shared::<Mark, T>::arbitrary(g).into()
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            Box::new(self.0.shrink().map(SharedAndWeak::from))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    /// Checks that `make_mut` only changes the written value, whatever the aliasing.
+    fn make_mut_is_local<Mark: RefCountFamily>(Aliased(mut values): Aliased<Mark, u8>) -> bool {
+        let before: Vec<u8> = values.iter().map(|v| **v).collect();
+        let Some(first) = values.first_mut() else {
+            return true;
+        };
+        *first.make_mut() = before[0].wrapping_add(1);
+        let unique = values[1..].iter().all(|v| !v.ptr_eq(&values[0]));
+        unique
+            && values[1..]
+                .iter()
+                .zip(&before[1..])
+                .all(|(after, before)| **after == *before)
+    }
+
+    /// The frameworks may keep clones of the pair, so the weak pointer is not
+    /// checked to expire.
+    fn weak_upgrades<Mark: RefCountFamily>(pair: SharedAndWeak<Mark, u8>) -> bool {
+        let SharedAndWeak(shared, weak) = pair;
+        weak.upgrade()
+            .is_some_and(|p| Mark::Pointer::ptr_eq(&p, shared.pointer()))
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn test_proptest() {
+        use proptest::{prelude::*, test_runner::TestRunner};
+        fn actual_test<Mark: RefCountFamily + 'static>() {
+            let mut runner = TestRunner::default();
+            runner
+                .run(&any::<Aliased<Mark, u8>>(), |values| {
+                    prop_assert!(make_mut_is_local(values));
+                    Ok(())
+                })
+                .unwrap();
+            runner
+                .run(&any::<SharedAndWeak<Mark, u8>>(), |pair| {
+                    prop_assert!(weak_upgrades(pair));
+                    Ok(())
+                })
+                .unwrap();
+            let mut aliased = false;
+            for _ in 0..64 {
+                let Aliased(values) = any::<Aliased<Mark, u8>>()
+                    .new_tree(&mut runner)
+                    .unwrap()
+                    .current();
+                aliased |= values
+                    .iter()
+                    .enumerate()
+                    .any(|(i, v)| values[..i].iter().any(|w| w.ptr_eq(v)));
+            }
+            assert!(aliased);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[cfg(feature = "quickcheck")]
+    #[test]
+    fn test_quickcheck() {
+        use quickcheck::{Arbitrary, Gen, QuickCheck};
+        fn actual_test<Mark: RefCountFamily + 'static>() {
+            QuickCheck::new().quickcheck(make_mut_is_local::<Mark> as fn(_) -> bool);
+            QuickCheck::new().quickcheck(weak_upgrades::<Mark> as fn(_) -> bool);
+            let values = Aliased::<Mark, u8>::from_pairs([(1, None), (2, Some(0)), (3, None)]);
+            assert_eq!(values.aliases(), [0, 0, 2]);
+            assert!(values.shrink().any(|shrunk| shrunk.aliases() == [0, 1, 2]));
+            let mut g = Gen::new(16);
+            assert!((0..64).any(|_| Aliased::<Mark, u8>::arbitrary(&mut g)
+                .aliases()
+                .iter()
+                .enumerate()
+                .any(|(i, &a)| a != i)));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,157 @@
+use crate::traits::*;
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// A strong pointer compared, ordered and hashed by the address of its allocation.
+///
+/// Two `ByAddress` are equal when they point to the same allocation, whatever the
+/// pointed values. Fat pointer metadata is ignored. The address is the one of the
+/// dereferenced value, which for [RefCounted] pointers is [RefCounted::as_ptr].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::collections::HashMap;
+/// fn count<R: RefCountFamily>() -> usize {
+///     let a = R::new(1);
+///     let mut visits = HashMap::new();
+///     for pointer in [a.clone(), R::new(1), a] {
+///         *visits.entry(ByAddress(pointer)).or_insert(0) += 1;
+///     }
+///     visits.len()
+/// }
+/// assert_eq!(count::<RcMark>(), 2);
+/// ```
+#[derive(Clone, Copy)]
+pub struct ByAddress<P>(pub P);
+
+/// The weak counterpart of [ByAddress], for `Mark::WeakPointer<T>`.
+///
+/// Weak pointers keep their allocation, though not their value, so the address
+/// stays unique for as long as the key lives.
+pub struct WeakByAddress<Mark: RefCountFamily, T: ?Sized>(pub Mark::WeakPointer<T>);
+
+impl<P: Deref> ByAddress<P> {
+    fn addr(&self) -> *const () {
+        &*self.0 as *const P::Target as *const ()
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> WeakByAddress<Mark, T> {
+    fn addr(&self) -> *const () {
+        self.0.as_ptr() as *const ()
+    }
+}
+
+impl<P> Deref for ByAddress<P> {
+    type Target = P;
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P: Deref> PartialEq for ByAddress<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<P: Deref> Eq for ByAddress<P> {}
+
+impl<P: Deref> PartialOrd for ByAddress<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Deref> Ord for ByAddress<P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl<P: Deref> Hash for ByAddress<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+impl<P: Deref> fmt::Debug for ByAddress<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ByAddress").field(&self.addr()).finish()
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for WeakByAddress<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> PartialEq for WeakByAddress<Mark, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Eq for WeakByAddress<Mark, T> {}
+
+impl<Mark: RefCountFamily, T: ?Sized> PartialOrd for WeakByAddress<Mark, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Ord for WeakByAddress<Mark, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Hash for WeakByAddress<Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> fmt::Debug for WeakByAddress<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WeakByAddress").field(&self.addr()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::collections::{BTreeSet, HashSet};
+
+    #[test]
+    fn test_by_address() {
+        fn actual_test<R: RefCountFamily>() {
+            let a = R::new_str("same");
+            let b = R::new_str("same");
+            assert_ne!(ByAddress(a.clone()), ByAddress(b.clone()));
+            assert_eq!(ByAddress(a.clone()), ByAddress(a.clone()));
+            let set: HashSet<_> = [a.clone(), b.clone(), a.clone()]
+                .into_iter()
+                .map(ByAddress)
+                .collect();
+            assert_eq!(set.len(), 2);
+            let weak: BTreeSet<WeakByAddress<R, str>> = [&a, &b, &a]
+                .into_iter()
+                .map(|p| WeakByAddress(R::Pointer::downgrade(p)))
+                .collect();
+            assert_eq!(weak.len(), 2);
+            drop(set);
+            drop(a);
+            let weak_a = WeakByAddress::<R, str>(R::Pointer::downgrade(&b));
+            assert!(weak.contains(&weak_a));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,574 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+};
+
+/// Chooses the value a [Cache] evicts when it is full.
+///
+/// The cache tells the policy about each key it inserts, reads, removes and
+/// evicts, and asks it for a victim among the keys it holds.
+///
+/// [Lru] and [Lfu] are provided. Adaptive policies such as ARC or 2Q are not, but
+/// can be written against this trait, keeping the keys given to
+/// [EvictionPolicy::on_evict] in their ghost lists.
+pub trait EvictionPolicy<K>: Default {
+    /// `key` was inserted into the cache.
+    fn on_insert(&mut self, key: &K);
+    /// The value of `key` was read with [Cache::get].
+    fn on_access(&mut self, key: &K);
+    /// `key` was removed from the cache with [Cache::remove], or replaced by
+    /// another value.
+    fn on_remove(&mut self, key: &K);
+    /// `key`, the victim, was evicted to make room for another key. Handled like a
+    /// removal by default.
+    fn on_evict(&mut self, key: &K) {
+        self.on_remove(key)
+    }
+    /// Returns the key to evict next.
+    fn victim(&self) -> Option<&K>;
+    /// Returns the keys from the next to be evicted to the last one.
+    fn eviction_order<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a;
+    fn clear(&mut self);
+}
+
+/// The [EvictionPolicy] evicting the least recently used value first.
+pub struct Lru<K> {
+    ticks: HashMap<K, u64>,
+    /// The keys by the tick of their last use.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K> Default for Lru<K> {
+    fn default() -> Self {
+        Self {
+            ticks: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> EvictionPolicy<K> for Lru<K> {
+    fn on_insert(&mut self, key: &K) {
+        self.on_access(key)
+    }
+
+    fn on_access(&mut self, key: &K) {
+        self.tick += 1;
+        if let Some(tick) = self.ticks.insert(key.clone(), self.tick) {
+            self.order.remove(&tick);
+        }
+        self.order.insert(self.tick, key.clone());
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(tick) = self.ticks.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+
+    fn victim(&self) -> Option<&K> {
+        self.order.values().next()
+    }
+
+    fn eviction_order<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+    {
+        self.order.values()
+    }
+
+    fn clear(&mut self) {
+        self.ticks.clear();
+        self.order.clear();
+    }
+}
+
+/// The [EvictionPolicy] evicting the least frequently used value first, and the
+/// least recently used one among equally used values.
+pub struct Lfu<K> {
+    uses: HashMap<K, (u64, u64)>,
+    /// The keys by their number of uses and the tick of their last use.
+    order: BTreeMap<(u64, u64), K>,
+    tick: u64,
+}
+
+impl<K> Default for Lfu<K> {
+    fn default() -> Self {
+        Self {
+            uses: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> EvictionPolicy<K> for Lfu<K> {
+    fn on_insert(&mut self, key: &K) {
+        self.on_access(key)
+    }
+
+    fn on_access(&mut self, key: &K) {
+        self.tick += 1;
+        let (count, tick) = self.uses.entry(key.clone()).or_insert((0, 0));
+        self.order.remove(&(*count, *tick));
+        *count += 1;
+        *tick = self.tick;
+        self.order.insert((*count, *tick), key.clone());
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(uses) = self.uses.remove(key) {
+            self.order.remove(&uses);
+        }
+    }
+
+    fn victim(&self) -> Option<&K> {
+        self.order.values().next()
+    }
+
+    fn eviction_order<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+    {
+        self.order.values()
+    }
+
+    fn clear(&mut self) {
+        self.uses.clear();
+        self.order.clear();
+    }
+}
+
+/// A cache holding at most `capacity` values, evicting them as chosen by its
+/// [EvictionPolicy], and handing out `Mark::Pointer`s to them.
+///
+/// Evicting a value only drops the strong pointer of the cache, so handles to it
+/// stay valid. Wrap the cache in a lock to share it, for instance a
+/// [crate::SharedCell].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let mut cache = Cache::<RcMark, &str, u32, Lfu<_>>::new(2);
+/// cache.insert("hot", 1);
+/// cache.get("hot");
+/// let cold = cache.insert("cold", 2);
+/// cache.insert("new", 3);
+/// assert!(cache.contains_key("hot") && !cache.contains_key("cold"));
+/// assert_eq!(*cold, 2);
+/// ```
+pub struct Cache<Mark: RefCountFamily, K, V, P = Lru<K>> {
+    entries: HashMap<K, Mark::Pointer<V>>,
+    policy: P,
+    capacity: usize,
+}
+
+/// A [Cache] evicting the least recently used value first.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn load<R: RefCountFamily>(cache: &mut LruCache<R, u32, String>, id: u32) -> R::Pointer<String> {
+///     cache.get_or_insert_with(id, || format!("page {id}"))
+/// }
+/// let mut cache = LruCache::<RcMark, _, _>::new(1);
+/// let first = load(&mut cache, 1);
+/// load(&mut cache, 2);
+/// assert!(!cache.contains_key(&1));
+/// assert_eq!(*first, "page 1");
+/// ```
+pub type LruCache<Mark, K, V> = Cache<Mark, K, V, Lru<K>>;
+
+impl<Mark: RefCountFamily, K: Hash + Eq + Clone, V, P: EvictionPolicy<K>> Cache<Mark, K, V, P> {
+    /// # Panics
+    /// If `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Cache capacity must not be 0");
+        Self {
+            entries: HashMap::new(),
+            policy: P::default(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns a handle to the value of `key`, telling the policy it was used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<Mark::Pointer<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (key, value) = self.entries.get_key_value(key)?;
+        self.policy.on_access(key);
+        Some(value.clone())
+    }
+
+    /// Returns a handle to the value of `key`, without telling the policy.
+    pub fn peek<Q>(&self, key: &Q) -> Option<Mark::Pointer<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key).cloned()
+    }
+
+    /// Caches `value` for `key`, evicting a value first if the cache is full, and
+    /// returns a handle to it.
+    pub fn insert(&mut self, key: K, value: V) -> Mark::Pointer<V> {
+        self.insert_pointer(key, Mark::new(value))
+    }
+
+    /// Caches `pointer` for `key`, like [Cache::insert].
+    pub fn insert_pointer(&mut self, key: K, pointer: Mark::Pointer<V>) -> Mark::Pointer<V> {
+        self.remove(&key);
+        if self.entries.len() == self.capacity {
+            if let Some(victim) = self.policy.victim().cloned() {
+                self.entries.remove(&victim);
+                self.policy.on_evict(&victim);
+            }
+        }
+        self.policy.on_insert(&key);
+        self.entries.insert(key, pointer.clone());
+        pointer
+    }
+
+    /// Returns a handle to the value of `key`, caching `f()` first if there is none.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> Mark::Pointer<V> {
+        match self.get(&key) {
+            Some(value) => value,
+            None => self.insert(key, f()),
+        }
+    }
+
+    /// Removes `key` from the cache, returning the cache's handle to its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Mark::Pointer<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (key, value) = self.entries.remove_entry(key)?;
+        self.policy.on_remove(&key);
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.policy.clear();
+    }
+}
+
+impl<Mark: RefCountFamily, K: fmt::Debug, V: fmt::Debug, P: EvictionPolicy<K>> fmt::Debug
+    for Cache<Mark, K, V, P>
+where
+    K: Hash + Eq,
+{
+    /// Lists the entries from the next to be evicted to the last one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self
+            .policy
+            .eviction_order()
+            .map(|key| (key, &*self.entries[key]));
+        f.debug_map().entries(entries).finish()
+    }
+}
+
+/// A map holding its values weakly, handing out `Mark::Pointer`s to them, so
+/// values live as long as handles to them.
+///
+/// The entries of dropped values are pruned once the map has doubled in size since
+/// the last pruning, or by [WeakCache::prune].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Assets<R: RefCountFamily> {
+///     textures: WeakCache<R, &'static str, Vec<u8>>,
+/// }
+/// impl<R: RefCountFamily> Assets<R> {
+///     fn texture(&mut self, path: &'static str) -> R::Pointer<Vec<u8>> {
+///         self.textures.get_or_insert_with(path, || path.as_bytes().to_vec())
+///     }
+/// }
+/// let mut assets = Assets::<ArcMark> { textures: WeakCache::new() };
+/// let grass = assets.texture("grass.png");
+/// assert!(std::sync::Arc::ptr_eq(&grass, &assets.texture("grass.png")));
+/// drop(grass);
+/// assert!(assets.textures.get("grass.png").is_none());
+/// ```
+pub struct WeakCache<Mark: RefCountFamily, K, V> {
+    entries: HashMap<K, Mark::WeakPointer<V>>,
+    /// The number of entries at which to prune.
+    prune_at: usize,
+}
+
+const MIN_PRUNE_AT: usize = 8;
+
+impl<Mark: RefCountFamily, K: Hash + Eq, V> WeakCache<Mark, K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            prune_at: MIN_PRUNE_AT,
+        }
+    }
+
+    /// Returns the number of entries, including the ones of dropped values not
+    /// pruned yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a handle to the value of `key`, if it is alive.
+    pub fn get<Q>(&self, key: &Q) -> Option<Mark::Pointer<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key)?.upgrade()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries
+            .get(key)
+            .is_some_and(|weak| weak.strong_count() > 0)
+    }
+
+    /// Caches `value` for `key`, and returns the only handle to it.
+    pub fn insert(&mut self, key: K, value: V) -> Mark::Pointer<V> {
+        let pointer = Mark::new(value);
+        self.insert_pointer(key, &pointer);
+        pointer
+    }
+
+    /// Caches the value of `pointer` for `key`.
+    pub fn insert_pointer(&mut self, key: K, pointer: &Mark::Pointer<V>) {
+        if self.entries.len() >= self.prune_at {
+            self.prune();
+        }
+        self.entries.insert(key, Mark::Pointer::downgrade(pointer));
+    }
+
+    /// Returns a handle to the value of `key`, caching `f()` first if it is not
+    /// alive.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> Mark::Pointer<V> {
+        match self.get(&key) {
+            Some(pointer) => pointer,
+            None => self.insert(key, f()),
+        }
+    }
+
+    /// Removes `key` from the cache, returning a handle to its value if it is alive.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Mark::Pointer<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.remove(key)?.upgrade()
+    }
+
+    /// Removes the entries of dropped values.
+    pub fn prune(&mut self) {
+        self.entries.retain(|_, weak| weak.strong_count() > 0);
+        self.prune_at = (self.entries.len() * 2).max(MIN_PRUNE_AT);
+    }
+
+    /// Returns the keys and handles to the values alive, in an unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Mark::Pointer<V>)> {
+        self.entries
+            .iter()
+            .filter_map(|(key, weak)| Some((key, weak.upgrade()?)))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.prune_at = MIN_PRUNE_AT;
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq, V> Default for WeakCache<Mark, K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: RefCountFamily, K: fmt::Debug, V: fmt::Debug> fmt::Debug for WeakCache<Mark, K, V> {
+    /// Lists the entries of the values alive.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|(key, weak)| Some((key, weak.upgrade()?)))
+            .collect();
+        f.debug_map()
+            .entries(entries.iter().map(|(key, value)| (key, &**value)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_lru_cache() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut cache = LruCache::<R, &str, i32>::new(2);
+            let a = cache.insert("a", 1);
+            cache.insert("b", 2);
+            assert_eq!(cache.get("a").map(|a| *a), Some(1));
+            assert_eq!(format!("{cache:?}"), r#"{"b": 2, "a": 1}"#);
+            cache.insert("c", 3);
+            assert!(!cache.contains_key("b"));
+            assert_eq!(cache.peek("a").map(|a| *a), Some(1));
+            cache.insert("d", 4);
+            assert_eq!((cache.len(), cache.contains_key("a")), (2, false));
+            assert_eq!((*a, R::Pointer::strong_count(&a)), (1, 1));
+
+            let c = cache.get_or_insert_with("c", || unreachable!());
+            assert!(R::Pointer::ptr_eq(&c, &cache.peek("c").unwrap()));
+            cache.insert("c", 30);
+            assert_eq!((*c, *cache.peek("c").unwrap(), cache.len()), (3, 30, 2));
+            assert_eq!(cache.remove("d").map(|d| *d), Some(4));
+            cache.clear();
+            assert!(cache.is_empty() && cache.remove("c").is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_lfu_cache() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut cache = Cache::<R, &str, i32, Lfu<_>>::new(2);
+            cache.insert("a", 1);
+            let b = cache.insert("b", 2);
+            assert_eq!(cache.get("a").map(|a| *a), Some(1));
+            assert_eq!(format!("{cache:?}"), r#"{"b": 2, "a": 1}"#);
+            cache.insert("c", 3);
+            assert!(cache.contains_key("a") && !cache.contains_key("b"));
+            assert_eq!(*b, 2);
+            cache.get("c");
+            cache.get("c");
+            cache.insert("d", 4);
+            assert!(!cache.contains_key("a") && cache.contains_key("c"));
+            assert_eq!(cache.remove("c").map(|c| *c), Some(3));
+            cache.clear();
+            assert!(cache.is_empty());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    /// An [Lru] policy keeping the keys evicted, as ghost-list policies do.
+    #[derive(Default)]
+    struct Ghosts {
+        lru: Lru<&'static str>,
+        evicted: Vec<&'static str>,
+    }
+
+    impl EvictionPolicy<&'static str> for Ghosts {
+        fn on_insert(&mut self, key: &&'static str) {
+            self.lru.on_insert(key)
+        }
+        fn on_access(&mut self, key: &&'static str) {
+            self.lru.on_access(key)
+        }
+        fn on_remove(&mut self, key: &&'static str) {
+            self.lru.on_remove(key)
+        }
+        fn on_evict(&mut self, key: &&'static str) {
+            self.lru.on_remove(key);
+            self.evicted.push(key);
+        }
+        fn victim(&self) -> Option<&&'static str> {
+            self.lru.victim()
+        }
+        fn eviction_order<'a>(&'a self) -> impl Iterator<Item = &'a &'static str>
+        where
+            &'static str: 'a,
+        {
+            self.lru.eviction_order()
+        }
+        fn clear(&mut self) {
+            self.lru.clear();
+        }
+    }
+
+    #[test]
+    fn test_eviction_hooks() {
+        let mut cache = Cache::<RcMark, &str, i32, Ghosts>::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("b", 20);
+        cache.remove("a");
+        cache.insert("c", 3);
+        cache.insert("d", 4);
+        cache.insert("e", 5);
+        assert_eq!(cache.policy.evicted, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_weak_cache() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut cache = WeakCache::<R, u32, String>::new();
+            let a = cache.insert(1, "a".to_owned());
+            let b = cache.get_or_insert_with(2, || "b".to_owned());
+            assert!(R::Pointer::ptr_eq(&a, &cache.get(&1).unwrap()));
+            assert!(R::Pointer::ptr_eq(
+                &b,
+                &cache.get_or_insert_with(2, || unreachable!())
+            ));
+            let debug = format!("{cache:?}");
+            assert!(debug == r#"{1: "a", 2: "b"}"# || debug == r#"{2: "b", 1: "a"}"#);
+            drop(b);
+            assert!(!cache.contains_key(&2) && cache.get(&2).is_none());
+            assert_eq!((cache.len(), cache.iter().count()), (2, 1));
+            let b = cache.get_or_insert_with(2, || "b2".to_owned());
+            assert_eq!(*b, "b2");
+            drop(b);
+            cache.prune();
+            assert_eq!(cache.len(), 1);
+
+            for i in 10..100 {
+                cache.insert(i, i.to_string());
+            }
+            assert!(cache.len() <= MIN_PRUNE_AT);
+            assert_eq!(cache.remove(&1).as_deref().map(String::as_str), Some("a"));
+            assert!(cache.remove(&1).is_none());
+            drop(a);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,60 @@
+//! Support for [yoke](https://docs.rs/yoke) carts.
+//!
+//! With the `yoke` feature, [Wrap](crate::Wrap) and [Shared](crate::Shared)
+//! implement `CloneableCart` when the pointer they wrap does, as `Rc` and `Arc`
+//! do, so a `Yoke` backed by them can be cloned. Code generic over the family
+//! asks for it with a `where R::Pointer<[u8]>: StableDeref + CloneableCart` bound.
+
+#[cfg(feature = "yoke")]
+mod impls {
+    use crate::{traits::*, Shared, Wrap, WrapHooks};
+    use yoke::CloneableCart;
+
+    // Both clone the pointer they wrap, so their clones point to the same value when
+    // the clones of that pointer do.
+    unsafe impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> CloneableCart
+        for Wrap<Mark, H, T, Tag>
+    where
+        Mark::Pointer<T>: CloneableCart,
+    {
+    }
+    unsafe impl<Mark: RefCountFamily, T: ?Sized> CloneableCart for Shared<Mark, T> where
+        Mark::Pointer<T>: CloneableCart
+    {
+    }
+}
+
+#[cfg(all(test, feature = "yoke"))]
+mod tests {
+    use crate::{traits::*, ArcMark, RcMark, WrapHooks, WrapMark};
+    use stable_deref_trait::StableDeref;
+    use yoke::{CloneableCart, Yoke};
+
+    struct NoHooks;
+    impl WrapHooks for NoHooks {}
+
+    #[test]
+    fn test_yoke_cart() {
+        fn actual_test<R: RefCountFamily>()
+        where
+            R::Pointer<str>: StableDeref + CloneableCart,
+            R::Pointer<[u8]>: StableDeref + CloneableCart,
+        {
+            let cart = R::new_str("John Doe");
+            let first: Yoke<&'static str, R::Pointer<str>> =
+                Yoke::attach_to_cart(cart, |s| s.split(' ').next().unwrap());
+            let clone = first.clone();
+            assert_eq!(*clone.get(), "John");
+            assert!(R::Pointer::ptr_eq(
+                first.backing_cart(),
+                clone.backing_cart()
+            ));
+            let bytes: Yoke<&'static [u8], R::Pointer<[u8]>> =
+                Yoke::attach_to_cart(R::new_slice(vec![1, 2, 3]), |b| &b[1..]);
+            assert_eq!(*bytes.clone().get(), [2, 3]);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        actual_test::<WrapMark<ArcMark, NoHooks>>();
+    }
+}
@@ -0,0 +1,89 @@
+//! Unsizing coercion of family pointers on stable.
+//!
+//! `Mark::Pointer<T>` can't coerce to `Mark::Pointer<dyn Trait>` in generic code without
+//! the `coerce_unsized` nightly feature. Raw pointers can, so [coerce!] goes through
+//! [RefCounted::into_raw] and [RefCounted::from_raw], for the families implementing
+//! [CoerceFamily].
+
+use crate::{traits::*, ArcMark, RcMark};
+
+/// A family whose [RefCounted::from_raw] accepts the result of [RefCounted::into_raw]
+/// after an unsizing coercion, as in `*const T` to `*const dyn Trait`.
+///
+/// # Safety
+/// Turning a `Pointer<T>` into a raw pointer, coercing it, and taking it back with
+/// `Pointer<U>::from_raw` must give a valid pointer owning the same strong count.
+pub unsafe trait CoerceFamily: RefCountFamily {}
+
+unsafe impl CoerceFamily for RcMark {}
+
+unsafe impl CoerceFamily for ArcMark {}
+
+unsafe impl<Mark: CoerceFamily, H: crate::WrapHooks, Tag> CoerceFamily
+    for crate::WrapMark<Mark, H, Tag>
+{
+}
+
+/// # Safety
+/// `cast` must be an unsizing coercion.
+#[doc(hidden)]
+pub unsafe fn __coerce<P, T, U: ?Sized>(
+    pointer: P,
+    cast: impl FnOnce(*const T) -> *const U,
+) -> <P::Mark as RefCountFamily>::Pointer<U>
+where
+    P: RefCounted<T>,
+    P::Mark: CoerceFamily,
+{
+    RefCounted::from_raw(cast(P::into_raw(pointer)))
+}
+
+/// Coerces a family pointer to a pointer to an unsized type, usually a trait object.
+///
+/// The pointer is either an identifier or a parenthesized expression.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::fmt::Display;
+/// fn to_display<Mark: CoerceFamily>(value: u32) -> Mark::Pointer<dyn Display> {
+///     let pointer = Mark::new(value);
+///     coerce!(pointer as dyn Display)
+/// }
+/// assert_eq!(to_display::<ArcMark>(3).to_string(), "3");
+/// ```
+#[macro_export]
+macro_rules! coerce {
+    (($pointer:expr) as $target:ty) => {
+        match $pointer {
+            pointer => unsafe {
+                $crate::coerce::__coerce(pointer, |raw| -> *const $target { raw })
+            },
+        }
+    };
+    ($pointer:ident as $target:ty) => {
+        $crate::coerce!(($pointer) as $target)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+
+    #[test]
+    fn test_coerce() {
+        fn actual_test<R: CoerceFamily>() {
+            let a = R::new(3);
+            let weak = R::Pointer::downgrade(&a);
+            let b = coerce!(a as dyn Debug);
+            assert_eq!(format!("{:?}", &*b), "3");
+            assert_eq!(weak.strong_count(), 1);
+            let slice = coerce!((R::new([1, 2])) as [i32]);
+            assert_eq!(*slice, [1, 2]);
+            drop(b);
+            assert!(weak.upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
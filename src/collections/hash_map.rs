@@ -0,0 +1,579 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hash},
+    mem,
+    ops::Index,
+    slice,
+};
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+/// An immutable hash map, stored as a hash array mapped trie whose nodes are
+/// shared between maps.
+///
+/// Each level of the trie takes 5 bits of the hash, so lookups, [HashMap::insert]
+/// and [HashMap::remove] take O(log n) steps, copying only the nodes on the path
+/// to the key. Their `_mut` counterparts edit the nodes in place where they are not
+/// shared, through [RefCounted::make_mut].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// use cark_ref_counted::collections::HashMap;
+/// fn with_default<R: RefCountFamily>(config: &HashMap<R, &'static str, u32>) -> HashMap<R, &'static str, u32> {
+///     if config.contains_key("port") {
+///         config.clone()
+///     } else {
+///         config.insert("port", 80)
+///     }
+/// }
+/// let config = HashMap::<ArcMark, _, _>::new().insert("workers", 4);
+/// let full = with_default(&config);
+/// assert_eq!((config.get("port"), full["port"], full.len()), (None, 80, 2));
+/// std::thread::spawn(move || assert_eq!(full["workers"], 4)).join().unwrap();
+/// ```
+pub struct HashMap<Mark: RefCountFamily, K, V, S = RandomState> {
+    root: Option<Mark::Pointer<Node<Mark, K, V>>>,
+    len: usize,
+    hasher: S,
+}
+
+enum Node<Mark: RefCountFamily, K, V> {
+    /// The entries whose hashes have the bits of `bitmap` at the level of the node,
+    /// in order.
+    Branch {
+        bitmap: u32,
+        entries: Vec<Entry<Mark, K, V>>,
+    },
+    /// The pairs whose hashes are all equal, below the last level.
+    Collision { hash: u64, pairs: Vec<(K, V)> },
+}
+
+enum Entry<Mark: RefCountFamily, K, V> {
+    Pair(u64, K, V),
+    Node(Mark::Pointer<Node<Mark, K, V>>),
+}
+
+impl<Mark: RefCountFamily, K: Clone, V: Clone> Clone for Node<Mark, K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Branch { bitmap, entries } => Node::Branch {
+                bitmap: *bitmap,
+                entries: entries.clone(),
+            },
+            Node::Collision { hash, pairs } => Node::Collision {
+                hash: *hash,
+                pairs: pairs.clone(),
+            },
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K: Clone, V: Clone> Clone for Entry<Mark, K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Entry::Pair(hash, key, value) => Entry::Pair(*hash, key.clone(), value.clone()),
+            Entry::Node(node) => Entry::Node(node.clone()),
+        }
+    }
+}
+
+/// Returns the bit of `hash` in the bitmap of a branch at `shift`.
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+/// Returns the position of the entry for `bit` in a branch.
+fn position(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<Mark: RefCountFamily, K, V> Node<Mark, K, V> {
+    /// Creates the nodes holding two pairs with different keys, from `shift` down.
+    fn pair(shift: u32, a: (u64, K, V), b: (u64, K, V)) -> Self {
+        if shift >= u64::BITS {
+            return Node::Collision {
+                hash: a.0,
+                pairs: vec![(a.1, a.2), (b.1, b.2)],
+            };
+        }
+        let (bit_a, bit_b) = (bit(a.0, shift), bit(b.0, shift));
+        if bit_a == bit_b {
+            let child = Mark::new(Self::pair(shift + BITS, a, b));
+            return Node::Branch {
+                bitmap: bit_a,
+                entries: vec![Entry::Node(child)],
+            };
+        }
+        let (a, b) = (Entry::Pair(a.0, a.1, a.2), Entry::Pair(b.0, b.1, b.2));
+        Node::Branch {
+            bitmap: bit_a | bit_b,
+            entries: if bit_a < bit_b {
+                vec![a, b]
+            } else {
+                vec![b, a]
+            },
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K: Clone + Eq, V: Clone> Node<Mark, K, V> {
+    fn insert(
+        node: &mut Mark::Pointer<Self>,
+        shift: u32,
+        hash: u64,
+        key: K,
+        value: V,
+    ) -> Option<V> {
+        match Mark::Pointer::make_mut(node) {
+            Node::Collision { pairs, .. } => {
+                if let Some((_, old)) = pairs.iter_mut().find(|(k, _)| *k == key) {
+                    return Some(mem::replace(old, value));
+                }
+                pairs.push((key, value));
+                None
+            }
+            Node::Branch { bitmap, entries } => {
+                let bit = bit(hash, shift);
+                let pos = position(*bitmap, bit);
+                if *bitmap & bit == 0 {
+                    *bitmap |= bit;
+                    entries.insert(pos, Entry::Pair(hash, key, value));
+                    return None;
+                }
+                match &mut entries[pos] {
+                    Entry::Node(child) => Self::insert(child, shift + BITS, hash, key, value),
+                    Entry::Pair(h, k, old) if *h == hash && *k == key => {
+                        Some(mem::replace(old, value))
+                    }
+                    Entry::Pair(..) => {
+                        let Entry::Pair(h, k, v) = entries.remove(pos) else {
+                            unreachable!()
+                        };
+                        let child = Self::pair(shift + BITS, (h, k, v), (hash, key, value));
+                        entries.insert(pos, Entry::Node(Mark::new(child)));
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the pair of `key`, which must be in the node.
+    fn remove<Q>(node: &mut Mark::Pointer<Self>, shift: u32, hash: u64, key: &Q) -> V
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match Mark::Pointer::make_mut(node) {
+            Node::Collision { pairs, .. } => {
+                let pos = pairs.iter().position(|(k, _)| k.borrow() == key);
+                pairs.swap_remove(pos.expect("the key is in the node")).1
+            }
+            Node::Branch { bitmap, entries } => {
+                let bit = bit(hash, shift);
+                let pos = position(*bitmap, bit);
+                match &mut entries[pos] {
+                    Entry::Pair(..) => {
+                        *bitmap &= !bit;
+                        let Entry::Pair(.., value) = entries.remove(pos) else {
+                            unreachable!()
+                        };
+                        value
+                    }
+                    Entry::Node(child) => {
+                        let value = Self::remove(child, shift + BITS, hash, key);
+                        // A child left with a single pair is replaced by the pair.
+                        if let Some(pair) = Self::take_single(child) {
+                            entries[pos] = pair;
+                        }
+                        value
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes the pair out of a node holding only one.
+    fn take_single(node: &mut Mark::Pointer<Self>) -> Option<Entry<Mark, K, V>> {
+        match Mark::Pointer::make_mut(node) {
+            Node::Branch { entries, .. } => match entries.as_slice() {
+                [Entry::Pair(..)] => entries.pop(),
+                _ => None,
+            },
+            Node::Collision { hash, pairs } if pairs.len() == 1 => {
+                let (key, value) = pairs.pop()?;
+                Some(Entry::Pair(*hash, key, value))
+            }
+            Node::Collision { .. } => None,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K, V> HashMap<Mark, K, V> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<Mark: RefCountFamily, K, V, S> HashMap<Mark, K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    /// Returns an iterator over the pairs, in an unspecified order.
+    pub fn iter(&self) -> Iter<'_, Mark, K, V> {
+        let entries = match self.root.as_deref() {
+            Some(Node::Branch { entries, .. }) => entries.as_slice(),
+            _ => &[],
+        };
+        Iter {
+            stack: vec![entries.iter()],
+            pairs: [].iter(),
+            len: self.len,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Returns `true` if both maps share all their nodes.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Mark::Pointer::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq, V, S: BuildHasher> HashMap<Mark, K, V, S> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        let mut node = self.root.as_deref()?;
+        let mut shift = 0;
+        loop {
+            match node {
+                Node::Collision { pairs, .. } => {
+                    return pairs
+                        .iter()
+                        .find(|(k, _)| k.borrow() == key)
+                        .map(|(_, v)| v)
+                }
+                Node::Branch { bitmap, entries } => {
+                    let bit = bit(hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    match &entries[position(*bitmap, bit)] {
+                        Entry::Pair(h, k, v) => {
+                            return (*h == hash && k.borrow() == key).then_some(v)
+                        }
+                        Entry::Node(child) => {
+                            node = child;
+                            shift += BITS;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone>
+    HashMap<Mark, K, V, S>
+{
+    /// Returns a map with `key` set to `value`.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let mut map = self.clone();
+        map.insert_mut(key, value);
+        map
+    }
+
+    /// Returns a map without `key`.
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut map = self.clone();
+        map.remove_mut(key);
+        map
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq + Clone, V: Clone, S: BuildHasher> HashMap<Mark, K, V, S> {
+    /// Sets `key` to `value`, copying only the shared nodes on its path, and
+    /// returns the previous value.
+    pub fn insert_mut(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&key);
+        let old = match &mut self.root {
+            Some(root) => Node::<Mark, K, V>::insert(root, 0, hash, key, value),
+            None => {
+                let entries = vec![Entry::Pair(hash, key, value)];
+                let bitmap = bit(hash, 0);
+                self.root = Some(Mark::new(Node::Branch { bitmap, entries }));
+                None
+            }
+        };
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Removes `key`, copying only the shared nodes on its path, and returns its
+    /// value. The map is left untouched if the key is absent.
+    pub fn remove_mut<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.contains_key(key) {
+            return None;
+        }
+        let hash = self.hasher.hash_one(key);
+        let root = self.root.as_mut()?;
+        let value = Node::<Mark, K, V>::remove(root, 0, hash, key);
+        self.len -= 1;
+        if self.len == 0 {
+            self.root = None;
+        }
+        Some(value)
+    }
+}
+
+impl<Mark: RefCountFamily, K, V, S: Clone> Clone for HashMap<Mark, K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K, V, S: Default> Default for HashMap<Mark, K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<Mark: RefCountFamily, K, Q, V, S> Index<&Q> for HashMap<Mark, K, V, S>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    type Output = V;
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in HashMap")
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq + Clone, V: Clone, S: BuildHasher> Extend<(K, V)>
+    for HashMap<Mark, K, V, S>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert_mut(key, value);
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Default>
+    FromIterator<(K, V)> for HashMap<Mark, K, V, S>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, Mark: RefCountFamily, K, V, S> IntoIterator for &'a HashMap<Mark, K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, Mark, K, V>;
+    fn into_iter(self) -> Iter<'a, Mark, K, V> {
+        self.iter()
+    }
+}
+
+impl<Mark: RefCountFamily, K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HashMap<Mark, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq, V: PartialEq, S: BuildHasher> PartialEq
+    for HashMap<Mark, K, V, S>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && (self.ptr_eq(other) || self.iter().all(|(k, v)| other.get(k) == Some(v)))
+    }
+}
+
+impl<Mark: RefCountFamily, K: Hash + Eq, V: Eq, S: BuildHasher> Eq for HashMap<Mark, K, V, S> {}
+
+/// An iterator over the pairs of a [HashMap].
+pub struct Iter<'a, Mark: RefCountFamily, K, V> {
+    stack: Vec<slice::Iter<'a, Entry<Mark, K, V>>>,
+    pairs: slice::Iter<'a, (K, V)>,
+    len: usize,
+}
+
+impl<'a, Mark: RefCountFamily, K, V> Iterator for Iter<'a, Mark, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            if let Some((key, value)) = self.pairs.next() {
+                self.len -= 1;
+                return Some((key, value));
+            }
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                }
+                Some(Entry::Pair(_, key, value)) => {
+                    self.len -= 1;
+                    return Some((key, value));
+                }
+                Some(Entry::Node(node)) => match &**node {
+                    Node::Branch { entries, .. } => self.stack.push(entries.iter()),
+                    Node::Collision { pairs, .. } => self.pairs = pairs.iter(),
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<Mark: RefCountFamily, K, V> ExactSizeIterator for Iter<'_, Mark, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// Hashes everything to the low byte of the last `u64` written, to force
+    /// deep paths and collisions.
+    #[derive(Default)]
+    struct LowByteHasher(u64);
+
+    impl Hasher for LowByteHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+        fn write(&mut self, _: &[u8]) {}
+        fn write_u64(&mut self, n: u64) {
+            self.0 = (n & 0xff) << 56;
+        }
+    }
+
+    #[test]
+    fn test_hash_map() {
+        fn actual_test<R: RefCountFamily>() {
+            let empty = HashMap::<R, u64, u64>::new();
+            let one = empty.insert(1, 10);
+            assert_eq!((empty.get(&1), one[&1], one.len()), (None, 10, 1));
+            assert_eq!(format!("{one:?}"), "{1: 10}");
+
+            let big: HashMap<R, u64, u64> = (0..2000).map(|i| (i, i * 2)).collect();
+            assert_eq!(big.len(), 2000);
+            assert!((0..2000).all(|i| big[&i] == i * 2));
+            assert_eq!(big.iter().len(), 2000);
+            let mut keys: Vec<_> = big.keys().copied().collect();
+            keys.sort();
+            assert!(keys.into_iter().eq(0..2000));
+
+            let edited = big.insert(7, 0).remove(&8);
+            assert_eq!(
+                (big[&7], big.get(&8), edited[&7], edited.get(&8)),
+                (14, Some(&16), 0, None)
+            );
+            assert_eq!(edited.len(), 1999);
+            assert_ne!(big, edited);
+            assert_eq!(big.remove(&5000), big);
+            assert!(big.remove(&5000).ptr_eq(&big));
+
+            let mut unique = edited;
+            let root = R::Pointer::as_ptr(unique.root.as_ref().unwrap());
+            assert_eq!(unique.insert_mut(7, 1), Some(0));
+            assert_eq!(unique.remove_mut(&7), Some(1));
+            assert_eq!(unique.remove_mut(&7), None);
+            assert_eq!(R::Pointer::as_ptr(unique.root.as_ref().unwrap()), root);
+            for i in 0..2000 {
+                unique.remove_mut(&i);
+            }
+            assert!(unique.is_empty() && unique.root.is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_hash_map_collisions() {
+        type Map = HashMap<RcMark, u64, u64, BuildHasherDefault<LowByteHasher>>;
+        let map: Map = (0..1000).map(|i| (i, i)).collect();
+        assert_eq!(map.len(), 1000);
+        assert!((0..1000).all(|i| map[&i] == i));
+        assert_eq!(map.iter().count(), 1000);
+        let mut smaller = map.clone();
+        for i in (0..1000).filter(|i| i % 256 != 3) {
+            assert_eq!(smaller.remove_mut(&i), Some(i));
+        }
+        assert_eq!(smaller.len(), 4);
+        assert!([3, 259, 515, 771].iter().all(|i| smaller[i] == *i));
+        assert_eq!(map.len(), 1000);
+        let mut one = smaller;
+        for i in [3, 259, 515] {
+            one.remove_mut(&i);
+        }
+        // The last pair was pulled back up to the root.
+        let Some(Node::Branch { entries, .. }) = one.root.as_deref() else {
+            unreachable!()
+        };
+        assert!(matches!(entries.as_slice(), [Entry::Pair(_, 771, 771)]));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::traits::*;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// An immutable singly-linked list, whose tails are shared between lists.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// use cark_ref_counted::collections::List;
+/// fn path<R: RefCountFamily>(parent: &List<R, &'static str>, name: &'static str) -> List<R, &'static str> {
+///     parent.cons(name)
+/// }
+/// let root = List::<RcMark, _>::new().cons("root");
+/// let (a, b) = (path(&root, "a"), path(&root, "b"));
+/// assert_eq!(a.iter().copied().collect::<Vec<_>>(), ["a", "root"]);
+/// assert!(a.tail().unwrap().ptr_eq(&b.tail().unwrap()));
+/// ```
+pub struct List<Mark: RefCountFamily, T> {
+    head: Option<Mark::Pointer<Node<Mark, T>>>,
+    len: usize,
+}
+
+struct Node<Mark: RefCountFamily, T> {
+    value: T,
+    next: Option<Mark::Pointer<Node<Mark, T>>>,
+}
+
+impl<Mark: RefCountFamily, T> List<Mark, T> {
+    pub fn new() -> Self {
+        Self { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// Returns a list starting with `value`, followed by this one.
+    pub fn cons(&self, value: T) -> Self {
+        Self {
+            head: Some(Mark::new(Node {
+                value,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    /// Returns the list without its first element, or `None` if it is empty.
+    pub fn tail(&self) -> Option<Self> {
+        self.head.as_ref().map(|node| Self {
+            head: node.next.clone(),
+            len: self.len - 1,
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_, Mark, T> {
+        Iter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+
+    /// Returns `true` if both lists share all their nodes.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Mark::Pointer::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Clone for List<Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Default for List<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: RefCountFamily, T> Drop for List<Mark, T> {
+    /// Drops the unshared nodes one by one, as the recursive drop of a long list
+    /// would overflow the stack.
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            next = Mark::Pointer::into_inner(node).and_then(|node| node.next);
+        }
+    }
+}
+
+/// Builds a list with the elements in the order of `iter`.
+impl<Mark: RefCountFamily, T> FromIterator<T> for List<Mark, T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let values: Vec<T> = iter.into_iter().collect();
+        values
+            .into_iter()
+            .rev()
+            .fold(Self::new(), |list, value| list.cons(value))
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T> IntoIterator for &'a List<Mark, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, Mark, T>;
+    fn into_iter(self) -> Iter<'a, Mark, T> {
+        self.iter()
+    }
+}
+
+impl<Mark: RefCountFamily, T: fmt::Debug> fmt::Debug for List<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<Mark: RefCountFamily, T: PartialEq> PartialEq for List<Mark, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (self.ptr_eq(other) || self.iter().eq(other))
+    }
+}
+
+impl<Mark: RefCountFamily, T: Eq> Eq for List<Mark, T> {}
+
+impl<Mark: RefCountFamily, T: Hash> Hash for List<Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+/// An iterator over the elements of a [List], from its head.
+pub struct Iter<'a, Mark: RefCountFamily, T> {
+    next: Option<&'a Node<Mark, T>>,
+    len: usize,
+}
+
+impl<'a, Mark: RefCountFamily, T> Iterator for Iter<'a, Mark, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.next?;
+        self.next = node.next.as_deref();
+        self.len -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<Mark: RefCountFamily, T> ExactSizeIterator for Iter<'_, Mark, T> {}
+
+impl<Mark: RefCountFamily, T> Clone for Iter<'_, Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            len: self.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_list() {
+        fn actual_test<R: RefCountFamily>() {
+            let empty = List::<R, i32>::new();
+            let one = empty.cons(1);
+            let list = one.cons(2).cons(3);
+            assert_eq!((list.len(), list.head()), (3, Some(&3)));
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), [3, 2, 1]);
+            let tail = list.tail().unwrap().tail().unwrap();
+            assert!(tail.ptr_eq(&one));
+            assert!(empty.tail().is_none() && empty.is_empty());
+            assert_eq!(list, [3, 2, 1].into_iter().collect());
+            assert_ne!(list, one);
+            assert_eq!(format!("{list:?}"), "[3, 2, 1]");
+            let branch = one.cons(4);
+            assert_eq!(branch.iter().len(), 2);
+            assert_eq!(R::Pointer::strong_count(one.head.as_ref().unwrap()), 4);
+
+            let long: List<R, usize> = (0..100_000).collect();
+            assert_eq!(long.iter().nth(99_999), Some(&99_999));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,13 @@
+//! Persistent collections built directly on [crate::RefCountFamily], sharing their
+//! nodes through `Mark::Pointer`.
+//!
+//! Unlike the [rpds](https://docs.rs/rpds) aliases of the `persistent` module, these
+//! work with any family, without going through archery. They are not re-exported
+//! at the crate root, where names like `List` would be too generic.
+
+pub mod hash_map;
+pub mod list;
+pub mod vector;
+pub use hash_map::HashMap;
+pub use list::List;
+pub use vector::Vector;
@@ -0,0 +1,382 @@
+use crate::traits::*;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Index,
+};
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+/// An immutable vector, stored as a trie of 32-wide nodes shared between vectors.
+///
+/// Indexing, [Vector::set] and [Vector::push_back] take O(log n) steps, and copy
+/// only the nodes on the path to the element. Their `_mut` counterparts edit the
+/// nodes in place where they are not shared, through [RefCounted::make_mut], which
+/// makes building a vector a series of cheap in-place pushes.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// use cark_ref_counted::collections::Vector;
+/// fn history<R: RefCountFamily>(states: &[i32]) -> Vec<Vector<R, i32>> {
+///     let mut history = vec![Vector::new()];
+///     for &state in states {
+///         let last = history.last().unwrap();
+///         history.push(last.push_back(state));
+///     }
+///     history
+/// }
+/// let history = history::<ArcMark>(&[1, 2, 3]);
+/// assert_eq!((history[2].len(), history[3][2]), (2, 3));
+/// let edited = history[3].set(0, 10);
+/// assert_eq!((history[3][0], edited[0]), (1, 10));
+/// ```
+pub struct Vector<Mark: RefCountFamily, T> {
+    root: Option<Mark::Pointer<Node<Mark, T>>>,
+    len: usize,
+    /// The shift of the index giving the slot in the root, a multiple of `BITS`.
+    shift: usize,
+}
+
+enum Node<Mark: RefCountFamily, T> {
+    Branch(Vec<Mark::Pointer<Node<Mark, T>>>),
+    Leaf(Vec<T>),
+}
+
+impl<Mark: RefCountFamily, T: Clone> Clone for Node<Mark, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Branch(children) => Node::Branch(children.clone()),
+            Node::Leaf(values) => Node::Leaf(values.clone()),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Node<Mark, T> {
+    /// Creates the nodes down to a leaf holding only `value`.
+    fn path(shift: usize, value: T) -> Self {
+        if shift == 0 {
+            let mut values = Vec::with_capacity(WIDTH);
+            values.push(value);
+            Node::Leaf(values)
+        } else {
+            Node::Branch(vec![Mark::new(Self::path(shift - BITS, value))])
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Vector<Mark, T> {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            len: 0,
+            shift: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = self.root.as_deref()?;
+        let mut shift = self.shift;
+        loop {
+            match node {
+                Node::Branch(children) => {
+                    node = &children[(index >> shift) & MASK];
+                    shift -= BITS;
+                }
+                Node::Leaf(values) => return values.get(index & MASK),
+            }
+        }
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> Iter<'_, Mark, T> {
+        Iter {
+            vector: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+
+    /// Returns `true` if both vectors share all their nodes.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Mark::Pointer::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: Clone> Vector<Mark, T> {
+    /// Returns a vector with `value` appended.
+    pub fn push_back(&self, value: T) -> Self {
+        let mut vector = self.clone();
+        vector.push_back_mut(value);
+        vector
+    }
+
+    /// Returns a vector with the element at `index` replaced by `value`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn set(&self, index: usize, value: T) -> Self {
+        let mut vector = self.clone();
+        vector.set_mut(index, value);
+        vector
+    }
+
+    /// Appends `value`, copying only the shared nodes on its path.
+    pub fn push_back_mut(&mut self, value: T) {
+        let index = self.len;
+        match &mut self.root {
+            None => self.root = Some(Mark::new(Node::path(0, value))),
+            Some(root) if index == 1 << (self.shift + BITS) => {
+                let branch = vec![root.clone(), Mark::new(Node::path(self.shift, value))];
+                *root = Mark::new(Node::Branch(branch));
+                self.shift += BITS;
+            }
+            Some(root) => {
+                let mut node = root;
+                let mut shift = self.shift;
+                loop {
+                    match Mark::Pointer::make_mut(node) {
+                        Node::Branch(children) => {
+                            let slot = (index >> shift) & MASK;
+                            if slot == children.len() {
+                                children.push(Mark::new(Node::path(shift - BITS, value)));
+                                break;
+                            }
+                            node = &mut children[slot];
+                            shift -= BITS;
+                        }
+                        Node::Leaf(values) => {
+                            values.push(value);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Returns a mutable reference to the element at `index`, copying the shared
+    /// nodes on its path first.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = self.root.as_mut()?;
+        let mut shift = self.shift;
+        loop {
+            match Mark::Pointer::make_mut(node) {
+                Node::Branch(children) => {
+                    node = &mut children[(index >> shift) & MASK];
+                    shift -= BITS;
+                }
+                Node::Leaf(values) => return values.get_mut(index & MASK),
+            }
+        }
+    }
+
+    /// Replaces the element at `index` by `value`, copying only the shared nodes on
+    /// its path.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn set_mut(&mut self, index: usize, value: T) {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(slot) => *slot = value,
+            None => panic!("index {index} is out of bounds of length {len}"),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Clone for Vector<Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            shift: self.shift,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Default for Vector<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: RefCountFamily, T> Index<usize> for Vector<Mark, T> {
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index {index} is out of bounds of length {}", self.len),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: Clone> Extend<T> for Vector<Mark, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back_mut(value);
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: Clone> FromIterator<T> for Vector<Mark, T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vector = Self::new();
+        vector.extend(iter);
+        vector
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T> IntoIterator for &'a Vector<Mark, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, Mark, T>;
+    fn into_iter(self) -> Iter<'a, Mark, T> {
+        self.iter()
+    }
+}
+
+impl<Mark: RefCountFamily, T: fmt::Debug> fmt::Debug for Vector<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<Mark: RefCountFamily, T: PartialEq> PartialEq for Vector<Mark, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (self.ptr_eq(other) || self.iter().eq(other))
+    }
+}
+
+impl<Mark: RefCountFamily, T: Eq> Eq for Vector<Mark, T> {}
+
+impl<Mark: RefCountFamily, T: Hash> Hash for Vector<Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+/// An iterator over the elements of a [Vector].
+pub struct Iter<'a, Mark: RefCountFamily, T> {
+    vector: &'a Vector<Mark, T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, Mark: RefCountFamily, T> Iterator for Iter<'a, Mark, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.vector.get(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<Mark: RefCountFamily, T> DoubleEndedIterator for Iter<'_, Mark, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.vector.get(self.back)
+    }
+}
+
+impl<Mark: RefCountFamily, T> ExactSizeIterator for Iter<'_, Mark, T> {}
+
+impl<Mark: RefCountFamily, T> Clone for Iter<'_, Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            vector: self.vector,
+            front: self.front,
+            back: self.back,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_vector() {
+        fn actual_test<R: RefCountFamily>() {
+            let empty = Vector::<R, usize>::new();
+            assert_eq!((empty.get(0), empty.last()), (None, None));
+            let one = empty.push_back(1);
+            assert_eq!((empty.len(), one.len(), one[0]), (0, 1, 1));
+            assert_eq!(format!("{:?}", one.push_back(2)), "[1, 2]");
+
+            let len = WIDTH * WIDTH + 3;
+            let big: Vector<R, usize> = (0..len).collect();
+            assert_eq!((big.len(), big.shift), (len, 2 * BITS));
+            assert!((0..len).all(|i| big[i] == i));
+            assert!(big.iter().rev().copied().eq((0..len).rev()));
+            assert_eq!(big.get(len), None);
+
+            let edited = big.set(WIDTH + 1, 0);
+            assert_eq!((big[WIDTH + 1], edited[WIDTH + 1]), (WIDTH + 1, 0));
+            assert_ne!(big, edited);
+            let (Some(a), Some(b)) = (&big.root, &edited.root) else {
+                unreachable!()
+            };
+            let (Node::Branch(a), Node::Branch(b)) = (&**a, &**b) else {
+                unreachable!()
+            };
+            assert!(!R::Pointer::ptr_eq(&a[0], &b[0]));
+            assert!(R::Pointer::ptr_eq(&a[1], &b[1]));
+
+            let mut unique = edited.clone();
+            drop(edited);
+            let root = R::Pointer::as_ptr(unique.root.as_ref().unwrap());
+            unique.set_mut(0, 7);
+            *unique.get_mut(1).unwrap() = 8;
+            unique.push_back_mut(9);
+            assert_eq!(R::Pointer::as_ptr(unique.root.as_ref().unwrap()), root);
+            assert_eq!(
+                (unique[0], unique[1], unique.last(), big.first()),
+                (7, 8, Some(&9), Some(&0))
+            );
+            assert_eq!(big, (0..len).collect());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
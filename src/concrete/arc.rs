@@ -1,7 +1,12 @@
 use crate::traits::*;
 use std::{
+    future::Future,
+    mem::MaybeUninit,
     pin::Pin,
     sync::{Arc, Weak},
+    task::{Context, Poll},
+    thread,
+    time::Duration,
 };
 
 /// This marker type implements [RefCountFamily] for [Arc].
@@ -28,14 +33,171 @@ use std::{
 /// let foo = Foo::<ArcMark>::new("John Doe");
 /// assert_eq!(foo.name(), "John Doe");
 /// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ArcMark;
 
 impl RefCountFamily for ArcMark {
     type Pointer<T: ?Sized> = Arc<T>;
     type WeakPointer<T: ?Sized> = Weak<T>;
+    const IS_THREAD_SAFE: bool = true;
     fn new<T>(value: T) -> Self::Pointer<T> {
         Arc::new(value)
     }
+    fn from_box<T: ?Sized>(value: Box<T>) -> Self::Pointer<T> {
+        Arc::from(value)
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Self::Pointer<[T]> {
+        Arc::from(vec)
+    }
+    fn new_slice_clone<T: Clone>(slice: &[T]) -> Self::Pointer<[T]> {
+        Arc::from(slice)
+    }
+    fn new_str(s: &str) -> Self::Pointer<str> {
+        Arc::from(s)
+    }
+    fn from_string(s: String) -> Self::Pointer<str> {
+        Arc::from(s)
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Self::Pointer<[T]> {
+        iter.into_iter().collect()
+    }
+    fn unsize_array<T, const N: usize>(this: Arc<[T; N]>) -> Arc<[T]> {
+        this
+    }
+}
+
+impl ArcMark {
+    /// Blocks the current thread until `this` is the only pointer to its value,
+    /// then returns a mutable reference to it.
+    ///
+    /// This is [Arc::get_mut] retried with a backoff, so it only returns once
+    /// all other strong and weak pointers are gone. Waiting on a pointer that
+    /// the current thread keeps a clone of never returns.
+    ///
+    /// ```
+    /// # use cark_ref_counted::*;
+    /// # use std::{sync::Arc, thread};
+    /// let mut buffer = Arc::new(vec![0u8; 16]);
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let buffer = buffer.clone();
+    ///         thread::spawn(move || buffer.len())
+    ///     })
+    ///     .collect();
+    /// ArcMark::wait_until_unique(&mut buffer).fill(1);
+    /// # for worker in workers { worker.join().unwrap(); }
+    /// assert_eq!(buffer[0], 1);
+    /// ```
+    pub fn wait_until_unique<T: ?Sized>(this: &mut Arc<T>) -> &mut T {
+        let mut backoff = Duration::from_micros(1);
+        while Arc::get_mut(this).is_none() {
+            thread::park_timeout(backoff);
+            backoff = (backoff * 2).min(Duration::from_millis(1));
+        }
+        Arc::get_mut(this).unwrap()
+    }
+
+    /// The async counterpart of [ArcMark::wait_until_unique].
+    ///
+    /// The returned future spin-polls: `Arc` tells nobody when its other pointers
+    /// are dropped, so while they remain the future wakes itself right away, and
+    /// keeps its task busy until then. It suits short waits; for longer ones, have
+    /// the owners of the other pointers signal their drop, for instance through a
+    /// channel.
+    pub fn wait_until_unique_async<T: ?Sized>(this: &mut Arc<T>) -> WaitUntilUnique<'_, T> {
+        WaitUntilUnique(Some(this))
+    }
+}
+
+/// The future returned by [ArcMark::wait_until_unique_async], spin-polling the
+/// counts of the `Arc`.
+pub struct WaitUntilUnique<'a, T: ?Sized>(Option<&'a mut Arc<T>>);
+
+impl<'a, T: ?Sized> Future for WaitUntilUnique<'a, T> {
+    type Output = &'a mut T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<&'a mut T> {
+        let this = self.0.take().expect("polled after completion");
+        if Arc::get_mut(this).is_some() {
+            Poll::Ready(Arc::get_mut(this).unwrap())
+        } else {
+            self.0 = Some(this);
+            // Nothing else will wake the task once the other pointers are dropped.
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl ThreadSafeFamily for ArcMark {
+    type SyncPointer<T: ?Sized + Send + Sync> = Arc<T>;
+
+    fn into_sync<T: ?Sized + Send + Sync>(pointer: Arc<T>) -> Arc<T> {
+        pointer
+    }
+
+    fn from_sync<T: ?Sized + Send + Sync>(pointer: Arc<T>) -> Arc<T> {
+        pointer
+    }
+}
+
+impl<U: ?Sized> FromUnsized<U> for ArcMark
+where
+    for<'a> Arc<U>: From<&'a U>,
+{
+    fn from_unsized(value: &U) -> Arc<U> {
+        Arc::from(value)
+    }
+}
+
+impl TryNewFamily for ArcMark {
+    fn try_new<T>(value: T) -> Result<Arc<T>, T> {
+        Ok(Arc::new(value))
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl TryCyclicFamily for ArcMark {
+    fn try_new_cyclic<T, F, E>(data_fn: F) -> Result<Arc<T>, E>
+    where
+        F: FnOnce(&Weak<T>) -> Result<T, E>,
+    {
+        // Unlike `new_cyclic`, the unique pointer can be dropped without ever having
+        // been shared, so its weak pointers never upgrade.
+        let mut unique = std::sync::UniqueArc::new(MaybeUninit::<T>::uninit());
+        let weak = std::sync::UniqueArc::downgrade(&unique);
+        // SAFETY: `MaybeUninit<T>` has the layout of `T`.
+        let weak = unsafe { Weak::from_raw(Weak::into_raw(weak).cast::<T>()) };
+        MaybeUninit::write(&mut *unique, data_fn(&weak)?);
+        // SAFETY: the value was just written.
+        Ok(unsafe { std::sync::UniqueArc::into_arc(unique).assume_init() })
+    }
+}
+
+impl UninitConstruct for ArcMark {
+    fn new_uninit<T>() -> Arc<MaybeUninit<T>> {
+        Arc::new_uninit()
+    }
+
+    fn new_uninit_slice<T>(len: usize) -> Arc<[MaybeUninit<T>]> {
+        Arc::new_uninit_slice(len)
+    }
+
+    fn new_zeroed<T>() -> Arc<MaybeUninit<T>> {
+        Arc::new_zeroed()
+    }
+
+    fn new_zeroed_slice<T>(len: usize) -> Arc<[MaybeUninit<T>]> {
+        Arc::new_zeroed_slice(len)
+    }
+
+    unsafe fn assume_init<T>(this: Arc<MaybeUninit<T>>) -> Arc<T> {
+        this.assume_init()
+    }
+
+    unsafe fn assume_init_slice<T>(this: Arc<[MaybeUninit<T>]>) -> Arc<[T]> {
+        this.assume_init()
+    }
 }
 
 impl<T: ?Sized> RefCounted<T> for Arc<T> {
@@ -46,6 +208,10 @@ impl<T: ?Sized> RefCounted<T> for Arc<T> {
         Self::as_ptr(this)
     }
 
+    fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Self::ptr_eq(this, other)
+    }
+
     fn downgrade(this: &Self) -> Weak<T> {
         Self::downgrade(this)
     }
@@ -85,6 +251,15 @@ impl<T: ?Sized> RefCounted<T> for Arc<T> {
         Self::get_mut(this)
     }
 
+    unsafe fn get_mut_unchecked(this: &mut Self) -> &mut T {
+        #[cfg(feature = "nightly")]
+        return Self::get_mut_unchecked(this);
+        // SAFETY: the pointer to the value is derived from the mutable pointer to
+        // the allocation, and the caller guarantees that it is not aliased.
+        #[cfg(not(feature = "nightly"))]
+        return &mut *(Self::as_ptr(this) as *mut T);
+    }
+
     fn new_cyclic<F>(data_fn: F) -> Self
     where
         F: FnOnce(&Weak<T>) -> T,
@@ -106,12 +281,33 @@ impl<T: ?Sized> RefCounted<T> for Arc<T> {
     {
         Self::try_unwrap(this)
     }
+
+    fn into_inner(this: Self) -> Option<T>
+    where
+        T: Sized,
+    {
+        Self::into_inner(this)
+    }
+
+    fn unwrap_or_clone(this: Self) -> T
+    where
+        T: Clone,
+    {
+        Self::unwrap_or_clone(this)
+    }
 }
 
 impl<T: ?Sized> WeakPointer<T> for Weak<T> {
     type StrongMark = ArcMark;
     type StrongPointer = Arc<T>;
 
+    fn new() -> Self
+    where
+        T: Sized,
+    {
+        Self::new()
+    }
+
     fn as_ptr(&self) -> *const T {
         self.as_ptr()
     }
@@ -0,0 +1,160 @@
+//! A bridge to [archery](https://docs.rs/archery).
+//!
+//! [FamilyKind] turns any [RefCountFamily] into an archery `SharedPointerKind`, so
+//! libraries built on archery can use the pointers of this crate. See
+//! the `persistent` module, behind the `rpds` feature.
+//!
+//! The other direction is not provided: `archery::SharedPointer` only holds sized
+//! values and has no weak pointers, so it can't be a [RefCountFamily] nor a
+//! [crate::strong::StrongFamily], which both need `Pointer<T: ?Sized>`.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! use archery::SharedPointer;
+//! fn shared_twice<R: RefCountFamily>() -> usize {
+//!     let a = SharedPointer::<_, FamilyKind<R>>::new(1);
+//!     let b = SharedPointer::clone(&a);
+//!     *a + *b + SharedPointer::strong_count(&a)
+//! }
+//! assert_eq!(shared_twice::<RcMark>(), 4);
+//! ```
+
+use crate::traits::*;
+use std::{fmt, marker::PhantomData, mem::ManuallyDrop};
+
+/// An archery `SharedPointerKind` backed by `Mark::Pointer`.
+///
+/// The pointer is stored type-erased, as returned by [RefCounted::into_raw].
+/// `FamilyKind<Mark>` is `Send` and `Sync` when `Mark` is a [ThreadSafeFamily].
+pub struct FamilyKind<Mark: RefCountFamily> {
+    ptr: *const (),
+    _mark: PhantomData<Mark>,
+}
+
+impl<Mark: RefCountFamily> FamilyKind<Mark> {
+    fn from_pointer<T>(pointer: Mark::Pointer<T>) -> Self {
+        Self {
+            ptr: Mark::Pointer::into_raw(pointer) as *const (),
+            _mark: PhantomData,
+        }
+    }
+
+    /// # Safety
+    /// `self` must have been constructed with the same `T`, and the returned pointer
+    /// must not be dropped.
+    unsafe fn pointer<T>(&self) -> ManuallyDrop<Mark::Pointer<T>> {
+        ManuallyDrop::new(Mark::Pointer::from_raw(self.ptr as *const T))
+    }
+}
+
+unsafe impl<Mark: ThreadSafeFamily> Send for FamilyKind<Mark> {}
+unsafe impl<Mark: ThreadSafeFamily> Sync for FamilyKind<Mark> {}
+
+impl<Mark: RefCountFamily> fmt::Debug for FamilyKind<Mark> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FamilyKind").field(&self.ptr).finish()
+    }
+}
+
+// Versions 0.5 and 1 of archery have the same `SharedPointerKind`.
+macro_rules! impl_shared_pointer_kind {
+    ($kind:path) => {
+        unsafe impl<Mark: RefCountFamily> $kind for FamilyKind<Mark> {
+            fn new<T>(v: T) -> Self {
+                Self::from_pointer(Mark::new(v))
+            }
+
+            fn from_box<T>(v: Box<T>) -> Self {
+                Self::from_pointer(Mark::from_box(v))
+            }
+
+            unsafe fn as_ptr<T>(&self) -> *const T {
+                self.ptr as *const T
+            }
+
+            unsafe fn deref<T>(&self) -> &T {
+                &*(self.ptr as *const T)
+            }
+
+            unsafe fn try_unwrap<T>(self) -> Result<T, Self> {
+                let this = ManuallyDrop::new(self);
+                let pointer = ManuallyDrop::into_inner(this.pointer::<T>());
+                Mark::Pointer::try_unwrap(pointer).map_err(Self::from_pointer)
+            }
+
+            unsafe fn get_mut<T>(&mut self) -> Option<&mut T> {
+                let mut pointer = self.pointer::<T>();
+                Mark::Pointer::get_mut(&mut pointer).map(|value| &mut *(value as *mut T))
+            }
+
+            unsafe fn make_mut<T: Clone>(&mut self) -> &mut T {
+                let mut pointer = self.pointer::<T>();
+                let value = Mark::Pointer::make_mut(&mut pointer) as *mut T;
+                // make_mut may have moved the value to a new allocation
+                self.ptr = Mark::Pointer::as_ptr(&pointer) as *const ();
+                &mut *value
+            }
+
+            unsafe fn strong_count<T>(&self) -> usize {
+                Mark::Pointer::strong_count(&self.pointer::<T>())
+            }
+
+            unsafe fn clone<T>(&self) -> Self {
+                Mark::Pointer::<T>::increment_strong_count(self.ptr as *const T);
+                Self {
+                    ptr: self.ptr,
+                    _mark: PhantomData,
+                }
+            }
+
+            unsafe fn drop<T>(&mut self) {
+                Mark::Pointer::<T>::decrement_strong_count(self.ptr as *const T);
+            }
+        }
+    };
+}
+
+impl_shared_pointer_kind!(::archery::SharedPointerKind);
+// rpds 0.13 is built on archery 0.5.
+#[cfg(feature = "rpds")]
+impl_shared_pointer_kind!(::archery05::SharedPointerKind);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use ::archery::SharedPointer;
+
+    #[test]
+    fn test_family_kind() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut a = SharedPointer::<_, FamilyKind<R>>::new(vec![1]);
+            assert_eq!(SharedPointer::get_mut(&mut a), Some(&mut vec![1]));
+            let b = SharedPointer::clone(&a);
+            assert!(SharedPointer::ptr_eq(&a, &b));
+            assert_eq!(SharedPointer::strong_count(&a), 2);
+            assert!(SharedPointer::get_mut(&mut a).is_none());
+            SharedPointer::make_mut(&mut a).push(2);
+            assert!(!SharedPointer::ptr_eq(&a, &b));
+            assert_eq!(SharedPointer::as_ptr(&a), &*a as *const _);
+            assert_eq!(*b, [1]);
+            let b = SharedPointer::try_unwrap(b).ok().unwrap();
+            assert_eq!(b, [1]);
+            let c = SharedPointer::clone(&a);
+            let a = SharedPointer::try_unwrap(a).err().unwrap();
+            drop(c);
+            assert_eq!(SharedPointer::try_unwrap(a).ok().unwrap(), [1, 2]);
+            let boxed = SharedPointer::<i32, FamilyKind<R>>::from(Box::new(3));
+            assert_eq!(*boxed, 3);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_family_kind_send() {
+        let a = SharedPointer::<_, FamilyKind<ArcMark>>::new(5);
+        let b = SharedPointer::clone(&a);
+        assert_eq!(std::thread::spawn(move || *b).join().unwrap(), 5);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::strong::*;
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+/// A [loom::sync::Arc] with the formatting [StrongFamily] requires.
+pub struct LoomArc<T: ?Sized>(pub loom::sync::Arc<T>);
+
+impl<T: ?Sized> Deref for LoomArc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> Clone for LoomArc<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for LoomArc<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for LoomArc<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for LoomArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for LoomArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&loom::sync::Arc::as_ptr(&self.0), f)
+    }
+}
+
+/// This marker type implements [StrongFamily] for [loom::sync::Arc], so family
+/// generic code can be model checked with loom.
+///
+/// loom has no `Weak`, so there is no [crate::RefCountFamily] for it. Like all loom
+/// types, the pointers can only be used inside `loom::model`.
+///
+/// ```
+/// # use cark_ref_counted::{strong::*, LoomArcMark};
+/// fn shared<S: StrongFamily>() -> usize {
+///     let a = S::new(1);
+///     let b = a.clone();
+///     S::strong_count(&b)
+/// }
+/// loom::model(|| assert_eq!(shared::<LoomArcMark>(), 2));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoomArcMark;
+
+impl StrongFamily for LoomArcMark {
+    type Pointer<T: ?Sized> = LoomArc<T>;
+    const IS_THREAD_SAFE: bool = true;
+    fn new<T>(value: T) -> LoomArc<T> {
+        LoomArc(loom::sync::Arc::new(value))
+    }
+    fn new_slice<T>(vec: Vec<T>) -> LoomArc<[T]> {
+        LoomArc(loom::sync::Arc::from_std(vec.into()))
+    }
+    fn new_str(s: &str) -> LoomArc<str> {
+        LoomArc(loom::sync::Arc::from_std(s.into()))
+    }
+    fn from_string(s: String) -> LoomArc<str> {
+        LoomArc(loom::sync::Arc::from_std(s.into()))
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> LoomArc<[T]> {
+        LoomArc(loom::sync::Arc::from_std(iter.into_iter().collect()))
+    }
+
+    fn as_ptr<T: ?Sized>(this: &LoomArc<T>) -> *const T {
+        loom::sync::Arc::as_ptr(&this.0)
+    }
+
+    fn ptr_eq<T: ?Sized>(this: &LoomArc<T>, other: &LoomArc<T>) -> bool {
+        loom::sync::Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    fn strong_count<T: ?Sized>(this: &LoomArc<T>) -> usize {
+        loom::sync::Arc::strong_count(&this.0)
+    }
+
+    fn get_mut<T: ?Sized>(this: &mut LoomArc<T>) -> Option<&mut T> {
+        loom::sync::Arc::get_mut(&mut this.0)
+    }
+
+    fn make_mut<T: Clone>(this: &mut LoomArc<T>) -> &mut T {
+        // loom's Arc has no make_mut, this is what std's does without weak pointers
+        if loom::sync::Arc::get_mut(&mut this.0).is_none() {
+            *this = Self::new((**this).clone());
+        }
+        loom::sync::Arc::get_mut(&mut this.0).unwrap()
+    }
+
+    fn into_raw<T: ?Sized>(this: LoomArc<T>) -> *const T {
+        loom::sync::Arc::into_raw(this.0)
+    }
+
+    unsafe fn from_raw<T: ?Sized>(ptr: *const T) -> LoomArc<T> {
+        LoomArc(loom::sync::Arc::from_raw(ptr))
+    }
+
+    fn try_unwrap<T>(this: LoomArc<T>) -> Result<T, LoomArc<T>> {
+        loom::sync::Arc::try_unwrap(this.0).map_err(LoomArc)
+    }
+
+    fn unwrap_or_clone<T: Clone>(this: LoomArc<T>) -> T {
+        Self::try_unwrap(this).unwrap_or_else(|this| (*this).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loom_arc() {
+        loom::model(crate::strong::tests::actual_test::<LoomArcMark>);
+    }
+
+    #[test]
+    fn test_loom_arc_threads() {
+        loom::model(|| {
+            let a = LoomArcMark::new(1);
+            let b = a.clone();
+            let handle = loom::thread::spawn(move || LoomArcMark::unwrap_or_clone(b));
+            assert_eq!(LoomArcMark::unwrap_or_clone(a), 1);
+            assert_eq!(handle.join().unwrap(), 1);
+        });
+    }
+}
@@ -1,2 +1,10 @@
 pub mod arc;
+#[cfg(feature = "archery")]
+pub mod archery;
+#[cfg(feature = "loom")]
+pub mod loom;
+#[cfg(feature = "portable-atomic-util")]
+pub mod portable_arc;
 pub mod rc;
+#[cfg(feature = "triomphe")]
+pub mod triomphe;
@@ -0,0 +1,90 @@
+use crate::strong::*;
+use portable_atomic_util::Arc;
+
+/// This marker type implements [StrongFamily] for [portable_atomic_util::Arc], which
+/// works on targets without native atomic read-modify-write operations.
+///
+/// This crate needs `std`, so it only helps hosted targets lacking those
+/// operations, not `no_std` embedded ones.
+///
+/// Its weak pointers only expose raw pointers for sized values, so there is no
+/// [crate::RefCountFamily] for it.
+///
+/// ```
+/// # use cark_ref_counted::{strong::*, PortableArcMark};
+/// struct Foo<S: StrongFamily> {
+///     name: S::Pointer<String>,
+/// }
+/// let foo = Foo::<PortableArcMark> {
+///     name: PortableArcMark::new("John Doe".to_owned()),
+/// };
+/// assert_eq!(*foo.name, "John Doe");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PortableArcMark;
+
+impl StrongFamily for PortableArcMark {
+    type Pointer<T: ?Sized> = Arc<T>;
+    const IS_THREAD_SAFE: bool = true;
+    fn new<T>(value: T) -> Arc<T> {
+        Arc::new(value)
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Arc<[T]> {
+        Arc::from(vec)
+    }
+    fn new_str(s: &str) -> Arc<str> {
+        Arc::from(s)
+    }
+    fn from_string(s: String) -> Arc<str> {
+        Arc::from(s)
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Arc<[T]> {
+        iter.into_iter().collect()
+    }
+
+    fn as_ptr<T: ?Sized>(this: &Arc<T>) -> *const T {
+        Arc::as_ptr(this)
+    }
+
+    fn ptr_eq<T: ?Sized>(this: &Arc<T>, other: &Arc<T>) -> bool {
+        Arc::ptr_eq(this, other)
+    }
+
+    fn strong_count<T: ?Sized>(this: &Arc<T>) -> usize {
+        Arc::strong_count(this)
+    }
+
+    fn get_mut<T: ?Sized>(this: &mut Arc<T>) -> Option<&mut T> {
+        Arc::get_mut(this)
+    }
+
+    fn make_mut<T: Clone>(this: &mut Arc<T>) -> &mut T {
+        Arc::make_mut(this)
+    }
+
+    fn into_raw<T: ?Sized>(this: Arc<T>) -> *const T {
+        Arc::into_raw(this)
+    }
+
+    unsafe fn from_raw<T: ?Sized>(ptr: *const T) -> Arc<T> {
+        Arc::from_raw(ptr)
+    }
+
+    fn try_unwrap<T>(this: Arc<T>) -> Result<T, Arc<T>> {
+        Arc::try_unwrap(this)
+    }
+
+    fn unwrap_or_clone<T: Clone>(this: Arc<T>) -> T {
+        Arc::unwrap_or_clone(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_arc() {
+        crate::strong::tests::actual_test::<PortableArcMark>();
+    }
+}
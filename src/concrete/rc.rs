@@ -1,5 +1,6 @@
 use crate::traits::*;
 use std::{
+    mem::{ManuallyDrop, MaybeUninit},
     pin::Pin,
     rc::{Rc, Weak},
 };
@@ -28,14 +29,109 @@ use std::{
 /// let foo = Foo::<RcMark>::new("John Doe");
 /// assert_eq!(foo.name(), "John Doe");
 /// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RcMark;
 
 impl RefCountFamily for RcMark {
     type Pointer<T: ?Sized> = Rc<T>;
     type WeakPointer<T: ?Sized> = Weak<T>;
+    const IS_THREAD_SAFE: bool = false;
     fn new<T>(value: T) -> Self::Pointer<T> {
         Rc::new(value)
     }
+    fn from_box<T: ?Sized>(value: Box<T>) -> Self::Pointer<T> {
+        Rc::from(value)
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Self::Pointer<[T]> {
+        Rc::from(vec)
+    }
+    fn new_slice_clone<T: Clone>(slice: &[T]) -> Self::Pointer<[T]> {
+        Rc::from(slice)
+    }
+    fn new_str(s: &str) -> Self::Pointer<str> {
+        Rc::from(s)
+    }
+    fn from_string(s: String) -> Self::Pointer<str> {
+        Rc::from(s)
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Self::Pointer<[T]> {
+        iter.into_iter().collect()
+    }
+    fn unsize_array<T, const N: usize>(this: Rc<[T; N]>) -> Rc<[T]> {
+        this
+    }
+}
+
+impl<U: ?Sized> FromUnsized<U> for RcMark
+where
+    for<'a> Rc<U>: From<&'a U>,
+{
+    fn from_unsized(value: &U) -> Rc<U> {
+        Rc::from(value)
+    }
+}
+
+impl TryNewFamily for RcMark {
+    fn try_new<T>(value: T) -> Result<Rc<T>, T> {
+        Ok(Rc::new(value))
+    }
+}
+
+impl TryCyclicFamily for RcMark {
+    fn try_new_cyclic<T, F, E>(data_fn: F) -> Result<Rc<T>, E>
+    where
+        F: FnOnce(&Weak<T>) -> Result<T, E>,
+    {
+        let mut error = None;
+        let this = Rc::<MaybeUninit<T>>::new_cyclic(|weak| {
+            // SAFETY: `MaybeUninit<T>` has the layout of `T`, and the weak count taken
+            // over by `from_raw` is never given back, as the pointer is not dropped.
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak.as_ptr().cast::<T>()) });
+            data_fn(&weak).map_or_else(
+                |e| {
+                    error = Some(e);
+                    MaybeUninit::uninit()
+                },
+                MaybeUninit::new,
+            )
+        });
+        match error {
+            // SAFETY: the value was written by `data_fn`.
+            None => Ok(unsafe { this.assume_init() }),
+            // The weak pointers can't leave this thread, so none can upgrade before
+            // the uninitialized value is dropped, which does nothing.
+            Some(e) => {
+                drop(this);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl UninitConstruct for RcMark {
+    fn new_uninit<T>() -> Rc<MaybeUninit<T>> {
+        Rc::new_uninit()
+    }
+
+    fn new_uninit_slice<T>(len: usize) -> Rc<[MaybeUninit<T>]> {
+        Rc::new_uninit_slice(len)
+    }
+
+    fn new_zeroed<T>() -> Rc<MaybeUninit<T>> {
+        Rc::new_zeroed()
+    }
+
+    fn new_zeroed_slice<T>(len: usize) -> Rc<[MaybeUninit<T>]> {
+        Rc::new_zeroed_slice(len)
+    }
+
+    unsafe fn assume_init<T>(this: Rc<MaybeUninit<T>>) -> Rc<T> {
+        this.assume_init()
+    }
+
+    unsafe fn assume_init_slice<T>(this: Rc<[MaybeUninit<T>]>) -> Rc<[T]> {
+        this.assume_init()
+    }
 }
 
 impl<T: ?Sized> RefCounted<T> for Rc<T> {
@@ -46,6 +142,10 @@ impl<T: ?Sized> RefCounted<T> for Rc<T> {
         Self::as_ptr(this)
     }
 
+    fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Self::ptr_eq(this, other)
+    }
+
     fn downgrade(this: &Self) -> Weak<T> {
         Self::downgrade(this)
     }
@@ -85,6 +185,15 @@ impl<T: ?Sized> RefCounted<T> for Rc<T> {
         Self::get_mut(this)
     }
 
+    unsafe fn get_mut_unchecked(this: &mut Self) -> &mut T {
+        #[cfg(feature = "nightly")]
+        return Self::get_mut_unchecked(this);
+        // SAFETY: the pointer to the value is derived from the mutable pointer to
+        // the allocation, and the caller guarantees that it is not aliased.
+        #[cfg(not(feature = "nightly"))]
+        return &mut *(Self::as_ptr(this) as *mut T);
+    }
+
     fn new_cyclic<F>(data_fn: F) -> Self
     where
         F: FnOnce(&Weak<T>) -> T,
@@ -106,12 +215,33 @@ impl<T: ?Sized> RefCounted<T> for Rc<T> {
     {
         Self::try_unwrap(this)
     }
+
+    fn into_inner(this: Self) -> Option<T>
+    where
+        T: Sized,
+    {
+        Self::into_inner(this)
+    }
+
+    fn unwrap_or_clone(this: Self) -> T
+    where
+        T: Clone,
+    {
+        Self::unwrap_or_clone(this)
+    }
 }
 
 impl<T: ?Sized> WeakPointer<T> for Weak<T> {
     type StrongMark = RcMark;
     type StrongPointer = Rc<T>;
 
+    fn new() -> Self
+    where
+        T: Sized,
+    {
+        Self::new()
+    }
+
     fn as_ptr(&self) -> *const T {
         self.as_ptr()
     }
@@ -0,0 +1,113 @@
+use crate::{strong::*, thin::ThinFamily};
+use triomphe::{Arc, ThinArc};
+
+/// This marker type implements [StrongFamily] for [triomphe::Arc].
+///
+/// `triomphe::Arc` has no weak count, so there is no [crate::RefCountFamily] for it.
+/// Its [ThinFamily] pointer is [triomphe::ThinArc].
+///
+/// ```
+/// # use cark_ref_counted::{strong::*, TriompheMark};
+/// struct Foo<S: StrongFamily> {
+///     name: S::Pointer<String>,
+/// }
+/// let foo = Foo::<TriompheMark> {
+///     name: TriompheMark::new("John Doe".to_owned()),
+/// };
+/// assert_eq!(*foo.name, "John Doe");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TriompheMark;
+
+impl StrongFamily for TriompheMark {
+    type Pointer<T: ?Sized> = Arc<T>;
+    const IS_THREAD_SAFE: bool = true;
+    fn new<T>(value: T) -> Arc<T> {
+        Arc::new(value)
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Arc<[T]> {
+        Arc::from(vec)
+    }
+    fn new_str(s: &str) -> Arc<str> {
+        Arc::from(s)
+    }
+    fn from_string(s: String) -> Arc<str> {
+        Arc::from(s)
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Arc<[T]> {
+        iter.into_iter().collect()
+    }
+
+    fn as_ptr<T: ?Sized>(this: &Arc<T>) -> *const T {
+        Arc::as_ptr(this)
+    }
+
+    fn ptr_eq<T: ?Sized>(this: &Arc<T>, other: &Arc<T>) -> bool {
+        Arc::ptr_eq(this, other)
+    }
+
+    fn strong_count<T: ?Sized>(this: &Arc<T>) -> usize {
+        Arc::count(this)
+    }
+
+    fn get_mut<T: ?Sized>(this: &mut Arc<T>) -> Option<&mut T> {
+        Arc::get_mut(this)
+    }
+
+    fn make_mut<T: Clone>(this: &mut Arc<T>) -> &mut T {
+        Arc::make_mut(this)
+    }
+
+    fn into_raw<T: ?Sized>(this: Arc<T>) -> *const T {
+        Arc::into_raw(this)
+    }
+
+    unsafe fn from_raw<T: ?Sized>(ptr: *const T) -> Arc<T> {
+        Arc::from_raw(ptr)
+    }
+
+    fn try_unwrap<T>(this: Arc<T>) -> Result<T, Arc<T>> {
+        Arc::try_unwrap(this)
+    }
+
+    fn unwrap_or_clone<T: Clone>(this: Arc<T>) -> T {
+        Arc::unwrap_or_clone(this)
+    }
+}
+
+impl ThinFamily for TriompheMark {
+    type ThinPointer<H, T> = ThinArc<H, T>;
+    fn from_header_and_iter<H, T, I: ExactSizeIterator<Item = T>>(
+        header: H,
+        items: I,
+    ) -> ThinArc<H, T> {
+        ThinArc::from_header_and_iter(header, items)
+    }
+
+    fn header<'a, H: 'a, T: 'a>(this: &'a ThinArc<H, T>) -> &'a H {
+        &this.header.header
+    }
+
+    fn slice<'a, H: 'a, T: 'a>(this: &'a ThinArc<H, T>) -> &'a [T] {
+        &this.slice
+    }
+
+    fn thin_ptr_eq<H, T>(this: &ThinArc<H, T>, other: &ThinArc<H, T>) -> bool {
+        this.ptr() == other.ptr()
+    }
+
+    fn thin_strong_count<H, T>(this: &ThinArc<H, T>) -> usize {
+        ThinArc::strong_count(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triomphe() {
+        crate::strong::tests::actual_test::<TriompheMark>();
+        crate::thin::tests::actual_test::<TriompheMark>();
+    }
+}
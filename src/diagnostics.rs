@@ -0,0 +1,222 @@
+use crate::adapters::*;
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    collections::HashMap,
+    fmt::{self, Write},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// A [WrapMark] decorator registering its live allocations, to hunt leaks such as
+/// reference cycles.
+///
+/// `DiagnosticMark<Mark>` behaves like `Mark`. Each allocation is registered with
+/// the type of its value and its strong and weak counts, until its last pointer
+/// is dropped. A backtrace of the allocation is captured when enabled by the
+/// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables, see
+/// [Backtrace::capture].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Node<R: LockFamily> {
+///     next: R::Lock<Option<R::Pointer<Node<R>>>>,
+/// }
+/// type D = DiagnosticMark<RcMark>;
+/// let a = D::new(Node::<D> { next: D::new_lock(None) });
+/// let b = D::new(Node::<D> { next: D::new_lock(Some(a.clone())) });
+/// *D::write(&a.next) = Some(b.clone());
+/// let b_id = live_allocation(&b).unwrap();
+/// drop((a, b));
+/// let leak = live_allocations().into_iter().find(|l| l.address == b_id.address).unwrap();
+/// assert!(leak.type_name.contains("Node<"));
+/// assert_eq!((leak.strong, leak.weak), (1, 0));
+/// assert!(report_live().contains("Node"));
+/// ```
+pub type DiagnosticMark<Mark> = WrapMark<Mark, DiagnosticHooks>;
+
+/// The strong pointer of [DiagnosticMark].
+pub type Diagnosed<Mark, T> = Wrap<Mark, DiagnosticHooks, T>;
+
+/// An allocation of a [DiagnosticMark] with live pointers, as listed by
+/// [live_allocations].
+#[derive(Clone, Debug)]
+pub struct LiveAllocation {
+    pub address: usize,
+    pub type_name: &'static str,
+    pub strong: usize,
+    /// The number of weak pointers, not counting the one shared by the strong
+    /// pointers.
+    pub weak: usize,
+    /// Where the allocation was made, if backtraces are enabled.
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+impl fmt::Display for LiveAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {:#x}: {} strong, {} weak",
+            self.type_name, self.address, self.strong, self.weak
+        )?;
+        if let Some(backtrace) = &self.backtrace {
+            write!(f, "\n{backtrace}")?;
+        }
+        Ok(())
+    }
+}
+
+static REGISTRY: Mutex<Option<HashMap<usize, LiveAllocation>>> = Mutex::new(None);
+
+/// Locks the registry, ignoring poisoning since each update is a single step.
+fn registry() -> MutexGuard<'static, Option<HashMap<usize, LiveAllocation>>> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Registers a new allocation, replacing any freed one at the same address.
+fn register(addr: *const (), strong: usize, weak: usize) {
+    let backtrace = Backtrace::capture();
+    let backtrace = (backtrace.status() == BacktraceStatus::Captured).then(|| Arc::new(backtrace));
+    let allocation = LiveAllocation {
+        address: addr as usize,
+        type_name: "?",
+        strong,
+        weak,
+        backtrace,
+    };
+    registry()
+        .get_or_insert_with(HashMap::new)
+        .insert(addr as usize, allocation);
+}
+
+/// Updates the counts of a registered allocation, unregistering it when no
+/// pointer is left.
+fn update(addr: *const (), f: impl FnOnce(&mut LiveAllocation)) {
+    let mut registry = registry();
+    let Some(registry) = registry.as_mut() else {
+        return;
+    };
+    if let Some(allocation) = registry.get_mut(&(addr as usize)) {
+        f(allocation);
+        if allocation.strong == 0 && allocation.weak == 0 {
+            registry.remove(&(addr as usize));
+        }
+    }
+}
+
+/// The [WrapHooks] of [DiagnosticMark], maintaining the registry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DiagnosticHooks;
+
+impl WrapHooks for DiagnosticHooks {
+    fn on_new(addr: *const (), _counts: RefCounts) {
+        register(addr, 1, 0)
+    }
+    fn on_cyclic_start(addr: *const (), _counts: RefCounts) {
+        register(addr, 0, 1)
+    }
+    fn on_type_name(addr: *const (), type_name: &'static str, _counts: RefCounts) {
+        update(addr, |a| a.type_name = type_name)
+    }
+    fn on_cyclic_end(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| {
+            a.strong = 1;
+            a.weak -= 1;
+        })
+    }
+    fn on_clone(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| a.strong += 1)
+    }
+    fn on_drop(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| a.strong -= 1)
+    }
+    fn on_downgrade(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| a.weak += 1)
+    }
+    fn on_clone_weak(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| a.weak += 1)
+    }
+    fn on_drop_weak(addr: *const (), _counts: RefCounts) {
+        update(addr, |a| a.weak -= 1)
+    }
+    fn on_upgrade(addr: *const (), upgraded: bool, _counts: RefCounts) {
+        if upgraded {
+            update(addr, |a| a.strong += 1)
+        }
+    }
+}
+
+/// Returns the registered allocations of all the [DiagnosticMark]s, including the
+/// ones whose value was dropped but which weak pointers keep, ordered by address.
+pub fn live_allocations() -> Vec<LiveAllocation> {
+    let mut allocations: Vec<_> = registry()
+        .iter()
+        .flat_map(|registry| registry.values().cloned())
+        .collect();
+    allocations.sort_by_key(|a| a.address);
+    allocations
+}
+
+/// Returns the registered allocation of `pointer`.
+pub fn live_allocation<Mark: crate::RefCountFamily, T: ?Sized>(
+    pointer: &Diagnosed<Mark, T>,
+) -> Option<LiveAllocation> {
+    let addr = crate::RefCounted::as_ptr(pointer) as *const () as usize;
+    registry().as_ref()?.get(&addr).cloned()
+}
+
+/// Describes the allocations whose value is still alive, one per line, with
+/// their backtrace if captured.
+pub fn report_live() -> String {
+    let mut report = String::new();
+    for allocation in live_allocations().iter().filter(|a| a.strong > 0) {
+        writeln!(report, "{allocation}").unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{traits::*, ArcMark, RcMark};
+
+    #[test]
+    fn test_diagnostics() {
+        fn actual_test<Mark: RefCountFamily>() {
+            struct Node<Mark: RefCountFamily> {
+                me: <DiagnosticMark<Mark> as RefCountFamily>::WeakPointer<Self>,
+            }
+
+            let a = DiagnosticMark::<Mark>::new(7u8);
+            let b = a.clone();
+            let weak = Diagnosed::downgrade(&a);
+            let live = live_allocation(&a).unwrap();
+            assert_eq!((live.type_name, live.strong, live.weak), ("u8", 2, 1));
+            let address = live.address;
+            drop((a, b));
+            let live = live_allocations();
+            let dead = live.iter().find(|a| a.address == address).unwrap();
+            assert_eq!((dead.strong, dead.weak), (0, 1));
+            assert!(!report_live().contains(&format!("{address:#x}")));
+            drop(weak);
+            assert!(live_allocations().iter().all(|a| a.address != address));
+
+            let node = Diagnosed::<Mark, Node<Mark>>::new_cyclic(|me| Node { me: me.clone() });
+            let live = live_allocation(&node).unwrap();
+            assert!(
+                live.type_name
+                    .ends_with("Node<cark_ref_counted::concrete::rc::RcMark>")
+                    || live
+                        .type_name
+                        .ends_with("Node<cark_ref_counted::concrete::arc::ArcMark>")
+            );
+            assert_eq!((live.strong, live.weak), (1, 1));
+            assert!(report_live().contains(&format!("{:#x}", live.address)));
+            assert!(node.me.upgrade().is_some());
+            assert_eq!(live_allocation(&node).unwrap().strong, 1);
+            drop(node);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
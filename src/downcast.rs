@@ -0,0 +1,82 @@
+use crate::{traits::*, ArcMark, RcMark};
+use std::{any::Any, rc::Rc, sync::Arc};
+
+/// Downcasting of pointers to `dyn Any`.
+///
+/// It is implemented for `Rc<dyn Any>`, `Arc<dyn Any>` and `Arc<dyn Any + Send + Sync>`,
+/// so family generic code can bound on `R::Pointer<dyn Any>: Downcast<Mark = R>`.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::any::Any;
+/// fn get_string<R: RefCountFamily>(value: R::Pointer<dyn Any>) -> Option<R::Pointer<String>>
+/// where
+///     R::Pointer<dyn Any>: Downcast<Mark = R>,
+/// {
+///     value.downcast::<String>().ok()
+/// }
+/// let value: std::rc::Rc<dyn Any> = RcMark::new("hello".to_owned());
+/// assert_eq!(*get_string::<RcMark>(value).unwrap(), "hello");
+/// ```
+pub trait Downcast: Sized {
+    type Mark: RefCountFamily;
+    /// Returns a pointer to the concrete value if it is a `T`, or gives back `self`.
+    fn downcast<T: Any>(self) -> Result<<Self::Mark as RefCountFamily>::Pointer<T>, Self>;
+}
+
+impl Downcast for Rc<dyn Any> {
+    type Mark = RcMark;
+
+    fn downcast<T: Any>(self) -> Result<Rc<T>, Self> {
+        self.downcast()
+    }
+}
+
+impl Downcast for Arc<dyn Any> {
+    type Mark = ArcMark;
+
+    fn downcast<T: Any>(self) -> Result<Arc<T>, Self> {
+        // std only downcasts `Arc<dyn Any + Send + Sync>`.
+        if (*self).is::<T>() {
+            // SAFETY: the value is a `T`, as checked just above, so the pointer to
+            // it is a pointer to a `T` in an `Arc` allocation.
+            Ok(unsafe { Arc::from_raw(Arc::into_raw(self) as *const T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Downcast for Arc<dyn Any + Send + Sync> {
+    type Mark = ArcMark;
+
+    fn downcast<T: Any>(self) -> Result<Arc<T>, Self> {
+        // `Arc::downcast` needs `T: Send + Sync`, which a `T` in this `Arc` is, but
+        // the bound of the trait can't tell. Going through `Arc<dyn Any>` once the
+        // type is checked keeps `self` on failure.
+        if (*self).is::<T>() {
+            let any: Arc<dyn Any> = self;
+            Ok(Downcast::downcast(any).unwrap_or_else(|_| unreachable!()))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downcast() {
+        fn actual_test<P: Downcast + Clone>(value: P) {
+            let other = value.clone();
+            assert!(value.downcast::<String>().is_err());
+            let number = other.downcast::<i32>().ok().unwrap();
+            assert_eq!(*number, 3);
+        }
+        actual_test::<Rc<dyn Any>>(Rc::new(3));
+        actual_test::<Arc<dyn Any>>(Arc::new(3));
+        actual_test::<Arc<dyn Any + Send + Sync>>(Arc::new(3));
+    }
+}
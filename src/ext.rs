@@ -0,0 +1,117 @@
+//! [RefCountedExt], method syntax for the associated functions of [RefCounted].
+//!
+//! It is implemented for every pointer and re-exported by the prelude, so
+//! `a.rc_strong_count()` can replace `R::Pointer::strong_count(&a)` in generic code.
+
+use crate::traits::*;
+
+/// Method syntax for [RefCounted], implemented for all pointers.
+///
+/// The methods are prefixed with `rc_` so they don't collide with the methods of
+/// the pointed value, which [RefCounted] avoids by only having associated functions.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn shared_twice<R: RefCountFamily>() -> bool {
+///     let a = R::new(1);
+///     let b = a.clone();
+///     a.rc_ptr_eq(&b) && a.rc_strong_count() == 2
+/// }
+/// assert!(shared_twice::<ArcMark>());
+/// ```
+pub trait RefCountedExt<T: ?Sized>: RefCounted<T> {
+    fn rc_as_ptr(&self) -> *const T {
+        Self::as_ptr(self)
+    }
+
+    fn rc_ptr_eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other)
+    }
+
+    fn rc_addr_eq(&self, other: &Self) -> bool {
+        Self::addr_eq(self, other)
+    }
+
+    fn rc_downgrade(&self) -> Self::WeakPointer {
+        Self::downgrade(self)
+    }
+
+    fn rc_strong_count(&self) -> usize {
+        Self::strong_count(self)
+    }
+
+    fn rc_weak_count(&self) -> usize {
+        Self::weak_count(self)
+    }
+
+    fn rc_make_mut(&mut self) -> &mut T
+    where
+        T: Clone,
+    {
+        Self::make_mut(self)
+    }
+
+    fn rc_get_mut(&mut self) -> Option<&mut T> {
+        Self::get_mut(self)
+    }
+
+    fn rc_release(self) -> usize
+    where
+        T: Sized,
+    {
+        Self::release(self)
+    }
+
+    fn rc_try_unwrap(self) -> Result<T, Self>
+    where
+        T: Sized,
+    {
+        Self::try_unwrap(self)
+    }
+
+    fn rc_into_inner(self) -> Option<T>
+    where
+        T: Sized,
+    {
+        Self::into_inner(self)
+    }
+
+    fn rc_unwrap_or_clone(self) -> T
+    where
+        T: Clone,
+    {
+        Self::unwrap_or_clone(self)
+    }
+}
+
+impl<T: ?Sized, P: RefCounted<T>> RefCountedExt<T> for P {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_ext() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut a = R::new(vec![1]);
+            let b = a.clone();
+            assert!(a.rc_ptr_eq(&b) && a.rc_addr_eq(&b));
+            assert_eq!(a.rc_strong_count(), 2);
+            assert!(a.rc_get_mut().is_none());
+            a.rc_make_mut().push(2);
+            assert!(!a.rc_ptr_eq(&b));
+            let weak = a.rc_downgrade();
+            assert_eq!(a.rc_weak_count(), 1);
+            assert_eq!(unsafe { &*a.rc_as_ptr() }, &[1, 2]);
+            assert_eq!(b.rc_unwrap_or_clone(), [1]);
+            let a = a.rc_try_unwrap().ok().unwrap();
+            assert_eq!(a, [1, 2]);
+            assert!(weak.upgrade().is_none());
+            assert_eq!(R::new(3).rc_into_inner(), Some(3));
+            assert_eq!(R::new(3).rc_release(), 0);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,166 @@
+//! Helpers for handing [RefCountFamily] pointers to C code.
+//!
+//! A handle is the raw pointer returned by [RefCounted::into_raw]. Keep the payload type
+//! opaque (no `#[repr(C)]`) and cbindgen emits it as a forward declared struct, so the C
+//! side only ever sees `Widget *`. [ref_counted_ffi](crate::ref_counted_ffi) emits the
+//! retain and release functions of a payload, and its accessors.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! pub struct Widget {
+//!     size: u32,
+//! }
+//!
+//! ref_counted_ffi!(ArcMark, Widget, widget_retain, widget_release, {
+//!     widget_size(widget) -> u32 { widget.size },
+//! });
+//!
+//! let handle = ffi::into_handle(ArcMark::new(Widget { size: 3 }));
+//! unsafe {
+//!     widget_retain(handle);
+//!     assert_eq!(widget_size(handle), 3);
+//!     widget_release(handle);
+//!     widget_release(handle);
+//!     assert_eq!(widget_size(std::ptr::null()), 0);
+//! }
+//! ```
+//!
+//! # Generating the header
+//!
+//! The functions are emitted by a macro, so cbindgen only sees them once it expands
+//! the crate defining them, which its `parse.expand` setting asks for:
+//!
+//! ```toml
+//! language = "C"
+//!
+//! [parse.expand]
+//! crates = ["my_crate"]
+//! ```
+//!
+//! For the example above, `cbindgen --config cbindgen.toml --output widget.h` then
+//! declares:
+//!
+//! ```c
+//! typedef struct Widget Widget;
+//!
+//! void widget_retain(const Widget *handle);
+//! void widget_release(const Widget *handle);
+//! uint32_t widget_size(const Widget *handle);
+//! ```
+
+use crate::traits::*;
+
+/// Turns `pointer` into a handle owning one strong count.
+pub fn into_handle<P: RefCounted<T>, T: ?Sized>(pointer: P) -> *const T {
+    P::into_raw(pointer)
+}
+
+/// Takes back the strong count owned by `handle`.
+///
+/// # Safety
+/// `handle` must come from [into_handle] with the same pointer type, and its
+/// strong count must not have been taken back already.
+pub unsafe fn from_handle<P: RefCounted<T>, T: ?Sized>(handle: *const T) -> P {
+    P::from_raw(handle)
+}
+
+/// Borrows the value behind `handle`, returning `None` for a null handle.
+///
+/// # Safety
+/// A non null `handle` must come from [into_handle] and stay alive for `'a`.
+pub unsafe fn borrow_handle<'a, T: ?Sized>(handle: *const T) -> Option<&'a T> {
+    handle.as_ref()
+}
+
+/// Emits the `extern "C"` retain and release functions for handles to `$payload`,
+/// and an accessor for each `$accessor($value) -> $output { ... }` given in braces.
+///
+/// All functions ignore null handles, for which accessors return
+/// `<$output>::default()`. Release drops the value once the last strong count is
+/// gone.
+#[macro_export]
+macro_rules! ref_counted_ffi {
+    ($mark:ty, $payload:ty, $retain:ident, $release:ident) => {
+        $crate::ref_counted_ffi!($mark, $payload, $retain, $release, {});
+    };
+    ($mark:ty, $payload:ty, $retain:ident, $release:ident, {
+        $($accessor:ident($value:ident) -> $output:ty $body:block),* $(,)?
+    }) => {
+        /// Adds a strong count to the handle.
+        ///
+        /// # Safety
+        /// `handle` must be null or a live handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $retain(handle: *const $payload) {
+            if !handle.is_null() {
+                <<$mark as $crate::RefCountFamily>::Pointer<$payload> as $crate::RefCounted<
+                    $payload,
+                >>::increment_strong_count(handle);
+            }
+        }
+
+        /// Removes a strong count from the handle.
+        ///
+        /// # Safety
+        /// `handle` must be null or a live handle, and is dangling once its
+        /// last count is removed.
+        #[no_mangle]
+        pub unsafe extern "C" fn $release(handle: *const $payload) {
+            if !handle.is_null() {
+                <<$mark as $crate::RefCountFamily>::Pointer<$payload> as $crate::RefCounted<
+                    $payload,
+                >>::decrement_strong_count(handle);
+            }
+        }
+
+        $(
+            /// Reads the value behind the handle, or returns the default for a
+            /// null handle.
+            ///
+            /// # Safety
+            /// `handle` must be null or a live handle.
+            #[no_mangle]
+            pub unsafe extern "C" fn $accessor(handle: *const $payload) -> $output {
+                match $crate::ffi::borrow_handle(handle) {
+                    Some($value) => $body,
+                    None => <$output as ::core::default::Default>::default(),
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RcMark;
+    use std::rc::Rc;
+
+    pub struct Payload(i32);
+
+    ref_counted_ffi!(RcMark, Payload, test_payload_retain, test_payload_release, {
+        test_payload_value(payload) -> i32 { payload.0 },
+        test_payload_is_negative(payload) -> bool { payload.0 < 0 },
+    });
+
+    #[test]
+    fn test_retain_release() {
+        let pointer = RcMark::new(Payload(4));
+        let weak = Rc::downgrade(&pointer);
+        let handle = into_handle(pointer);
+        unsafe {
+            test_payload_retain(handle);
+            assert_eq!(weak.strong_count(), 2);
+            assert_eq!(borrow_handle(handle).unwrap().0, 4);
+            assert_eq!(test_payload_value(handle), 4);
+            assert!(!test_payload_is_negative(handle));
+            test_payload_release(handle);
+            let pointer: Rc<Payload> = from_handle(handle);
+            assert_eq!(Rc::strong_count(&pointer), 1);
+            test_payload_release(std::ptr::null());
+            assert_eq!(test_payload_value(std::ptr::null()), 0);
+            assert!(borrow_handle::<Payload>(std::ptr::null()).is_none());
+        }
+        assert!(weak.upgrade().is_none());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+/// How an [Interner] holds its values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Retention {
+    /// Values live as long as the interner.
+    #[default]
+    Strong,
+    /// Values live as long as handles to them, and are interned anew afterwards.
+    /// Their dead entries are pruned when their hash is interned again, or by
+    /// [Interner::prune].
+    Weak,
+}
+
+/// Deduplicates values, handing out a single `Mark::Pointer` for all the equal
+/// ones, so interned values can be compared with [RefCounted::ptr_eq].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Lexer<R: RefCountFamily> {
+///     symbols: Interner<R, str>,
+/// }
+/// impl<R: RefCountFamily> Lexer<R> {
+///     fn words(&mut self, source: &str) -> Vec<R::Pointer<str>> {
+///         source.split(' ').map(|word| self.symbols.intern_str(word)).collect()
+///     }
+/// }
+/// let mut lexer = Lexer::<RcMark> { symbols: Interner::new() };
+/// let words = lexer.words("let x = x");
+/// assert!(std::rc::Rc::ptr_eq(&words[1], &words[3]));
+/// assert_eq!(lexer.symbols.len(), 3);
+/// ```
+pub struct Interner<Mark: RefCountFamily, T: ?Sized, S = RandomState> {
+    buckets: HashMap<u64, Vec<Slot<Mark, T>>>,
+    len: usize,
+    retention: Retention,
+    hasher: S,
+}
+
+enum Slot<Mark: RefCountFamily, T: ?Sized> {
+    Strong(Mark::Pointer<T>),
+    Weak(Mark::WeakPointer<T>),
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Slot<Mark, T> {
+    fn upgrade(&self) -> Option<Mark::Pointer<T>> {
+        match self {
+            Slot::Strong(pointer) => Some(pointer.clone()),
+            Slot::Weak(weak) => weak.upgrade(),
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        match self {
+            Slot::Strong(_) => true,
+            Slot::Weak(weak) => weak.strong_count() > 0,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Interner<Mark, T> {
+    pub fn new() -> Self {
+        Self::with_retention(Retention::Strong)
+    }
+
+    pub fn with_retention(retention: Retention) -> Self {
+        Self::with_retention_and_hasher(retention, RandomState::new())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized, S> Interner<Mark, T, S> {
+    pub fn with_retention_and_hasher(retention: Retention, hasher: S) -> Self {
+        Self {
+            buckets: HashMap::new(),
+            len: 0,
+            retention,
+            hasher,
+        }
+    }
+
+    /// Returns the number of entries, including the dead ones not pruned yet with
+    /// [Retention::Weak].
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    /// Returns handles to the live values, in an unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = Mark::Pointer<T>> + '_ {
+        self.buckets.values().flatten().filter_map(Slot::upgrade)
+    }
+
+    /// Removes the dead entries.
+    pub fn prune(&mut self) {
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(Slot::is_live);
+            !bucket.is_empty()
+        });
+        self.len = self.buckets.values().map(Vec::len).sum();
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.len = 0;
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + Hash + Eq, S: BuildHasher> Interner<Mark, T, S> {
+    /// Returns the interned value equal to `key`, if any.
+    pub fn get<Q>(&self, key: &Q) -> Option<Mark::Pointer<T>>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let bucket = self.buckets.get(&self.hasher.hash_one(key))?;
+        bucket
+            .iter()
+            .filter_map(Slot::upgrade)
+            .find(|pointer| (**pointer).borrow() == key)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the interned value equal to `value`, interning it first if needed.
+    pub fn intern(&mut self, value: T) -> Mark::Pointer<T>
+    where
+        T: Sized,
+    {
+        let hash = self.hasher.hash_one(&value);
+        match self.find_hashed(hash, &value) {
+            Some(pointer) => pointer,
+            None => self.insert_hashed(hash, Mark::new(value)),
+        }
+    }
+
+    /// Returns the interned value equal to `*pointer`, interning `pointer` first if
+    /// needed.
+    pub fn intern_pointer(&mut self, pointer: Mark::Pointer<T>) -> Mark::Pointer<T> {
+        let hash = self.hasher.hash_one(&*pointer);
+        match self.find_hashed(hash, &*pointer) {
+            Some(pointer) => pointer,
+            None => self.insert_hashed(hash, pointer),
+        }
+    }
+
+    /// Returns the interned value equal to `key`, interning `make(key)` first if
+    /// needed, which must be equal to `key`.
+    pub fn intern_with<Q, F>(&mut self, key: &Q, make: F) -> Mark::Pointer<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&Q) -> Mark::Pointer<T>,
+    {
+        let hash = self.hasher.hash_one(key);
+        match self.find_hashed(hash, key) {
+            Some(pointer) => pointer,
+            None => self.insert_hashed(hash, make(key)),
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized, S> Interner<Mark, T, S> {
+    /// Returns the interned value equal to `key`, whose hash is `hash`, pruning the
+    /// dead entries with that hash on the way.
+    pub(crate) fn find_hashed<Q>(&mut self, hash: u64, key: &Q) -> Option<Mark::Pointer<T>>
+    where
+        T: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let bucket = self.buckets.get_mut(&hash)?;
+        let len = bucket.len();
+        bucket.retain(Slot::is_live);
+        self.len -= len - bucket.len();
+        bucket
+            .iter()
+            .filter_map(Slot::upgrade)
+            .find(|pointer| (**pointer).borrow() == key)
+    }
+
+    /// Adds `pointer` with its precomputed `hash`, and returns it.
+    pub(crate) fn insert_hashed(
+        &mut self,
+        hash: u64,
+        pointer: Mark::Pointer<T>,
+    ) -> Mark::Pointer<T> {
+        let slot = match self.retention {
+            Retention::Strong => Slot::Strong(pointer.clone()),
+            Retention::Weak => Slot::Weak(Mark::Pointer::downgrade(&pointer)),
+        };
+        self.buckets.entry(hash).or_default().push(slot);
+        self.len += 1;
+        pointer
+    }
+}
+
+impl<Mark: RefCountFamily, S: BuildHasher> Interner<Mark, str, S> {
+    /// Returns the interned string equal to `s`, interning a copy first if needed.
+    pub fn intern_str(&mut self, s: &str) -> Mark::Pointer<str> {
+        self.intern_with(s, Mark::new_str)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized, S: Default> Default for Interner<Mark, T, S> {
+    fn default() -> Self {
+        Self::with_retention_and_hasher(Retention::default(), S::default())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + fmt::Debug, S> fmt::Debug for Interner<Mark, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<_> = self.iter().collect();
+        f.debug_set()
+            .entries(values.iter().map(|value| &**value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_interner() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut interner = Interner::<R, String>::new();
+            let a = interner.intern("a".to_owned());
+            let b = interner.intern_with("b", |b| R::new(b.to_owned()));
+            assert!(R::Pointer::ptr_eq(&a, &interner.intern("a".to_owned())));
+            assert!(R::Pointer::ptr_eq(&b, &interner.get("b").unwrap()));
+            let other_a = R::new("a".to_owned());
+            assert!(R::Pointer::ptr_eq(&a, &interner.intern_pointer(other_a)));
+            assert_eq!((interner.len(), interner.contains("c")), (2, false));
+            drop((a, b));
+            interner.prune();
+            assert_eq!(interner.iter().count(), 2);
+            assert_eq!(format!("{:?}", Interner::<R, str>::new()), "{}");
+
+            let mut weak = Interner::<R, str>::with_retention(Retention::Weak);
+            let x = weak.intern_str("x");
+            assert!(R::Pointer::ptr_eq(&x, &weak.intern_str("x")));
+            assert_eq!(format!("{weak:?}"), r#"{"x"}"#);
+            drop(x);
+            assert_eq!((weak.len(), weak.get("x").is_none()), (1, true));
+            let _y = weak.intern_pointer(R::new_str("y"));
+            weak.prune();
+            assert_eq!(weak.len(), 1);
+            let x = weak.intern_str("x");
+            assert_eq!((weak.len(), R::Pointer::strong_count(&x)), (2, 1));
+            drop(x);
+            weak.intern_str("x");
+            assert_eq!(weak.len(), 2);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -1,4 +1,15 @@
-#![cfg_attr(feature = "nightly", feature(coerce_unsized))]
+#![cfg_attr(
+    feature = "nightly",
+    feature(
+        allocator_api,
+        coerce_unsized,
+        fn_traits,
+        get_mut_unchecked,
+        local_waker,
+        unboxed_closures,
+        unique_rc_arc
+    )
+)]
 /*!
 A [GATs](https://blog.rust-lang.org/2022/10/28/gats-stabilization.html) powered abstraction for reference counted smart pointers.
 
@@ -86,6 +97,36 @@ let _a = Foo::<RcMark>::wrap(|a| a + 1).0;
 
 ```
 
+On stable, the closure can be boxed first, and the box moved into the pointer
+with [RefCountFamily::from_box]. This costs an extra allocation and a copy.
+
+```
+# use cark_ref_counted::*;
+struct Foo<Mark: RefCountFamily>(Mark::Pointer<dyn Fn(i32) -> i32>);
+
+impl<Mark: RefCountFamily> Foo<Mark> {
+    fn wrap<T: Fn(i32) -> i32 + 'static>(c: T) -> Self {
+        Self(Mark::from_box(Box::new(c)))
+    }
+}
+assert_eq!((Foo::<RcMark>::wrap(|a| a + 1).0)(1), 2);
+```
+
+The [coerce!] macro avoids the extra allocation, for families implementing
+[CoerceFamily]. It goes through raw pointers, which do coerce on stable.
+
+```
+# use cark_ref_counted::*;
+struct Foo<Mark: CoerceFamily>(Mark::Pointer<dyn Fn(i32) -> i32>);
+
+impl<Mark: CoerceFamily> Foo<Mark> {
+    fn wrap<T: Fn(i32) -> i32 + 'static>(c: T) -> Self {
+        Self(coerce!((Mark::new(c)) as dyn Fn(i32) -> i32))
+    }
+}
+assert_eq!((Foo::<RcMark>::wrap(|a| a + 1).0)(1), 2);
+```
+
 We obviously would prefer not to use a nightly feature here. It seems to me that the issue
 is not really with GATs but rather with the way rust closures are implemented.
 
@@ -99,11 +140,162 @@ A shot in the dark: Maybe would it be possible for the std team to implement Coe
 - Rust programming language forum user [semicoleon](https://users.rust-lang.org/u/semicoleon) for pointing me toward the CoerceUnsized trait.
  */
 
+pub mod adapters;
+#[cfg(feature = "nightly")]
+pub mod alloc;
+pub mod any_shared;
+#[cfg(any(feature = "proptest", feature = "quickcheck"))]
+pub mod arbitrary;
+pub mod by_address;
+pub mod cache;
+pub mod cart;
+pub mod coerce;
+#[cfg(feature = "collections")]
+pub mod collections;
 pub mod concrete;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod downcast;
+pub mod ext;
+pub mod ffi;
+pub mod intern;
+pub mod lock;
+pub mod maybe_shared;
+pub mod memo;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod observe;
+pub mod once_shared;
+#[cfg(feature = "rpds")]
+pub mod persistent;
+pub mod pin_weak;
+pub mod pinned;
+pub mod pool;
+pub mod prelude;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(feature = "rkyv")]
+pub mod rkyv_pointer;
+#[cfg(feature = "zeroize")]
+pub mod secret;
+#[cfg(feature = "serde")]
+pub mod serde_dedup;
+#[cfg(feature = "serde")]
+pub mod serde_graph;
+#[cfg(feature = "serde")]
+pub mod serde_pointer;
+#[cfg(feature = "sharded-intern")]
+pub mod sharded_intern;
+pub mod shareable;
+pub mod shared;
+pub mod shared_bytes;
+pub mod shared_cell;
+pub mod shared_error;
+pub mod shared_fn;
+pub mod shared_option;
+pub mod shared_slice;
+pub mod shared_str;
+pub mod signal;
+pub mod slice_builder;
+pub mod stable_deref;
+pub mod strong;
+pub mod swap_cell;
+pub mod task;
+pub mod testing;
+pub mod thin;
+#[cfg(feature = "tracing")]
+pub mod traced;
 pub mod traits;
+pub mod tree;
+pub mod unique;
+pub mod weak_vec;
+pub use adapters::*;
+#[cfg(feature = "nightly")]
+pub use alloc::*;
+pub use any_shared::{AnyShared, AnyWeak};
+pub use by_address::*;
+pub use cache::*;
+pub use coerce::*;
 pub use concrete::arc::*;
+#[cfg(feature = "archery")]
+pub use concrete::archery::*;
+#[cfg(feature = "loom")]
+pub use concrete::loom::*;
+#[cfg(feature = "portable-atomic-util")]
+pub use concrete::portable_arc::*;
 pub use concrete::rc::*;
+#[cfg(feature = "triomphe")]
+pub use concrete::triomphe::*;
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::*;
+pub use downcast::*;
+pub use ext::*;
+pub use intern::*;
+pub use lock::*;
+pub use maybe_shared::*;
+pub use memo::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+pub use observe::*;
+pub use once_shared::*;
+pub use pin_weak::*;
+pub use pinned::*;
+pub use pool::*;
+#[cfg(feature = "record")]
+pub use record::*;
+#[cfg(feature = "zeroize")]
+pub use secret::*;
+/// The [serde_pointer] helpers, for `#[serde(with = "cark_ref_counted::serde")]`.
+/// Code importing `cark_ref_counted::*` must name the serde crate `::serde`.
+#[cfg(feature = "serde")]
+pub use serde_pointer as serde;
+#[cfg(feature = "sharded-intern")]
+pub use sharded_intern::*;
+pub use shareable::*;
+pub use shared::*;
+pub use shared_bytes::*;
+pub use shared_cell::*;
+pub use shared_error::*;
+pub use shared_fn::*;
+pub use shared_option::*;
+pub use shared_slice::*;
+pub use shared_str::*;
+pub use signal::*;
+pub use slice_builder::*;
+pub use swap_cell::*;
+pub use task::*;
+pub use thin::*;
+#[cfg(feature = "tracing")]
+pub use traced::*;
 pub use traits::*;
+pub use tree::*;
+pub use unique::*;
+pub use weak_vec::*;
+
+#[cfg(feature = "derive")]
+extern crate self as cark_ref_counted;
+
+/// Declares the `<Name>Pointer` and `<Name>WeakPointer` aliases for a type generic
+/// over a family, and adds the [RefCountFamily] bound on the family parameter if missing.
+///
+/// The family is the parameter bounded by [RefCountFamily], or the one named in
+/// the attribute.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// #[ref_counted_generic(RC)]
+/// struct Gadget<RC> {
+///     me: RC::WeakPointer<Self>,
+/// }
+///
+/// fn gadget<RC: RefCountFamily>() -> GadgetPointer<RC> {
+///     RC::Pointer::new_cyclic(|me: &GadgetWeakPointer<RC>| Gadget { me: me.clone() })
+/// }
+/// let g = gadget::<RcMark>();
+/// assert!(std::rc::Rc::ptr_eq(&g, &g.me.upgrade().unwrap()));
+/// ```
+#[cfg(feature = "derive")]
+pub use cark_ref_counted_derive::ref_counted_generic;
 
 // WeakFamily
 
@@ -124,6 +316,121 @@ mod tests {
         actual_test::<Rc<_>>()
     }
 
+    #[test]
+    fn test_into_inner() {
+        fn actual_test<RC: RefCounted<i32>>() {
+            let x = RC::new(3);
+            assert_eq!(RC::into_inner(x), Some(3));
+            let x = RC::new(4);
+            let y = RC::clone(&x);
+            assert_eq!(RC::into_inner(x), None);
+            assert_eq!(RC::into_inner(y), Some(4));
+        }
+        actual_test::<Rc<_>>();
+        actual_test::<std::sync::Arc<_>>()
+    }
+
+    #[test]
+    fn test_unwrap_or_clone() {
+        fn actual_test<RC: RefCounted<String>>() {
+            let x = RC::new("a".to_owned());
+            let y = RC::clone(&x);
+            let mut value = RC::unwrap_or_clone(x);
+            value.push('b');
+            assert_eq!(value, "ab");
+            assert_eq!(&*y, "a");
+            assert_eq!(RC::unwrap_or_clone(y), "a");
+        }
+        actual_test::<Rc<_>>();
+        actual_test::<std::sync::Arc<_>>()
+    }
+
+    #[test]
+    fn test_from_box() {
+        fn actual_test<R: RefCountFamily>() {
+            let boxed: Box<dyn Fn(i32) -> i32> = Box::new(|a| a + 1);
+            let f = R::from_box(boxed);
+            assert_eq!(f(1), 2);
+            let s = R::Pointer::<i32>::from_box::<str>("abc".into());
+            assert_eq!(&*s, "abc");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_new_slice() {
+        fn actual_test<R: RefCountFamily>() {
+            let a = R::new_slice(vec![1, 2, 3]);
+            assert_eq!(&*a, [1, 2, 3]);
+            let b = R::new_slice_clone(&a[1..]);
+            assert_eq!(&*b, [2, 3]);
+            let empty = R::new_slice(Vec::<String>::new());
+            assert!(empty.is_empty());
+            let c = R::from_iter((1..4).map(|i| i * 2));
+            assert_eq!(&*c, [2, 4, 6]);
+            let array = R::new_array([1u8; 4]);
+            let ptr = R::Pointer::as_ptr(&array) as *const u8;
+            let slice = R::unsize_array(array);
+            assert_eq!(slice.len(), 4);
+            assert_eq!(slice.as_ptr(), ptr);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_new_str() {
+        fn actual_test<R: RefCountFamily>() {
+            let a = R::new_str("hello");
+            let b = R::from_string("hello".to_owned());
+            assert_eq!(&*a, &*b);
+            assert_eq!(&*R::new_str(""), "");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_new_uninit() {
+        fn actual_test<R: UninitConstruct>() {
+            let mut x = R::new_uninit::<String>();
+            R::Pointer::get_mut(&mut x).unwrap().write("a".to_owned());
+            let x = unsafe { R::assume_init(x) };
+            assert_eq!(&*x, "a");
+            let mut s = R::new_uninit_slice::<i32>(3);
+            for (i, slot) in R::Pointer::get_mut(&mut s).unwrap().iter_mut().enumerate() {
+                slot.write(i as i32);
+            }
+            let s = unsafe { R::assume_init_slice(s) };
+            assert_eq!(&*s, [0, 1, 2]);
+            let z = unsafe { R::assume_init(R::new_zeroed::<u64>()) };
+            assert_eq!(*z, 0);
+            let z = unsafe { R::assume_init_slice(R::new_zeroed_slice::<u8>(1024)) };
+            assert!(z.iter().all(|&b| b == 0));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_from_unsized() {
+        use std::{
+            ffi::{CStr, OsStr},
+            path::Path,
+        };
+        fn actual_test<R: FromUnsized<OsStr> + FromUnsized<CStr> + FromUnsized<Path>>() {
+            let os = <R as FromUnsized<OsStr>>::from_unsized(OsStr::new("os"));
+            assert_eq!(&*os, "os");
+            let c = <R as FromUnsized<CStr>>::from_unsized(c"c");
+            assert_eq!(c.to_bytes(), b"c");
+            let path = <R as FromUnsized<Path>>::from_unsized(Path::new("a/b"));
+            assert_eq!(path.file_name(), Some(OsStr::new("b")));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
     #[test]
     fn test_new_cyclic() {
         struct Gadget<RC: RefCountFamily> {
@@ -143,6 +450,80 @@ mod tests {
         assert!(Rc::ptr_eq(&g, &g.me()));
     }
 
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_ref_counted_generic() {
+        #[ref_counted_generic]
+        struct Node<'a, RC: RefCountFamily, const N: usize = 2> {
+            name: &'a str,
+            parent: Option<RC::WeakPointer<Self>>,
+            children: [Option<RC::Pointer<Self>>; N],
+        }
+        fn actual_test<RC: RefCountFamily>() {
+            let child: NodePointer<RC> = RC::new(Node {
+                name: "child",
+                parent: None,
+                children: [None, None],
+            });
+            let root = RC::new(Node {
+                name: "root",
+                parent: None,
+                children: [Some(child.clone()), None],
+            });
+            let weak: NodeWeakPointer<RC> = RC::Pointer::downgrade(&root);
+            assert_eq!(
+                weak.upgrade().unwrap().children[0].as_ref().unwrap().name,
+                "child"
+            );
+            assert!(child.parent.is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_try_new_cyclic() {
+        struct Gadget<RC: RefCountFamily> {
+            me: RC::WeakPointer<Self>,
+        }
+        fn actual_test<RC: TryCyclicFamily>() {
+            let g = RC::try_new_cyclic(|me| Ok::<_, ()>(Gadget::<RC> { me: me.clone() }))
+                .ok()
+                .unwrap();
+            assert!(RC::Pointer::ptr_eq(&g, &g.me.upgrade().unwrap()));
+
+            let mut leaked = None;
+            let result = RC::try_new_cyclic(|me| {
+                leaked = Some(me.clone());
+                Err::<Gadget<RC>, _>("failed")
+            });
+            assert_eq!(result.err(), Some("failed"));
+            assert!(leaked.unwrap().upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        #[cfg(feature = "nightly")]
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_new_weak() {
+        struct Child<RC: RefCountFamily> {
+            parent: RC::WeakPointer<i32>,
+        }
+        fn actual_test<RC: RefCountFamily>() {
+            let mut child = Child::<RC> {
+                parent: RC::new_weak(),
+            };
+            assert!(child.parent.upgrade().is_none());
+            assert_eq!(child.parent.strong_count(), 0);
+            let parent = RC::new(1);
+            child.parent = RC::Pointer::downgrade(&parent);
+            assert_eq!(child.parent.upgrade().as_deref(), Some(&1));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
     #[test]
     fn test_get_mut() {
         fn actual_test<RC: RefCounted<i32>>() {
@@ -155,6 +536,177 @@ mod tests {
         actual_test::<Rc<_>>()
     }
 
+    #[test]
+    fn test_derives() {
+        use std::collections::{BTreeSet, HashSet};
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        struct Foo<R: RefCountFamily> {
+            value: R::Pointer<i32>,
+        }
+        fn actual_test<R: RefCountFamily>()
+        where
+            Foo<R>: std::fmt::Debug + Clone + Eq + std::hash::Hash + Ord,
+        {
+            let a = Foo::<R> { value: R::new(1) };
+            let b = Foo::<R> { value: R::new(2) };
+            assert!(a < b);
+            assert_eq!(a, a.clone());
+            assert_eq!(format!("{a:?}"), "Foo { value: 1 }");
+            assert_eq!(HashSet::from([a.clone(), a.clone()]).len(), 1);
+            assert_eq!(BTreeSet::from([b, a]).first().map(|f| *f.value), Some(1));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_fmt_pointer() {
+        fn actual_test<R: RefCountFamily>() {
+            let x = R::new(1);
+            let expected = format!("{:p}", R::Pointer::as_ptr(&x));
+            assert_eq!(format!("{x:p}"), expected);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_borrow() {
+        use std::borrow::Borrow;
+        fn len(s: impl Borrow<str>) -> usize {
+            s.borrow().len()
+        }
+        fn sum(v: impl AsRef<Vec<i32>>) -> i32 {
+            v.as_ref().iter().sum()
+        }
+        fn actual_test<R: RefCountFamily>() {
+            assert_eq!(len(R::new_str("abc")), 3);
+            assert_eq!(sum(R::new(vec![1, 2])), 3);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_ptr_eq() {
+        fn actual_test<RC: RefCounted<str>>(a: RC, b: RC) {
+            let c = RC::clone(&a);
+            assert!(RC::ptr_eq(&a, &c));
+            assert!(!RC::ptr_eq(&a, &b));
+        }
+        actual_test::<Rc<str>>(Rc::from("a"), Rc::from("a"));
+        actual_test::<std::sync::Arc<str>>("a".into(), "a".into())
+    }
+
+    #[test]
+    fn test_addr_eq() {
+        use std::fmt::Debug;
+        fn actual_test<R: RefCountFamily>() {
+            let a = R::from_box::<dyn Debug>(Box::new(1));
+            let b = R::Pointer::clone(&a);
+            let c = R::from_box::<dyn Debug>(Box::new(1));
+            assert!(R::Pointer::addr_eq(&a, &b));
+            assert!(!R::Pointer::addr_eq(&a, &c));
+            let weak_a = R::Pointer::downgrade(&a);
+            assert!(weak_a.addr_eq(&R::Pointer::downgrade(&b)));
+            assert!(!weak_a.addr_eq(&R::Pointer::downgrade(&c)));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_release() {
+        fn actual_test<RC: RefCounted<i32>>() {
+            let x = RC::new(3);
+            let y = RC::clone(&x);
+            let weak = RC::downgrade(&x);
+            assert_eq!(RC::release(x), 1);
+            assert_eq!(RC::release(y), 0);
+            assert!(weak.upgrade().is_none());
+        }
+        actual_test::<Rc<_>>();
+        actual_test::<std::sync::Arc<_>>();
+
+        for _ in 0..100 {
+            let x = ArcMark::new(3);
+            let mut pointers = vec![x.clone(), x.clone(), x.clone(), x];
+            let handles: Vec<_> = pointers
+                .drain(..)
+                .map(|x| std::thread::spawn(move || RefCounted::release(x)))
+                .collect();
+            let results = handles.into_iter().map(|h| h.join().unwrap());
+            assert_eq!(results.filter(|&count| count == 0).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_is_thread_safe() {
+        const _: () = assert!(ArcMark::IS_THREAD_SAFE);
+        const _: () = assert!(!RcMark::IS_THREAD_SAFE);
+        fn name<R: RefCountFamily>() -> &'static str {
+            if R::IS_THREAD_SAFE {
+                "atomic"
+            } else {
+                "local"
+            }
+        }
+        assert_eq!(name::<RcMark>(), "local");
+        assert_eq!(name::<ArcMark>(), "atomic");
+    }
+
+    #[test]
+    fn test_thread_safe_family() {
+        fn actual_test<R: ThreadSafeFamily>() {
+            let x = R::into_sync(R::new(3));
+            let y = x.clone();
+            let handle = std::thread::spawn(move || *y + 1);
+            assert_eq!(handle.join().unwrap(), 4);
+            let x: R::Pointer<i32> = R::from_sync(x);
+            assert_eq!(R::Pointer::strong_count(&x), 1);
+        }
+        actual_test::<ArcMark>()
+    }
+
+    #[test]
+    fn test_wait_until_unique() {
+        use std::{
+            future::Future,
+            pin::Pin,
+            sync::Arc,
+            task::{Context, Poll, Waker},
+        };
+
+        let mut x = Arc::new(3);
+        let y = x.clone();
+        let handle = std::thread::spawn(move || drop(y));
+        *ArcMark::wait_until_unique(&mut x) += 1;
+        handle.join().unwrap();
+        assert_eq!(*x, 4);
+
+        let y = x.clone();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = ArcMark::wait_until_unique_async(&mut x);
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        drop(y);
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(value) => *value += 1,
+            Poll::Pending => panic!("should be unique"),
+        }
+        assert_eq!(*x, 5);
+    }
+
+    #[test]
+    fn test_get_mut_unchecked() {
+        fn actual_test<RC: RefCounted<i32>>() {
+            let mut x = RC::new(3);
+            unsafe { *RC::get_mut_unchecked(&mut x) = 4 };
+            assert_eq!(*x, 4);
+        }
+        actual_test::<Rc<_>>();
+        actual_test::<std::sync::Arc<_>>()
+    }
+
     #[test]
     fn test_into_raw() {
         fn actual_test<RC: RefCounted<String>>() {
@@ -381,6 +933,8 @@ mod tests {
     }
 
     #[test]
+    // Keeps the original vec! so the test reads as the pattern it documents.
+    #[allow(clippy::useless_vec)]
     fn test_closure() {
         let a = RcMark::new(|a: i32| a + 1); // Rc<|i32| -> i32>
         assert_eq!(a(1), 2);
@@ -0,0 +1,354 @@
+//! [LockFamily] implementations for [RcMark] and [ArcMark].
+//!
+//! [RcMark] is paired with a [RefCell], and [ArcMark] with an [std::sync::RwLock].
+//! With the `parking_lot` feature, `ParkingLotArcMark` is an [ArcMark] paired with a
+//! `parking_lot::RwLock` instead. The guards are associated types of the family, so
+//! generic code is the same whichever backend it is given.
+//!
+//! With the `async-lock` feature, `AsyncLockMark<Mark>` is a `Mark` paired with an
+//! `async_lock::RwLock`, and implements [AsyncLockFamily].
+
+use crate::{traits::*, ArcMark, RcMark};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    future::Future,
+    ops::{Deref, DerefMut},
+};
+
+/// A [RefCountFamily] paired with its natural interior mutability primitive, as in
+/// `Rc<RefCell<T>>` and `Arc<RwLock<T>>`.
+///
+/// Locking never fails on a poisoned lock: the poison is ignored, as a [RefCell]
+/// has none. Conflicting borrows of a [RefCell] panic, where an `RwLock` blocks.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn push<R: LockFamily>(list: &R::Pointer<R::Lock<Vec<i32>>>, value: i32) {
+///     R::write(list).push(value);
+/// }
+/// let list = RcMark::new(RcMark::new_lock(vec![]));
+/// push::<RcMark>(&list, 1);
+/// assert_eq!(*RcMark::read(&list), [1]);
+/// ```
+pub trait LockFamily: RefCountFamily {
+    type Lock<T>;
+    type ReadGuard<'a, T: 'a>: Deref<Target = T>;
+    type WriteGuard<'a, T: 'a>: DerefMut<Target = T>;
+    fn new_lock<T>(value: T) -> Self::Lock<T>;
+    fn read<T>(lock: &Self::Lock<T>) -> Self::ReadGuard<'_, T>;
+    fn write<T>(lock: &Self::Lock<T>) -> Self::WriteGuard<'_, T>;
+    /// Returns `None` if the lock is held for writing.
+    fn try_read<T>(lock: &Self::Lock<T>) -> Option<Self::ReadGuard<'_, T>>;
+    /// Returns `None` if the lock is held.
+    fn try_write<T>(lock: &Self::Lock<T>) -> Option<Self::WriteGuard<'_, T>>;
+    fn lock_get_mut<T>(lock: &mut Self::Lock<T>) -> &mut T;
+    fn lock_into_inner<T>(lock: Self::Lock<T>) -> T;
+}
+
+/// A [LockFamily] whose locks can be awaited, so async code doesn't block the
+/// executor while the lock is held elsewhere.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # #[cfg(feature = "async-lock")] {
+/// # use std::{future::Future, pin::pin, task::{Context, Waker}};
+/// async fn push<R: AsyncLockFamily>(list: &SharedCell<R, Vec<i32>>, value: i32) {
+///     list.write_async().await.push(value);
+/// }
+/// let list = SharedCell::<AsyncLockMark<RcMark>, _>::new(vec![]);
+/// let future = pin!(push(&list, 1));
+/// assert!(future.poll(&mut Context::from_waker(Waker::noop())).is_ready());
+/// assert_eq!(*list.read(), [1]);
+/// # }
+/// ```
+pub trait AsyncLockFamily: LockFamily {
+    fn read_async<'a, T: 'a>(
+        lock: &'a Self::Lock<T>,
+    ) -> impl Future<Output = Self::ReadGuard<'a, T>>;
+    fn write_async<'a, T: 'a>(
+        lock: &'a Self::Lock<T>,
+    ) -> impl Future<Output = Self::WriteGuard<'a, T>>;
+}
+
+impl LockFamily for RcMark {
+    type Lock<T> = RefCell<T>;
+    type ReadGuard<'a, T: 'a> = Ref<'a, T>;
+    type WriteGuard<'a, T: 'a> = RefMut<'a, T>;
+
+    fn new_lock<T>(value: T) -> RefCell<T> {
+        RefCell::new(value)
+    }
+
+    fn read<T>(lock: &RefCell<T>) -> Ref<'_, T> {
+        lock.borrow()
+    }
+
+    fn write<T>(lock: &RefCell<T>) -> RefMut<'_, T> {
+        lock.borrow_mut()
+    }
+
+    fn try_read<T>(lock: &RefCell<T>) -> Option<Ref<'_, T>> {
+        lock.try_borrow().ok()
+    }
+
+    fn try_write<T>(lock: &RefCell<T>) -> Option<RefMut<'_, T>> {
+        lock.try_borrow_mut().ok()
+    }
+
+    fn lock_get_mut<T>(lock: &mut RefCell<T>) -> &mut T {
+        lock.get_mut()
+    }
+
+    fn lock_into_inner<T>(lock: RefCell<T>) -> T {
+        lock.into_inner()
+    }
+}
+
+impl LockFamily for ArcMark {
+    type Lock<T> = std::sync::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = std::sync::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = std::sync::RwLockWriteGuard<'a, T>;
+
+    fn new_lock<T>(value: T) -> std::sync::RwLock<T> {
+        std::sync::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &std::sync::RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+        lock.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write<T>(lock: &std::sync::RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+        lock.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn try_read<T>(lock: &std::sync::RwLock<T>) -> Option<std::sync::RwLockReadGuard<'_, T>> {
+        match lock.try_read() {
+            Ok(guard) => Some(guard),
+            Err(std::sync::TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn try_write<T>(lock: &std::sync::RwLock<T>) -> Option<std::sync::RwLockWriteGuard<'_, T>> {
+        match lock.try_write() {
+            Ok(guard) => Some(guard),
+            Err(std::sync::TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn lock_get_mut<T>(lock: &mut std::sync::RwLock<T>) -> &mut T {
+        lock.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_into_inner<T>(lock: std::sync::RwLock<T>) -> T {
+        lock.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The tag of [ParkingLotArcMark].
+#[cfg(feature = "parking_lot")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParkingLot;
+
+/// An [ArcMark] paired with a `parking_lot::RwLock`, which is smaller, fair, and
+/// has no poisoning.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let counter = ParkingLotArcMark::new(ParkingLotArcMark::new_lock(0));
+/// *ParkingLotArcMark::write(&counter) += 1;
+/// assert_eq!(*ParkingLotArcMark::read(&counter), 1);
+/// ```
+#[cfg(feature = "parking_lot")]
+pub type ParkingLotArcMark = crate::WrapMark<ArcMark, (), ParkingLot>;
+
+#[cfg(feature = "parking_lot")]
+impl LockFamily for ParkingLotArcMark {
+    type Lock<T> = parking_lot::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = parking_lot::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = parking_lot::RwLockWriteGuard<'a, T>;
+
+    fn new_lock<T>(value: T) -> parking_lot::RwLock<T> {
+        parking_lot::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &parking_lot::RwLock<T>) -> parking_lot::RwLockReadGuard<'_, T> {
+        lock.read()
+    }
+
+    fn write<T>(lock: &parking_lot::RwLock<T>) -> parking_lot::RwLockWriteGuard<'_, T> {
+        lock.write()
+    }
+
+    fn try_read<T>(lock: &parking_lot::RwLock<T>) -> Option<parking_lot::RwLockReadGuard<'_, T>> {
+        lock.try_read()
+    }
+
+    fn try_write<T>(lock: &parking_lot::RwLock<T>) -> Option<parking_lot::RwLockWriteGuard<'_, T>> {
+        lock.try_write()
+    }
+
+    fn lock_get_mut<T>(lock: &mut parking_lot::RwLock<T>) -> &mut T {
+        lock.get_mut()
+    }
+
+    fn lock_into_inner<T>(lock: parking_lot::RwLock<T>) -> T {
+        lock.into_inner()
+    }
+}
+
+/// The tag of [AsyncLockMark].
+#[cfg(feature = "async-lock")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AsyncLock;
+
+/// A `Mark` paired with an `async_lock::RwLock`, whose locks can be awaited.
+///
+/// Awaiting tasks are queued by the lock and woken when it is released. The
+/// [LockFamily] methods block the thread instead, so with [RcMark], conflicting
+/// locks taken on the same thread deadlock where a [RefCell] would panic.
+#[cfg(feature = "async-lock")]
+pub type AsyncLockMark<Mark> = crate::WrapMark<Mark, (), AsyncLock>;
+
+#[cfg(feature = "async-lock")]
+impl<Mark: RefCountFamily> LockFamily for AsyncLockMark<Mark> {
+    type Lock<T> = async_lock::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = async_lock::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = async_lock::RwLockWriteGuard<'a, T>;
+
+    fn new_lock<T>(value: T) -> async_lock::RwLock<T> {
+        async_lock::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &async_lock::RwLock<T>) -> async_lock::RwLockReadGuard<'_, T> {
+        lock.read_blocking()
+    }
+
+    fn write<T>(lock: &async_lock::RwLock<T>) -> async_lock::RwLockWriteGuard<'_, T> {
+        lock.write_blocking()
+    }
+
+    fn try_read<T>(lock: &async_lock::RwLock<T>) -> Option<async_lock::RwLockReadGuard<'_, T>> {
+        lock.try_read()
+    }
+
+    fn try_write<T>(lock: &async_lock::RwLock<T>) -> Option<async_lock::RwLockWriteGuard<'_, T>> {
+        lock.try_write()
+    }
+
+    fn lock_get_mut<T>(lock: &mut async_lock::RwLock<T>) -> &mut T {
+        lock.get_mut()
+    }
+
+    fn lock_into_inner<T>(lock: async_lock::RwLock<T>) -> T {
+        lock.into_inner()
+    }
+}
+
+#[cfg(feature = "async-lock")]
+impl<Mark: RefCountFamily> AsyncLockFamily for AsyncLockMark<Mark> {
+    fn read_async<'a, T: 'a>(
+        lock: &'a async_lock::RwLock<T>,
+    ) -> impl Future<Output = async_lock::RwLockReadGuard<'a, T>> {
+        lock.read()
+    }
+
+    fn write_async<'a, T: 'a>(
+        lock: &'a async_lock::RwLock<T>,
+    ) -> impl Future<Output = async_lock::RwLockWriteGuard<'a, T>> {
+        lock.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_family() {
+        fn actual_test<R: LockFamily>() {
+            let shared = R::new(R::new_lock(vec![1]));
+            let clone = shared.clone();
+            R::write(&clone).push(2);
+            {
+                let read = R::read(&shared);
+                assert_eq!(*read, [1, 2]);
+                assert!(R::try_read(&shared).is_some());
+                assert!(R::try_write(&shared).is_none());
+            }
+            assert!(R::try_write(&shared).is_some());
+            drop(clone);
+            let mut lock = R::Pointer::try_unwrap(shared).ok().unwrap();
+            R::lock_get_mut(&mut lock).push(3);
+            assert_eq!(R::lock_into_inner(lock), [1, 2, 3]);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        #[cfg(feature = "parking_lot")]
+        actual_test::<ParkingLotArcMark>();
+        #[cfg(feature = "async-lock")]
+        actual_test::<AsyncLockMark<RcMark>>();
+        #[cfg(feature = "async-lock")]
+        actual_test::<AsyncLockMark<ArcMark>>();
+    }
+
+    #[test]
+    fn test_lock_family_threads() {
+        let counter = ArcMark::new(ArcMark::new_lock(0));
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        *ArcMark::write(&counter) += 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(*ArcMark::read(&counter), 400);
+    }
+
+    #[cfg(feature = "async-lock")]
+    #[test]
+    fn test_async_lock_family() {
+        use std::{
+            pin::pin,
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            task::{Context, Poll, Wake, Waker},
+        };
+
+        struct CountWakes(AtomicUsize);
+        impl Wake for CountWakes {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn actual_test<R: AsyncLockFamily>() {
+            let lock = R::new_lock(vec![1]);
+            let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+            let waker = Waker::from(wakes.clone());
+            let mut cx = Context::from_waker(&waker);
+            let guard = R::read(&lock);
+            let mut read = pin!(R::read_async(&lock));
+            assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(ref g) if **g == [1]));
+            let mut write = pin!(R::write_async(&lock));
+            assert!(write.as_mut().poll(&mut cx).is_pending());
+            // Woken by the release, not right away.
+            assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+            drop(guard);
+            assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+            let Poll::Ready(mut guard) = write.as_mut().poll(&mut cx) else {
+                panic!("the lock is free");
+            };
+            guard.push(2);
+            drop(guard);
+            assert_eq!(*R::read(&lock), [1, 2]);
+        }
+        actual_test::<AsyncLockMark<RcMark>>();
+        actual_test::<AsyncLockMark<ArcMark>>();
+    }
+}
@@ -0,0 +1,211 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// A `Cow` whose owned side is a shared pointer: either a borrowed `&'a T`, or a
+/// `Mark::Pointer<T>`.
+///
+/// Borrowed values are promoted to shared ones by cloning them, with
+/// [MaybeShared::to_shared], or by [MaybeShared::to_shared_with] for unsized
+/// values.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn field<'a, R: RefCountFamily>(
+///     cache: &'a [R::Pointer<String>],
+///     name: &'a String,
+/// ) -> MaybeShared<'a, R, String> {
+///     match cache.iter().find(|cached| cached.as_str() == name) {
+///         Some(cached) => MaybeShared::Shared(cached.clone()),
+///         None => MaybeShared::Borrowed(name),
+///     }
+/// }
+/// let cache = [RcMark::new("id".to_owned())];
+/// let (id, name) = ("id".to_owned(), "name".to_owned());
+/// assert!(field::<RcMark>(&cache, &id).is_shared());
+/// let mut name = field::<RcMark>(&cache, &name);
+/// name.make_mut().push('s');
+/// assert_eq!((name.is_shared(), &**name), (true, "names"));
+/// ```
+pub enum MaybeShared<'a, Mark: RefCountFamily, T: ?Sized> {
+    Borrowed(&'a T),
+    Shared(Mark::Pointer<T>),
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> MaybeShared<'a, Mark, T> {
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, MaybeShared::Borrowed(_))
+    }
+
+    pub fn is_shared(&self) -> bool {
+        matches!(self, MaybeShared::Shared(_))
+    }
+
+    /// Returns a shared pointer to the value, promoting a borrowed value with
+    /// `share`.
+    pub fn to_shared_with(&self, share: impl FnOnce(&T) -> Mark::Pointer<T>) -> Mark::Pointer<T> {
+        match self {
+            MaybeShared::Borrowed(value) => share(value),
+            MaybeShared::Shared(pointer) => pointer.clone(),
+        }
+    }
+
+    /// Returns the shared pointer, promoting a borrowed value with `share`.
+    pub fn into_shared_with(self, share: impl FnOnce(&T) -> Mark::Pointer<T>) -> Mark::Pointer<T> {
+        match self {
+            MaybeShared::Borrowed(value) => share(value),
+            MaybeShared::Shared(pointer) => pointer,
+        }
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: Clone> MaybeShared<'a, Mark, T> {
+    /// Returns a shared pointer to the value, cloning a borrowed value into a new
+    /// one.
+    pub fn to_shared(&self) -> Mark::Pointer<T> {
+        self.to_shared_with(|value| Mark::new(value.clone()))
+    }
+
+    pub fn into_shared(self) -> Mark::Pointer<T> {
+        self.into_shared_with(|value| Mark::new(value.clone()))
+    }
+
+    /// Returns the value, cloning it unless this is the only pointer to it.
+    pub fn into_owned(self) -> T {
+        match self {
+            MaybeShared::Borrowed(value) => value.clone(),
+            MaybeShared::Shared(pointer) => {
+                Mark::Pointer::try_unwrap(pointer).unwrap_or_else(|pointer| (*pointer).clone())
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the value, promoting a borrowed value, and
+    /// cloning a shared one unless this is its only pointer.
+    pub fn make_mut(&mut self) -> &mut T {
+        if let MaybeShared::Borrowed(value) = self {
+            *self = MaybeShared::Shared(Mark::new((*value).clone()));
+        }
+        match self {
+            MaybeShared::Shared(pointer) => Mark::Pointer::make_mut(pointer),
+            MaybeShared::Borrowed(_) => unreachable!(),
+        }
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> Clone for MaybeShared<'a, Mark, T> {
+    fn clone(&self) -> Self {
+        match self {
+            MaybeShared::Borrowed(value) => MaybeShared::Borrowed(value),
+            MaybeShared::Shared(pointer) => MaybeShared::Shared(pointer.clone()),
+        }
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> Deref for MaybeShared<'a, Mark, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match self {
+            MaybeShared::Borrowed(value) => value,
+            MaybeShared::Shared(pointer) => pointer,
+        }
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> AsRef<T> for MaybeShared<'a, Mark, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> Borrow<T> for MaybeShared<'a, Mark, T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized> From<&'a T> for MaybeShared<'a, Mark, T> {
+    fn from(value: &'a T) -> Self {
+        MaybeShared::Borrowed(value)
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + fmt::Debug> fmt::Debug for MaybeShared<'a, Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + fmt::Display> fmt::Display for MaybeShared<'a, Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<'a, 'b, Mark: RefCountFamily, T: ?Sized + PartialEq> PartialEq<MaybeShared<'b, Mark, T>>
+    for MaybeShared<'a, Mark, T>
+{
+    fn eq(&self, other: &MaybeShared<'b, Mark, T>) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + Eq> Eq for MaybeShared<'a, Mark, T> {}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + PartialOrd> PartialOrd for MaybeShared<'a, Mark, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + Ord> Ord for MaybeShared<'a, Mark, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + Hash> Hash for MaybeShared<'a, Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_maybe_shared() {
+        fn actual_test<R: RefCountFamily>() {
+            let local = vec![1, 2];
+            let borrowed = MaybeShared::<R, _>::from(&local);
+            let shared = borrowed.to_shared();
+            let promoted = MaybeShared::<R, _>::Shared(shared.clone());
+            assert!(borrowed.is_borrowed() && promoted.is_shared());
+            assert!(borrowed == promoted && borrowed.clone() == promoted.clone());
+            assert_eq!(format!("{borrowed:?} {promoted:?}"), "[1, 2] [1, 2]");
+
+            let mut copy = promoted.clone();
+            copy.make_mut().push(3);
+            assert_eq!((&copy[..], &shared[..]), (&[1, 2, 3][..], &[1, 2][..]));
+            let mut unique = MaybeShared::<R, Vec<i32>>::Shared(R::new(vec![4]));
+            let address: *const Vec<i32> = &*unique;
+            unique.make_mut().push(5);
+            assert!(std::ptr::eq(address, &*unique));
+            assert_eq!(unique.into_owned(), [4, 5]);
+            assert_eq!(promoted.into_shared().len(), 2);
+
+            let text = MaybeShared::<R, str>::Borrowed("text");
+            let text = text.into_shared_with(R::new_str);
+            assert_eq!(&*text, "text");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    cache::{Cache, Lfu, LruCache, WeakCache},
+    lock::LockFamily,
+    traits::*,
+};
+use std::{collections::HashMap, fmt, hash::Hash};
+
+/// Which results a [Memo] keeps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemoPolicy {
+    /// Results live as long as the memo.
+    #[default]
+    Strong,
+    /// Results live as long as handles to them, and are computed anew afterwards.
+    Weak,
+    /// The given number of most recently used results are kept.
+    ///
+    /// # Panics
+    /// Making a memo with a capacity of 0 panics.
+    Lru(usize),
+    /// The given number of most frequently used results are kept.
+    ///
+    /// # Panics
+    /// Making a memo with a capacity of 0 panics.
+    Lfu(usize),
+}
+
+enum MemoCache<Mark: RefCountFamily, K, V> {
+    Strong(HashMap<K, Mark::Pointer<V>>),
+    Weak(WeakCache<Mark, K, V>),
+    Lru(LruCache<Mark, K, V>),
+    Lfu(Cache<Mark, K, V, Lfu<K>>),
+}
+
+/// A function caching its results by argument, and handing out shared pointers
+/// to them.
+///
+/// The cache is not locked while the function runs, so it may call the memo
+/// recursively. Two calls racing on the same argument may both compute it; the
+/// first result stored is returned to both.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let parse = Memo::<RcMark, _, _, _>::with_policy(MemoPolicy::Lru(64), |source: &String| {
+///     source.split(',').map(str::to_owned).collect::<Vec<_>>()
+/// });
+/// let fields = parse.get("a,b".to_owned());
+/// assert!(std::rc::Rc::ptr_eq(&fields, &parse.get("a,b".to_owned())));
+/// assert_eq!(*fields, ["a", "b"]);
+/// ```
+pub struct Memo<Mark: LockFamily, K, V, F> {
+    cache: Mark::Lock<MemoCache<Mark, K, V>>,
+    policy: MemoPolicy,
+    f: F,
+}
+
+impl<Mark, K, V, F> Memo<Mark, K, V, F>
+where
+    Mark: LockFamily,
+    K: Hash + Eq + Clone,
+    F: Fn(&K) -> V,
+{
+    /// Memoizes `f` with [MemoPolicy::Strong].
+    pub fn new(f: F) -> Self {
+        Self::with_policy(MemoPolicy::Strong, f)
+    }
+
+    pub fn with_policy(policy: MemoPolicy, f: F) -> Self {
+        let cache = match policy {
+            MemoPolicy::Strong => MemoCache::Strong(HashMap::new()),
+            MemoPolicy::Weak => MemoCache::Weak(WeakCache::new()),
+            MemoPolicy::Lru(capacity) => MemoCache::Lru(LruCache::new(capacity)),
+            MemoPolicy::Lfu(capacity) => MemoCache::Lfu(Cache::new(capacity)),
+        };
+        Self {
+            cache: Mark::new_lock(cache),
+            policy,
+            f,
+        }
+    }
+
+    /// Returns the result for `key`, computing it first if it is not cached.
+    pub fn get(&self, key: K) -> Mark::Pointer<V> {
+        if let Some(pointer) = self.cached(&key) {
+            return pointer;
+        }
+        let pointer = Mark::new((self.f)(&key));
+        let mut cache = Mark::write(&self.cache);
+        match &mut *cache {
+            MemoCache::Strong(map) => map.entry(key).or_insert(pointer).clone(),
+            MemoCache::Weak(cache) => match cache.get(&key) {
+                Some(pointer) => pointer,
+                None => {
+                    cache.insert_pointer(key, &pointer);
+                    pointer
+                }
+            },
+            MemoCache::Lru(cache) => match cache.get(&key) {
+                Some(pointer) => pointer,
+                None => cache.insert_pointer(key, pointer),
+            },
+            MemoCache::Lfu(cache) => match cache.get(&key) {
+                Some(pointer) => pointer,
+                None => cache.insert_pointer(key, pointer),
+            },
+        }
+    }
+
+    /// Returns the cached result for `key`, if any, without computing it.
+    pub fn cached(&self, key: &K) -> Option<Mark::Pointer<V>> {
+        match &mut *Mark::write(&self.cache) {
+            MemoCache::Strong(map) => map.get(key).cloned(),
+            MemoCache::Weak(cache) => cache.get(key),
+            MemoCache::Lru(cache) => cache.get(key),
+            MemoCache::Lfu(cache) => cache.get(key),
+        }
+    }
+}
+
+impl<Mark: LockFamily, K: Hash + Eq + Clone, V, F> Memo<Mark, K, V, F> {
+    pub fn policy(&self) -> MemoPolicy {
+        self.policy
+    }
+
+    /// Returns the number of cached results, including the dropped ones not
+    /// pruned yet with [MemoPolicy::Weak].
+    pub fn len(&self) -> usize {
+        match &*Mark::read(&self.cache) {
+            MemoCache::Strong(map) => map.len(),
+            MemoCache::Weak(cache) => cache.len(),
+            MemoCache::Lru(cache) => cache.len(),
+            MemoCache::Lfu(cache) => cache.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the cached results.
+    pub fn clear(&self) {
+        match &mut *Mark::write(&self.cache) {
+            MemoCache::Strong(map) => map.clear(),
+            MemoCache::Weak(cache) => cache.clear(),
+            MemoCache::Lru(cache) => cache.clear(),
+            MemoCache::Lfu(cache) => cache.clear(),
+        }
+    }
+}
+
+impl<Mark: LockFamily, K: Hash + Eq + Clone, V, F> fmt::Debug for Memo<Mark, K, V, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Memo")
+            .field("policy", &self.policy)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Returns `f` memoized with [MemoPolicy::Strong]. See [Memo].
+pub fn memoize<Mark, K, V, F>(f: F) -> impl Fn(K) -> Mark::Pointer<V>
+where
+    Mark: LockFamily,
+    K: Hash + Eq + Clone,
+    F: Fn(&K) -> V,
+{
+    memoize_with::<Mark, K, V, F>(MemoPolicy::Strong, f)
+}
+
+/// Returns `f` memoized with `policy`. See [Memo].
+pub fn memoize_with<Mark, K, V, F>(policy: MemoPolicy, f: F) -> impl Fn(K) -> Mark::Pointer<V>
+where
+    Mark: LockFamily,
+    K: Hash + Eq + Clone,
+    F: Fn(&K) -> V,
+{
+    let memo = Memo::<Mark, K, V, F>::with_policy(policy, f);
+    move |key| memo.get(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_memo() {
+        fn actual_test<R: LockFamily>() {
+            let policies = [
+                MemoPolicy::Strong,
+                MemoPolicy::Weak,
+                MemoPolicy::Lru(2),
+                MemoPolicy::Lfu(2),
+            ];
+            for policy in policies {
+                let calls = AtomicUsize::new(0);
+                let memo = Memo::<R, u32, String, _>::with_policy(policy, |n| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    n.to_string()
+                });
+                let one = memo.get(1);
+                assert!(R::Pointer::ptr_eq(&one, &memo.get(1)));
+                assert_eq!((calls.load(Ordering::SeqCst), memo.policy()), (1, policy));
+                drop(one);
+                let two = memo.get(2);
+                memo.get(3);
+                memo.get(1);
+                // The least frequently used result is 2, not 1.
+                let expected = match policy {
+                    MemoPolicy::Strong | MemoPolicy::Lfu(_) => 3,
+                    _ => 4,
+                };
+                assert_eq!(calls.load(Ordering::SeqCst), expected);
+                assert_eq!(*two, "2");
+                memo.clear();
+                assert!(memo.is_empty() && memo.cached(&2).is_none());
+            }
+
+            let square = memoize::<R, _, _, _>(|n: &u64| n * n);
+            assert_eq!((*square(3), *square(3)), (9, 9));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
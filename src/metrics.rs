@@ -0,0 +1,293 @@
+use crate::adapters::*;
+use std::{
+    any::{type_name, TypeId},
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        LazyLock, Mutex, MutexGuard, RwLock,
+    },
+};
+
+/// A [WrapMark] decorator counting the allocations made through it, per wrapped
+/// mark.
+///
+/// `MeteredMark<Mark>` behaves like `Mark`, and updates the [Metrics] of `Mark`,
+/// as returned by [metrics_of] and [metrics].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// type M = MeteredMark<ArcMark>;
+/// let names: Vec<_> = (0..3).map(|i| M::new(i.to_string())).collect();
+/// let copies = names.clone();
+/// drop(names);
+/// let metrics = metrics_of::<ArcMark>();
+/// assert_eq!((metrics.allocations, metrics.live, metrics.peak_live), (3, 3, 3));
+/// drop(copies);
+/// reset_metrics();
+/// assert_eq!(metrics_of::<ArcMark>(), Metrics { mark: metrics.mark, ..Metrics::default() });
+/// ```
+pub type MeteredMark<Mark> = WrapMark<Mark, MetricHooks<Mark>>;
+
+/// The strong pointer of [MeteredMark].
+pub type Metered<Mark, T> = Wrap<Mark, MetricHooks<Mark>, T>;
+
+/// The weak pointer of [MeteredMark].
+pub type MeteredWeak<Mark, T> = WrapWeak<Mark, MetricHooks<Mark>, T>;
+
+/// The counters of a mark used through [MeteredMark].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Metrics {
+    /// The name of the wrapped mark.
+    pub mark: &'static str,
+    /// The number of allocations made.
+    pub allocations: u64,
+    /// The number of allocations whose value is alive.
+    pub live: u64,
+    /// The highest value of `live`.
+    pub peak_live: u64,
+    /// The size of the values allocated, not counting the reference counts nor the
+    /// values cloned by `make_mut`.
+    pub bytes: u64,
+}
+
+/// The counters of a mark, updated without locking.
+struct Meter {
+    mark: &'static str,
+    allocations: AtomicU64,
+    live: AtomicU64,
+    peak_live: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// The meters of the marks used so far, only locked to add a mark or list them.
+static METERS: RwLock<Vec<(TypeId, &'static Meter)>> = RwLock::new(Vec::new());
+
+/// The strong pointers of each live allocation made through [MeteredMark], by
+/// address. The counts of a thread-safe family can't tell which of two concurrent
+/// drops is the last one, so the hooks keep their own.
+static STRONG: LazyLock<[Mutex<HashMap<usize, usize>>; 16]> = LazyLock::new(Default::default);
+
+/// Locks the shard of [STRONG] holding the allocation at `addr`.
+fn strong_counts(addr: *const ()) -> MutexGuard<'static, HashMap<usize, usize>> {
+    // The low bits are the same for all the allocations.
+    let shard = &STRONG[(addr as usize >> 4) % STRONG.len()];
+    shard
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+thread_local! {
+    /// The meters already looked up on this thread.
+    static CACHE: RefCell<HashMap<TypeId, &'static Meter>> = RefCell::default();
+}
+
+/// Returns the meter of `Mark`, leaking a new one the first time.
+fn meter<Mark: 'static>() -> &'static Meter {
+    let find = || {
+        let id = TypeId::of::<Mark>();
+        let found = METERS
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .find(|(mark, _)| *mark == id)
+            .map(|&(_, meter)| meter);
+        found.unwrap_or_else(|| {
+            let mut meters = METERS
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(&(_, meter)) = meters.iter().find(|(mark, _)| *mark == id) {
+                return meter;
+            }
+            let meter = Box::leak(Box::new(Meter {
+                mark: type_name::<Mark>(),
+                allocations: AtomicU64::new(0),
+                live: AtomicU64::new(0),
+                peak_live: AtomicU64::new(0),
+                bytes: AtomicU64::new(0),
+            }));
+            meters.push((id, meter));
+            meter
+        })
+    };
+    // The cache is gone while the thread exits, which may drop pointers.
+    CACHE
+        .try_with(|cache| {
+            *cache
+                .borrow_mut()
+                .entry(TypeId::of::<Mark>())
+                .or_insert_with(find)
+        })
+        .unwrap_or_else(|_| find())
+}
+
+impl Meter {
+    fn metrics(&self) -> Metrics {
+        Metrics {
+            mark: self.mark,
+            allocations: self.allocations.load(Relaxed),
+            live: self.live.load(Relaxed),
+            peak_live: self.peak_live.load(Relaxed),
+            bytes: self.bytes.load(Relaxed),
+        }
+    }
+
+    /// A strong pointer to the allocation at `addr` was made.
+    fn add_strong(&self, addr: *const ()) {
+        let mut counts = strong_counts(addr);
+        let strong = counts.entry(addr as usize).or_insert(0);
+        *strong += 1;
+        if *strong == 1 {
+            let live = self.live.fetch_add(1, Relaxed) + 1;
+            self.peak_live.fetch_max(live, Relaxed);
+        }
+    }
+
+    /// A strong pointer to the allocation at `addr` is dropped.
+    fn remove_strong(&self, addr: *const ()) {
+        let mut counts = strong_counts(addr);
+        if let Entry::Occupied(mut strong) = counts.entry(addr as usize) {
+            *strong.get_mut() -= 1;
+            if *strong.get() == 0 {
+                strong.remove();
+                self.live.fetch_sub(1, Relaxed);
+            }
+        }
+    }
+}
+
+/// The [WrapHooks] of [MeteredMark], updating the metrics of `Mark`.
+///
+/// The value of an allocation is alive from its first strong pointer to the drop
+/// of the last one. An upgrade racing with the last drop may briefly count the
+/// allocation as dead, then alive again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MetricHooks<Mark>(PhantomData<Mark>);
+
+impl<Mark: 'static> WrapHooks for MetricHooks<Mark> {
+    fn on_alloc(size: usize) -> bool {
+        meter::<Mark>().bytes.fetch_add(size as u64, Relaxed);
+        true
+    }
+    fn on_new(addr: *const (), _counts: RefCounts) {
+        let meter = meter::<Mark>();
+        meter.allocations.fetch_add(1, Relaxed);
+        meter.add_strong(addr);
+    }
+    fn on_cyclic_start(_addr: *const (), _counts: RefCounts) {
+        meter::<Mark>().allocations.fetch_add(1, Relaxed);
+    }
+    fn on_cyclic_end(addr: *const (), _counts: RefCounts) {
+        meter::<Mark>().add_strong(addr);
+    }
+    fn on_clone(addr: *const (), _counts: RefCounts) {
+        meter::<Mark>().add_strong(addr);
+    }
+    fn on_upgrade(addr: *const (), upgraded: bool, _counts: RefCounts) {
+        if upgraded {
+            meter::<Mark>().add_strong(addr);
+        }
+    }
+    fn on_drop(addr: *const (), _counts: RefCounts) {
+        meter::<Mark>().remove_strong(addr);
+    }
+}
+
+/// Returns the metrics of `Mark` used through [MeteredMark].
+pub fn metrics_of<Mark: 'static>() -> Metrics {
+    meter::<Mark>().metrics()
+}
+
+/// Returns the metrics of all the marks used through [MeteredMark], ordered by
+/// name.
+pub fn metrics() -> Vec<Metrics> {
+    let mut metrics: Vec<_> = METERS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|(_, meter)| meter.metrics())
+        .collect();
+    metrics.sort_by_key(|m| m.mark);
+    metrics
+}
+
+/// Resets the counters of all the marks, as if the live allocations had just been
+/// made, for instance before running a benchmark.
+pub fn reset_metrics() {
+    for (_, meter) in METERS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+    {
+        meter.allocations.store(0, Relaxed);
+        meter.bytes.store(0, Relaxed);
+        meter.peak_live.store(meter.live.load(Relaxed), Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{traits::*, ArcMark, RcMark};
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+    };
+
+    #[test]
+    fn test_metrics() {
+        fn actual_test<Mark: RefCountFamily + 'static>() {
+            struct Node<Mark: RefCountFamily + 'static> {
+                me: MeteredWeak<Mark, Node<Mark>>,
+            }
+
+            let before = metrics_of::<Mark>();
+            let a = MeteredMark::<Mark>::new(1u32);
+            let b = a.clone();
+            let weak = Metered::downgrade(&a);
+            let node = Metered::<Mark, Node<Mark>>::new_cyclic(|me| Node { me: me.clone() });
+            let after = metrics_of::<Mark>();
+            assert_eq!(after.allocations - before.allocations, 2);
+            assert_eq!(after.live - before.live, 2);
+            assert!(after.bytes - before.bytes >= 4);
+            assert!(after.mark.ends_with("Mark"));
+
+            drop(a);
+            assert_eq!(metrics_of::<Mark>().live, after.live);
+            drop((b, node.me.upgrade(), node));
+            assert!(weak.upgrade().is_none());
+            assert_eq!(metrics_of::<Mark>().live, before.live);
+            assert!(metrics().iter().any(|m| m.mark == after.mark));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        reset_metrics();
+        let reset = metrics_of::<RcMark>();
+        assert_eq!(
+            (reset.allocations, reset.bytes, reset.peak_live),
+            (0, 0, reset.live)
+        );
+    }
+
+    #[test]
+    fn test_metrics_concurrent_drops() {
+        // A mark of its own, so that the other tests don't change its metrics.
+        struct Tag;
+        type M = MeteredMark<WrapMark<ArcMark, (), Tag>>;
+        for _ in 0..200 {
+            let a = M::new(0u8);
+            let b = a.clone();
+            let barrier = Arc::new(Barrier::new(2));
+            let other = barrier.clone();
+            let dropper = thread::spawn(move || {
+                other.wait();
+                drop(b);
+            });
+            barrier.wait();
+            drop(a);
+            dropper.join().unwrap();
+        }
+        assert_eq!(metrics_of::<WrapMark<ArcMark, (), Tag>>().live, 0);
+    }
+}
@@ -0,0 +1,295 @@
+//! [Subject], an event emitter calling shared callbacks, and [Subscription], the
+//! guard removing a callback when dropped.
+//!
+//! Callbacks are [Callback]s, `Mark::Pointer<dyn Fn(&E)>`, which are `Send + Sync`
+//! for thread-safe families, see [DynFamily]. The subject either
+//! holds them, or holds them weakly, so they are removed when their last strong
+//! pointer is dropped. Callbacks are called with the subject unlocked, so they may
+//! subscribe and unsubscribe.
+
+use crate::{
+    lock::LockFamily,
+    shareable::*,
+    shared_cell::{SharedCell, WeakCell},
+    traits::*,
+};
+use std::fmt;
+
+/// A list of callbacks to call with each event.
+///
+/// Clones share the list.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::{cell::RefCell, rc::Rc};
+/// struct Button<R: LockFamily + DynFamily> {
+///     clicked: Subject<R, (i32, i32)>,
+/// }
+/// let button = Button::<RcMark> { clicked: Subject::new() };
+/// let log = Rc::new(RefCell::new(vec![]));
+/// let log2 = log.clone();
+/// let subscription = button.clicked.subscribe(move |&(x, y)| log2.borrow_mut().push(x + y));
+/// button.clicked.emit(&(1, 2));
+/// drop(subscription);
+/// button.clicked.emit(&(3, 4));
+/// assert_eq!(*log.borrow(), [3]);
+/// ```
+pub struct Subject<Mark: LockFamily + DynFamily, E>(SharedCell<Mark, Listeners<Mark, E>>);
+
+/// A shared callback of a [Subject].
+pub type Callback<Mark, E> = <Mark as RefCountFamily>::Pointer<<Mark as DynFamily>::DynCallback<E>>;
+
+struct Listeners<Mark: LockFamily + DynFamily, E> {
+    next_id: u64,
+    entries: Vec<(u64, Listener<Mark, E>)>,
+}
+
+enum Listener<Mark: LockFamily + DynFamily, E> {
+    Strong(Callback<Mark, E>),
+    Weak(Mark::WeakPointer<Mark::DynCallback<E>>),
+}
+
+impl<Mark: LockFamily + DynFamily, E> Listener<Mark, E> {
+    fn upgrade(&self) -> Option<Callback<Mark, E>> {
+        match self {
+            Listener::Strong(callback) => Some(callback.clone()),
+            Listener::Weak(weak) => weak.upgrade(),
+        }
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> Subject<Mark, E> {
+    pub fn new() -> Self {
+        Self(SharedCell::new(Listeners {
+            next_id: 0,
+            entries: Vec::new(),
+        }))
+    }
+
+    /// Calls `f` with each event until the subscription is dropped.
+    pub fn subscribe<F: Fn(&E) + Shareable<Mark>>(&self, f: F) -> Subscription<Mark, E> {
+        self.subscribe_pointer(Mark::from_box(f.into_dyn_callback()))
+    }
+
+    /// Calls `callback` with each event until the subscription is dropped.
+    pub fn subscribe_pointer(&self, callback: Callback<Mark, E>) -> Subscription<Mark, E> {
+        self.add(Listener::Strong(callback))
+    }
+
+    /// Calls `callback` with each event until the subscription or the last strong
+    /// pointer to the callback is dropped.
+    pub fn subscribe_weak(&self, callback: &Callback<Mark, E>) -> Subscription<Mark, E> {
+        self.add(Listener::Weak(Mark::Pointer::downgrade(callback)))
+    }
+
+    fn add(&self, listener: Listener<Mark, E>) -> Subscription<Mark, E> {
+        let mut listeners = self.0.write();
+        let id = listeners.next_id;
+        listeners.next_id += 1;
+        listeners.entries.push((id, listener));
+        Subscription {
+            subject: Some(self.0.downgrade()),
+            id,
+        }
+    }
+
+    /// Calls the callbacks with `event`, in the order they subscribed.
+    pub fn emit(&self, event: &E) {
+        let (callbacks, removed) = {
+            let mut listeners = self.0.write();
+            let mut callbacks = Vec::with_capacity(listeners.entries.len());
+            let removed: Vec<_> = listeners
+                .entries
+                .extract_if(.., |(_, listener)| match listener.upgrade() {
+                    Some(callback) => {
+                        callbacks.push(callback);
+                        false
+                    }
+                    None => true,
+                })
+                .collect();
+            (callbacks, removed)
+        };
+        // Dropped with the listeners unlocked, like the callbacks below.
+        drop(removed);
+        for callback in callbacks {
+            callback(event);
+        }
+    }
+
+    /// Returns the number of callbacks, including the weakly held ones dropped
+    /// since the last event.
+    pub fn len(&self) -> usize {
+        self.0.read().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> Clone for Subject<Mark, E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> Default for Subject<Mark, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> fmt::Debug for Subject<Mark, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subject").field("len", &self.len()).finish()
+    }
+}
+
+/// Removes its callback from its [Subject] when dropped.
+#[must_use = "dropping a subscription removes its callback"]
+pub struct Subscription<Mark: LockFamily + DynFamily, E> {
+    subject: Option<WeakCell<Mark, Listeners<Mark, E>>>,
+    id: u64,
+}
+
+impl<Mark: LockFamily + DynFamily, E> Subscription<Mark, E> {
+    /// Removes the callback now.
+    pub fn unsubscribe(self) {}
+
+    /// Keeps the callback subscribed for as long as the subject lives.
+    pub fn forget(mut self) {
+        self.subject = None;
+    }
+
+    /// Returns `true` while the subject lives and holds the callback.
+    pub fn is_active(&self) -> bool {
+        let Some(listeners) = self.subject.as_ref().and_then(WeakCell::upgrade) else {
+            return false;
+        };
+        let listeners = listeners.read();
+        listeners
+            .entries
+            .iter()
+            .any(|(id, listener)| *id == self.id && listener.upgrade().is_some())
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> Drop for Subscription<Mark, E> {
+    fn drop(&mut self) {
+        if let Some(listeners) = self.subject.as_ref().and_then(WeakCell::upgrade) {
+            let removed = {
+                let mut listeners = listeners.write();
+                let index = listeners.entries.iter().position(|(id, _)| *id == self.id);
+                index.map(|index| listeners.entries.remove(index))
+            };
+            // The callback may own subscriptions to the subject, or emit from its
+            // drop, so it is dropped with the listeners unlocked.
+            drop(removed);
+        }
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, E> fmt::Debug for Subscription<Mark, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_subject() {
+        fn actual_test<R: LockFamily + DynFamily>(
+            total: &AtomicI32,
+            add: impl Fn(&i32) + Clone + Shareable<R>,
+        ) {
+            let subject = Subject::<R, i32>::new();
+            let strong = subject.subscribe(add.clone());
+            let callback: Callback<R, i32> = R::from_box(add.clone().into_dyn_callback());
+            let weak = subject.subscribe_weak(&callback);
+            subject.subscribe(add.clone()).forget();
+            subject.clone().emit(&1);
+            assert_eq!((total.load(Ordering::SeqCst), subject.len()), (3, 3));
+
+            drop(callback);
+            assert!(strong.is_active() && !weak.is_active());
+            subject.emit(&10);
+            assert_eq!((total.load(Ordering::SeqCst), subject.len()), (23, 2));
+            strong.unsubscribe();
+            subject.emit(&100);
+            assert_eq!((total.load(Ordering::SeqCst), subject.len()), (123, 1));
+
+            let late = subject.subscribe(add);
+            drop(subject);
+            assert!(!late.is_active());
+        }
+        fn adder(total: &Arc<AtomicI32>) -> impl Fn(&i32) + Clone + Send + Sync {
+            let total = total.clone();
+            move |e| {
+                total.fetch_add(*e, Ordering::SeqCst);
+            }
+        }
+        let total = Arc::new(AtomicI32::new(0));
+        actual_test::<RcMark>(&total, adder(&total));
+        let total = Arc::new(AtomicI32::new(0));
+        actual_test::<ArcMark>(&total, adder(&total));
+    }
+
+    #[test]
+    fn test_subject_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Subject<ArcMark, i32>>();
+        assert_send_sync::<Subscription<ArcMark, i32>>();
+        let subject = Subject::<ArcMark, i32>::new();
+        let total = Arc::new(AtomicI32::new(0));
+        let added = total.clone();
+        subject
+            .subscribe(move |e| {
+                added.fetch_add(*e, Ordering::SeqCst);
+            })
+            .forget();
+        let emitter = subject.clone();
+        std::thread::spawn(move || emitter.emit(&2)).join().unwrap();
+        assert_eq!(total.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_unsubscribe_from_callback_drop() {
+        fn actual_test<R: LockFamily + DynFamily + 'static, F: Fn(&i32) + Shareable<R>>(
+            ignore: impl Fn(&i32) + Shareable<R>,
+            owner: impl FnOnce(Subscription<R, i32>) -> F,
+        ) {
+            let subject = Subject::<R, i32>::new();
+            let inner = subject.subscribe(ignore);
+            let outer = subject.subscribe(owner(inner));
+            subject.emit(&1);
+            assert_eq!(subject.len(), 2);
+            drop(outer);
+            assert_eq!(subject.len(), 0);
+        }
+        actual_test::<RcMark, _>(|_| (), |inner| move |_| assert!(inner.is_active()));
+        actual_test::<ArcMark, _>(|_| (), |inner| move |_| assert!(inner.is_active()));
+    }
+
+    #[test]
+    fn test_subscribe_from_callback() {
+        let subject = Subject::<RcMark, ()>::new();
+        let inner = subject.clone();
+        subject
+            .subscribe(move |_| inner.subscribe(|_| ()).forget())
+            .forget();
+        subject.emit(&());
+        subject.emit(&());
+        assert_eq!(subject.len(), 3);
+    }
+}
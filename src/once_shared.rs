@@ -0,0 +1,284 @@
+//! [OnceShared], a slot set at most once with a shared pointer, and [LazyShared],
+//! which sets it with its constructor on first use.
+//!
+//! The slot of each family is given by [OnceFamily]: [RcMark] uses a
+//! [std::cell::OnceCell], and [ArcMark] a [std::sync::OnceLock], which blocks
+//! concurrent initializers until the first one is done.
+
+use crate::{lock::LockFamily, traits::*, ArcMark, RcMark};
+use std::{cell::OnceCell, fmt, ops::Deref, sync::OnceLock};
+
+/// A [RefCountFamily] paired with its once-cell, as in `OnceCell<Rc<T>>` and
+/// `OnceLock<Arc<T>>`.
+pub trait OnceFamily: RefCountFamily {
+    type Once<T>;
+    fn new_once<T>() -> Self::Once<T>;
+    fn once_get<T>(once: &Self::Once<T>) -> Option<&T>;
+    /// Sets the value if there is none, returning `value` back otherwise.
+    fn once_set<T>(once: &Self::Once<T>, value: T) -> Result<(), T>;
+    fn once_get_or_init<T, F: FnOnce() -> T>(once: &Self::Once<T>, f: F) -> &T;
+    fn once_into_inner<T>(once: Self::Once<T>) -> Option<T>;
+}
+
+/// A slot holding a `Mark::Pointer<T>` once it is set, handing out clones of it.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Registry<R: OnceFamily> {
+///     names: OnceShared<R, Vec<String>>,
+/// }
+/// impl<R: OnceFamily> Registry<R> {
+///     fn names(&self) -> R::Pointer<Vec<String>> {
+///         self.names.get_or_init(|| vec!["John Doe".to_owned()])
+///     }
+/// }
+/// let registry = Registry::<ArcMark> { names: OnceShared::new() };
+/// assert!(registry.names.get().is_none());
+/// let names = registry.names();
+/// assert!(std::sync::Arc::ptr_eq(&names, &registry.names()));
+/// ```
+pub struct OnceShared<Mark: OnceFamily, T>(Mark::Once<Mark::Pointer<T>>);
+
+impl<Mark: OnceFamily, T> OnceShared<Mark, T> {
+    pub fn new() -> Self {
+        Self(Mark::new_once())
+    }
+
+    /// Creates a slot already set to `pointer`.
+    pub fn with_pointer(pointer: Mark::Pointer<T>) -> Self {
+        let once = Self::new();
+        // The slot was just created, so it is empty.
+        let _ = once.set(pointer);
+        once
+    }
+
+    /// Returns a clone of the pointer, if it was set.
+    pub fn get(&self) -> Option<Mark::Pointer<T>> {
+        Mark::once_get(&self.0).cloned()
+    }
+
+    /// Sets the pointer if there is none, returning `pointer` back otherwise.
+    pub fn set(&self, pointer: Mark::Pointer<T>) -> Result<(), Mark::Pointer<T>> {
+        Mark::once_set(&self.0, pointer)
+    }
+
+    /// Returns a clone of the pointer, setting it to a pointer to `f()` first if
+    /// there is none.
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> Mark::Pointer<T> {
+        Mark::once_get_or_init(&self.0, || Mark::new(f())).clone()
+    }
+
+    pub fn into_inner(self) -> Option<Mark::Pointer<T>> {
+        Mark::once_into_inner(self.0)
+    }
+}
+
+impl<Mark: OnceFamily, T> Default for OnceShared<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: OnceFamily, T: fmt::Debug> fmt::Debug for OnceShared<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("OnceShared");
+        match Mark::once_get(&self.0) {
+            Some(pointer) => tuple.field(&**pointer),
+            None => tuple.field(&format_args!("<uninit>")),
+        };
+        tuple.finish()
+    }
+}
+
+/// A shared value built by `F` on first use, handing out clones of its pointer.
+///
+/// The generic counterpart of `LazyLock<Arc<T>>`: `F` runs at most once, other
+/// threads waiting for it with a thread-safe family. The constructor is kept in
+/// the lock of the [LockFamily] until then.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn names<R: OnceFamily + LockFamily>() -> LazyShared<R, Vec<String>> {
+///     LazyShared::new(|| vec!["John Doe".to_owned()])
+/// }
+/// let lazy = names::<RcMark>();
+/// assert!(lazy.get().is_none());
+/// assert_eq!(lazy.len(), 1);
+/// assert!(std::rc::Rc::ptr_eq(&lazy.force(), &lazy.get().unwrap()));
+/// ```
+pub struct LazyShared<Mark: OnceFamily + LockFamily, T, F = fn() -> T> {
+    once: OnceShared<Mark, T>,
+    init: Mark::Lock<Option<F>>,
+}
+
+impl<Mark: OnceFamily + LockFamily, T, F: FnOnce() -> T> LazyShared<Mark, T, F> {
+    pub fn new(f: F) -> Self {
+        Self {
+            once: OnceShared::new(),
+            init: Mark::new_lock(Some(f)),
+        }
+    }
+
+    fn pointer(&self) -> &Mark::Pointer<T> {
+        Mark::once_get_or_init(&self.once.0, || {
+            let f = Mark::write(&self.init).take();
+            Mark::new(f.expect("LazyShared constructor panicked")())
+        })
+    }
+
+    /// Returns a clone of the pointer, building the value first if needed.
+    pub fn force(&self) -> Mark::Pointer<T> {
+        self.pointer().clone()
+    }
+
+    /// Returns a clone of the pointer, if the value was built.
+    pub fn get(&self) -> Option<Mark::Pointer<T>> {
+        self.once.get()
+    }
+}
+
+impl<Mark: OnceFamily + LockFamily, T, F: FnOnce() -> T> Deref for LazyShared<Mark, T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.pointer()
+    }
+}
+
+impl<Mark: OnceFamily + LockFamily, T: Default> Default for LazyShared<Mark, T> {
+    fn default() -> Self {
+        Self::new(T::default)
+    }
+}
+
+impl<Mark: OnceFamily + LockFamily, T: fmt::Debug, F> fmt::Debug for LazyShared<Mark, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("LazyShared");
+        match Mark::once_get(&self.once.0) {
+            Some(pointer) => tuple.field(&**pointer),
+            None => tuple.field(&format_args!("<uninit>")),
+        };
+        tuple.finish()
+    }
+}
+
+impl OnceFamily for RcMark {
+    type Once<T> = OnceCell<T>;
+
+    fn new_once<T>() -> OnceCell<T> {
+        OnceCell::new()
+    }
+
+    fn once_get<T>(once: &OnceCell<T>) -> Option<&T> {
+        once.get()
+    }
+
+    fn once_set<T>(once: &OnceCell<T>, value: T) -> Result<(), T> {
+        once.set(value)
+    }
+
+    fn once_get_or_init<T, F: FnOnce() -> T>(once: &OnceCell<T>, f: F) -> &T {
+        once.get_or_init(f)
+    }
+
+    fn once_into_inner<T>(once: OnceCell<T>) -> Option<T> {
+        once.into_inner()
+    }
+}
+
+impl OnceFamily for ArcMark {
+    type Once<T> = OnceLock<T>;
+
+    fn new_once<T>() -> OnceLock<T> {
+        OnceLock::new()
+    }
+
+    fn once_get<T>(once: &OnceLock<T>) -> Option<&T> {
+        once.get()
+    }
+
+    fn once_set<T>(once: &OnceLock<T>, value: T) -> Result<(), T> {
+        once.set(value)
+    }
+
+    fn once_get_or_init<T, F: FnOnce() -> T>(once: &OnceLock<T>, f: F) -> &T {
+        once.get_or_init(f)
+    }
+
+    fn once_into_inner<T>(once: OnceLock<T>) -> Option<T> {
+        once.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_once_shared() {
+        fn actual_test<R: OnceFamily>() {
+            let once = OnceShared::<R, i32>::new();
+            assert!(once.get().is_none());
+            assert_eq!(format!("{once:?}"), "OnceShared(<uninit>)");
+            let first = once.get_or_init(|| 1);
+            let second = once.get_or_init(|| unreachable!());
+            assert!(R::Pointer::ptr_eq(&first, &second));
+            let rejected = once.set(R::new(2)).unwrap_err();
+            assert_eq!((*rejected, *once.get().unwrap()), (2, 1));
+            assert_eq!(format!("{once:?}"), "OnceShared(1)");
+            assert_eq!(R::Pointer::strong_count(&first), 3);
+            let inner = once.into_inner().unwrap();
+            assert!(R::Pointer::ptr_eq(&first, &inner));
+            let set = OnceShared::<R, i32>::with_pointer(R::new(3));
+            assert_eq!(*set.get().unwrap(), 3);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_lazy_shared() {
+        fn actual_test<R: OnceFamily + LockFamily>() {
+            let calls = std::cell::Cell::new(0);
+            let lazy = LazyShared::<R, _, _>::new(|| {
+                calls.set(calls.get() + 1);
+                vec![1, 2]
+            });
+            assert!(lazy.get().is_none());
+            assert_eq!(format!("{lazy:?}"), "LazyShared(<uninit>)");
+            assert_eq!(lazy.len(), 2);
+            let pointer = lazy.force();
+            assert!(R::Pointer::ptr_eq(&pointer, &lazy.get().unwrap()));
+            assert_eq!((calls.get(), R::Pointer::strong_count(&pointer)), (1, 2));
+            assert_eq!(format!("{lazy:?}"), "LazyShared([1, 2])");
+            assert_eq!(*LazyShared::<R, i32>::default(), 0);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_lazy_shared_threads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let lazy = &LazyShared::<ArcMark, usize>::new(|| CALLS.fetch_add(1, Ordering::SeqCst));
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(move || assert_eq!(**lazy, 0));
+            }
+        });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_once_shared_threads() {
+        let once = &OnceShared::<ArcMark, usize>::new();
+        let pointers: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|i| scope.spawn(move || once.get_or_init(|| i)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(pointers.iter().all(|p| Arc::ptr_eq(p, &pointers[0])));
+    }
+}
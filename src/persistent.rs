@@ -0,0 +1,77 @@
+//! [rpds](https://docs.rs/rpds) persistent collections parameterized by a family.
+//!
+//! rpds collections are generic over an archery pointer kind. These aliases plug
+//! in [FamilyKind], so a structure written against [RefCountFamily] can hold
+//! persistent collections sharing nodes through `Mark::Pointer`.
+//!
+//! The aliases are not re-exported at the crate root, where names like `List`
+//! would be too generic.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! use cark_ref_counted::persistent::Vector;
+//! struct History<R: RefCountFamily> {
+//!     states: Vector<R, i32>,
+//! }
+//! fn push<R: RefCountFamily>(history: &History<R>, state: i32) -> History<R> {
+//!     History {
+//!         states: history.states.push_back(state),
+//!     }
+//! }
+//! let empty = History::<ArcMark> { states: Vector::new_with_ptr_kind() };
+//! let one = push(&empty, 1);
+//! assert_eq!((empty.states.len(), one.states.len()), (0, 1));
+//! ```
+
+use crate::FamilyKind;
+use std::collections::hash_map::RandomState;
+
+pub type List<Mark, T> = rpds::List<T, FamilyKind<Mark>>;
+pub type Stack<Mark, T> = rpds::Stack<T, FamilyKind<Mark>>;
+pub type Queue<Mark, T> = rpds::Queue<T, FamilyKind<Mark>>;
+pub type Vector<Mark, T> = rpds::Vector<T, FamilyKind<Mark>>;
+pub type HashTrieMap<Mark, K, V, H = RandomState> = rpds::HashTrieMap<K, V, FamilyKind<Mark>, H>;
+pub type HashTrieSet<Mark, T, H = RandomState> = rpds::HashTrieSet<T, FamilyKind<Mark>, H>;
+pub type RedBlackTreeMap<Mark, K, V> = rpds::RedBlackTreeMap<K, V, FamilyKind<Mark>>;
+pub type RedBlackTreeSet<Mark, T> = rpds::RedBlackTreeSet<T, FamilyKind<Mark>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark, RefCountFamily};
+
+    #[test]
+    fn test_persistent() {
+        fn actual_test<R: RefCountFamily>() {
+            let list: List<R, i32> = List::new_with_ptr_kind().push_front(2).push_front(1);
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2]);
+            let vector: Vector<R, i32> = Vector::new_with_ptr_kind().push_back(1);
+            let vector2 = vector.set(0, 2).unwrap();
+            assert_eq!((vector[0], vector2[0]), (1, 2));
+            let map: HashTrieMap<R, &str, i32> = HashTrieMap::default().insert("a", 1);
+            let map2 = map.insert("b", 2).remove("a");
+            assert_eq!(
+                (map.get("a"), map2.get("b"), map2.size()),
+                (Some(&1), Some(&2), 1)
+            );
+            let set: RedBlackTreeSet<R, i32> = [3, 1, 2].into_iter().collect();
+            assert_eq!(set.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+            let queue: Queue<R, i32> = Queue::new_with_ptr_kind().enqueue(1).enqueue(2);
+            assert_eq!(queue.dequeue().unwrap().peek(), Some(&2));
+            let stack: Stack<R, i32> = Stack::new_with_ptr_kind().push(1);
+            assert_eq!(stack.peek(), Some(&1));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_persistent_send() {
+        let map: HashTrieMap<ArcMark, i32, i32> = HashTrieMap::default().insert(1, 2);
+        let sent = map.clone();
+        let value = std::thread::spawn(move || sent.get(&1).copied())
+            .join()
+            .unwrap();
+        assert_eq!(value, Some(2));
+    }
+}
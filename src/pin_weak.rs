@@ -0,0 +1,93 @@
+use crate::{pinned::unpinned, traits::*};
+use std::{fmt, pin::Pin};
+
+/// A weak reference to a pinned [RefCountFamily] pointer.
+///
+/// It can only be created from a `Pin<Mark::Pointer<T>>`, and upgrades
+/// straight back to a `Pin<Mark::Pointer<T>>`. The strong pointer is never
+/// handed out unpinned, so the pinning guarantee holds for as long as the
+/// allocation lives.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::{pin::Pin, sync::Arc};
+/// let pinned = Arc::pin(5);
+/// let weak = PinWeak::<ArcMark, i32>::downgrade(&pinned);
+/// let upgraded: Pin<_> = weak.upgrade().unwrap();
+/// assert_eq!(*upgraded, 5);
+/// drop((pinned, upgraded));
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct PinWeak<Mark: RefCountFamily, T: ?Sized>(Mark::WeakPointer<T>);
+
+impl<Mark: RefCountFamily, T: ?Sized> PinWeak<Mark, T> {
+    pub fn downgrade(this: &Pin<Mark::Pointer<T>>) -> Self {
+        Self(Mark::Pointer::downgrade(unpinned(this)))
+    }
+
+    pub fn upgrade(&self) -> Option<Pin<Mark::Pointer<T>>> {
+        // SAFETY: the weak pointer was created from a pinned pointer, and
+        // no unpinned strong pointer to that allocation is ever given out.
+        self.0
+            .upgrade()
+            .map(|pointer| unsafe { Pin::new_unchecked(pointer) })
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.0.strong_count()
+    }
+
+    pub fn weak_count(&self) -> usize {
+        self.0.weak_count()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for PinWeak<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> fmt::Debug for PinWeak<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PinWeak(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::marker::PhantomPinned;
+
+    struct Node {
+        value: i32,
+        _pinned: PhantomPinned,
+    }
+
+    #[test]
+    fn test_upgrade() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let pinned = Mark::Pointer::pin(Node {
+                value: 3,
+                _pinned: PhantomPinned,
+            });
+            let weak = PinWeak::<Mark, Node>::downgrade(&pinned);
+            assert_eq!(weak.strong_count(), 1);
+            assert_eq!(weak.weak_count(), 1);
+            let upgraded = weak.upgrade().unwrap();
+            assert_eq!(upgraded.value, 3);
+            assert_eq!(weak.strong_count(), 2);
+            assert!(weak.ptr_eq(&PinWeak::downgrade(&upgraded)));
+            drop(pinned);
+            drop(upgraded);
+            assert!(weak.upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{pin_weak::PinWeak, traits::*};
+use std::{marker::PhantomData, ops::Deref, pin::Pin};
+
+/// Views a pinned pointer as the pointer itself.
+///
+/// The result must only be used to look at the pointer and its counts. Cloning
+/// it would give an unpinned strong pointer to a pinned value.
+pub(crate) fn unpinned<P>(this: &Pin<P>) -> &P {
+    // SAFETY: Pin is repr(transparent), and only a shared reference is handed out.
+    unsafe { &*(this as *const Pin<P>).cast::<P>() }
+}
+
+/// Helpers for `Pin<Pointer<T>>`, implemented for all [RefCounted] pointers.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::rc::Rc;
+/// let pinned = Rc::pin(5);
+/// let weak = Rc::downgrade_pinned(&pinned);
+/// let other = Rc::upgrade_pinned(&weak).unwrap();
+/// assert!(Rc::ptr_eq_pinned(&pinned, &other));
+/// assert_eq!(Rc::strong_count_pinned(&pinned), 2);
+/// ```
+pub trait PinnedRefCounted<T: ?Sized>: RefCounted<T> {
+    fn downgrade_pinned(this: &Pin<Self>) -> PinWeak<Self::Mark, T> {
+        PinWeak::downgrade(this)
+    }
+
+    fn upgrade_pinned(weak: &PinWeak<Self::Mark, T>) -> Option<Pin<Self>> {
+        weak.upgrade()
+    }
+
+    fn as_ptr_pinned(this: &Pin<Self>) -> *const T {
+        Self::as_ptr(unpinned(this))
+    }
+
+    fn ptr_eq_pinned(this: &Pin<Self>, other: &Pin<Self>) -> bool {
+        Self::ptr_eq(unpinned(this), unpinned(other))
+    }
+
+    fn strong_count_pinned(this: &Pin<Self>) -> usize {
+        Self::strong_count(unpinned(this))
+    }
+
+    fn weak_count_pinned(this: &Pin<Self>) -> usize {
+        Self::weak_count(unpinned(this))
+    }
+}
+
+impl<T: ?Sized, P: RefCounted<T>> PinnedRefCounted<T> for P {}
+
+/// A family whose pointers are always pinned.
+///
+/// Pinned pointers can't be a [RefCountFamily]: operations like
+/// [RefCounted::try_unwrap] or [RefCounted::get_mut] would move the value out
+/// of its pin. This is the subset that keeps the pinning guarantee.
+pub trait PinFamily {
+    type Pointer<T: ?Sized>: Deref<Target = T> + Clone;
+    type WeakPointer<T: ?Sized>: Clone;
+    fn pin<T>(value: T) -> Self::Pointer<T>;
+    fn downgrade<T: ?Sized>(this: &Self::Pointer<T>) -> Self::WeakPointer<T>;
+    fn upgrade<T: ?Sized>(weak: &Self::WeakPointer<T>) -> Option<Self::Pointer<T>>;
+    fn strong_count<T: ?Sized>(this: &Self::Pointer<T>) -> usize;
+    fn ptr_eq<T: ?Sized>(this: &Self::Pointer<T>, other: &Self::Pointer<T>) -> bool;
+}
+
+/// Adapts a [RefCountFamily] into a [PinFamily], with `Pin<Mark::Pointer<T>>` pointers.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::marker::PhantomPinned;
+/// struct Node<P: PinFamily> {
+///     value: i32,
+///     parent: Option<P::WeakPointer<Node<P>>>,
+///     _pinned: PhantomPinned,
+/// }
+/// fn tree<P: PinFamily>() -> (P::Pointer<Node<P>>, P::Pointer<Node<P>>) {
+///     let root = P::pin(Node { value: 1, parent: None, _pinned: PhantomPinned });
+///     let parent = Some(P::downgrade(&root));
+///     let child = P::pin(Node { value: 2, parent, _pinned: PhantomPinned });
+///     (root, child)
+/// }
+/// let (_root, child) = tree::<PinMark<ArcMark>>();
+/// let parent = child.parent.as_ref().unwrap();
+/// assert_eq!(PinMark::<ArcMark>::upgrade(parent).unwrap().value, 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PinMark<Mark: RefCountFamily>(PhantomData<Mark>);
+
+impl<Mark: RefCountFamily> PinFamily for PinMark<Mark> {
+    type Pointer<T: ?Sized> = Pin<Mark::Pointer<T>>;
+    type WeakPointer<T: ?Sized> = PinWeak<Mark, T>;
+
+    fn pin<T>(value: T) -> Pin<Mark::Pointer<T>> {
+        Mark::Pointer::pin(value)
+    }
+
+    fn downgrade<T: ?Sized>(this: &Pin<Mark::Pointer<T>>) -> PinWeak<Mark, T> {
+        PinWeak::downgrade(this)
+    }
+
+    fn upgrade<T: ?Sized>(weak: &PinWeak<Mark, T>) -> Option<Pin<Mark::Pointer<T>>> {
+        weak.upgrade()
+    }
+
+    fn strong_count<T: ?Sized>(this: &Pin<Mark::Pointer<T>>) -> usize {
+        Mark::Pointer::strong_count_pinned(this)
+    }
+
+    fn ptr_eq<T: ?Sized>(this: &Pin<Mark::Pointer<T>>, other: &Pin<Mark::Pointer<T>>) -> bool {
+        Mark::Pointer::ptr_eq_pinned(this, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{rc::Rc, sync::Arc};
+
+    #[test]
+    fn test_pinned() {
+        fn actual_test<RC: PinnedRefCounted<i32>>() {
+            let a = RC::pin(3);
+            let b = a.clone();
+            let weak = RC::downgrade_pinned(&a);
+            assert!(RC::ptr_eq_pinned(&a, &b));
+            assert!(!RC::ptr_eq_pinned(&a, &RC::pin(3)));
+            assert_eq!(RC::strong_count_pinned(&a), 2);
+            assert_eq!(RC::weak_count_pinned(&a), 1);
+            assert_eq!(unsafe { *RC::as_ptr_pinned(&a) }, 3);
+            drop((a, b));
+            assert!(RC::upgrade_pinned(&weak).is_none());
+        }
+        actual_test::<Rc<_>>();
+        actual_test::<Arc<_>>()
+    }
+
+    #[test]
+    fn test_pin_mark() {
+        fn actual_test<P: PinFamily>() {
+            let a = P::pin(3);
+            let b = a.clone();
+            assert!(P::ptr_eq(&a, &b));
+            assert_eq!(P::strong_count(&a), 2);
+            let weak = P::downgrade(&a);
+            assert_eq!(P::upgrade(&weak).as_deref(), Some(&3));
+            drop((a, b));
+            assert!(P::upgrade(&weak).is_none());
+        }
+        actual_test::<PinMark<crate::RcMark>>();
+        actual_test::<PinMark<crate::ArcMark>>()
+    }
+}
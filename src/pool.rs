@@ -0,0 +1,213 @@
+use crate::{lock::LockFamily, traits::*};
+use std::{fmt, ops::Deref};
+
+/// A pool of reusable values, such as buffers, handed out as shared [Pooled]
+/// handles.
+///
+/// When the last handle to a value is dropped, the value goes back to the pool
+/// instead of being dropped, unless the pool is gone or already holds
+/// [Pool::max_idle] values. Values are handed out again as they were returned, so
+/// they should be reset by their user.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn read_packet<R: LockFamily>(pool: &Pool<R, Vec<u8>>, packet: &[u8]) -> Pooled<R, Vec<u8>> {
+///     let mut buffer = pool.get();
+///     let bytes = buffer.get_mut().unwrap();
+///     bytes.clear();
+///     bytes.extend_from_slice(packet);
+///     buffer
+/// }
+/// let pool = Pool::<RcMark, Vec<u8>>::new();
+/// let first = read_packet(&pool, b"hello");
+/// let address = first.as_ptr();
+/// drop(first);
+/// let second = read_packet(&pool, b"world");
+/// assert_eq!((&**second, second.as_ptr()), (&b"world"[..], address));
+/// ```
+pub struct Pool<Mark: LockFamily, T>(Mark::Pointer<PoolShared<Mark, T>>);
+
+struct PoolShared<Mark: LockFamily, T> {
+    idle: Mark::Lock<Vec<T>>,
+    max_idle: usize,
+}
+
+impl<Mark: LockFamily, T> Pool<Mark, T> {
+    /// Creates a pool keeping all the values returned to it.
+    pub fn new() -> Self {
+        Self::with_max_idle(usize::MAX)
+    }
+
+    /// Creates a pool keeping at most `max_idle` values, dropping the others
+    /// returned to it.
+    pub fn with_max_idle(max_idle: usize) -> Self {
+        Self(Mark::new(PoolShared {
+            idle: Mark::new_lock(Vec::new()),
+            max_idle,
+        }))
+    }
+
+    pub fn max_idle(&self) -> usize {
+        self.0.max_idle
+    }
+
+    /// Returns the number of values waiting to be reused.
+    pub fn idle_count(&self) -> usize {
+        Mark::read(&self.0.idle).len()
+    }
+
+    /// Returns a handle to an idle value, or to `make()` if there is none.
+    ///
+    /// The pool is not locked while `make` runs, so it may use the pool.
+    pub fn get_or_else(&self, make: impl FnOnce() -> T) -> Pooled<Mark, T> {
+        let idle = Mark::write(&self.0.idle).pop();
+        let value = idle.unwrap_or_else(make);
+        Pooled(Mark::new(PooledValue {
+            value: Some(value),
+            pool: Mark::Pointer::downgrade(&self.0),
+        }))
+    }
+
+    /// Returns a handle to an idle value, or to a default one if there is none.
+    pub fn get(&self) -> Pooled<Mark, T>
+    where
+        T: Default,
+    {
+        self.get_or_else(T::default)
+    }
+
+    /// Adds `value` to the idle values, or drops it if the pool is full.
+    pub fn put(&self, value: T) {
+        self.0.put(value);
+    }
+
+    /// Drops the idle values.
+    pub fn clear(&self) {
+        Mark::write(&self.0.idle).clear();
+    }
+}
+
+impl<Mark: LockFamily, T> PoolShared<Mark, T> {
+    fn put(&self, value: T) {
+        let mut idle = Mark::write(&self.idle);
+        if idle.len() < self.max_idle {
+            idle.push(value);
+        }
+    }
+}
+
+impl<Mark: LockFamily, T> Clone for Pool<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily, T> Default for Pool<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: LockFamily, T> fmt::Debug for Pool<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("idle_count", &self.idle_count())
+            .field("max_idle", &self.0.max_idle)
+            .finish()
+    }
+}
+
+/// A shared handle to a value of a [Pool], returning it to the pool when the
+/// last handle is dropped.
+pub struct Pooled<Mark: LockFamily, T>(Mark::Pointer<PooledValue<Mark, T>>);
+
+struct PooledValue<Mark: LockFamily, T> {
+    /// Only `None` while dropped or detached.
+    value: Option<T>,
+    pool: Mark::WeakPointer<PoolShared<Mark, T>>,
+}
+
+impl<Mark: LockFamily, T> Pooled<Mark, T> {
+    /// Returns a mutable reference to the value if this is its only handle.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        Mark::Pointer::get_mut(&mut self.0)?.value.as_mut()
+    }
+
+    /// Takes the value out of the pool if this is its only handle, so it is
+    /// dropped normally.
+    pub fn detach(self) -> Result<T, Self> {
+        match Mark::Pointer::try_unwrap(self.0) {
+            Ok(mut pooled) => Ok(pooled.value.take().expect("the value is present")),
+            Err(pointer) => Err(Self(pointer)),
+        }
+    }
+
+    /// Returns `true` if both are handles to the same value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: LockFamily, T> Drop for PooledValue<Mark, T> {
+    fn drop(&mut self) {
+        if let (Some(value), Some(pool)) = (self.value.take(), self.pool.upgrade()) {
+            pool.put(value);
+        }
+    }
+}
+
+impl<Mark: LockFamily, T> Clone for Pooled<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily, T> Deref for Pooled<Mark, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0.value.as_ref().expect("the value is present")
+    }
+}
+
+impl<Mark: LockFamily, T: fmt::Debug> fmt::Debug for Pooled<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Pooled").field(&**self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_pool() {
+        fn actual_test<R: LockFamily>() {
+            let pool = Pool::<R, Vec<i32>>::with_max_idle(1);
+            let mut a = pool.get_or_else(|| Vec::with_capacity(16));
+            a.get_mut().unwrap().push(1);
+            let shared = a.clone();
+            assert!(a.get_mut().is_none() && shared.ptr_eq(&a));
+            assert_eq!(format!("{a:?}"), "Pooled([1])");
+            drop((a, shared));
+            assert_eq!(pool.idle_count(), 1);
+
+            let b = pool.get();
+            assert_eq!((&*b, b.capacity(), pool.idle_count()), (&vec![1], 16, 0));
+            let c = pool.get();
+            drop((b, c));
+            assert_eq!(pool.idle_count(), 1);
+
+            let d = pool.get();
+            assert_eq!(d.detach().unwrap(), [1]);
+            assert_eq!(pool.idle_count(), 0);
+            let e = pool.get_or_else(|| vec![pool.idle_count() as i32]);
+            assert_eq!(*e, [0]);
+            pool.put(vec![2]);
+            drop(pool);
+            drop(e);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,19 @@
+//! The traits, marks and aliases most family generic code needs.
+//!
+//! ```
+//! use cark_ref_counted::prelude::*;
+//!
+//! struct Gadget<M: RefCountFamily> {
+//!     me: Weak<M, Self>,
+//! }
+//!
+//! fn gadget<M: RefCountFamily>() -> Strong<M, Gadget<M>> {
+//!     M::Pointer::new_cyclic(|me| Gadget { me: me.clone() })
+//! }
+//! let g = gadget::<ArcMark>();
+//! assert!(RefCounted::ptr_eq(&g, &g.me.upgrade().unwrap()));
+//! ```
+
+pub use crate::{
+    ArcMark, RcMark, RefCountFamily, RefCounted, RefCountedExt, Strong, Weak, WeakPointer,
+};
@@ -0,0 +1,363 @@
+use crate::{adapters::*, traits::*};
+use std::{collections::HashMap, error::Error, fmt, sync::Mutex};
+
+/// A [WrapMark] decorator recording the pointer operations done through it.
+///
+/// `RecordMark<Mark>` behaves like `Mark`. While a recording is running (see
+/// [start_recording]), each operation on its pointers is appended to a global log,
+/// tagged with the id of the allocation it touched. The resulting [Recording] can be
+/// checked against a counting model, or replayed against another mark.
+///
+/// Only allocations created while recording are tracked.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// start_recording();
+/// let a = RecordMark::<RcMark>::new(1);
+/// let weak = RefCounted::downgrade(&a);
+/// drop(a);
+/// assert!(weak.upgrade().is_none());
+/// drop(weak);
+/// let recording = stop_recording();
+/// assert_eq!(
+///     recording.ops(),
+///     [Op::New(0), Op::Downgrade(0), Op::Drop(0), Op::Upgrade(0, false), Op::DropWeak(0)]
+/// );
+/// assert!(recording.replay_model().is_ok());
+/// assert!(recording.replay::<ArcMark>().is_ok());
+/// ```
+pub type RecordMark<Mark> = WrapMark<Mark, RecordHooks>;
+
+/// The strong pointer of [RecordMark].
+pub type Recorded<Mark, T> = Wrap<Mark, RecordHooks, T>;
+
+/// The weak pointer of [RecordMark].
+pub type RecordedWeak<Mark, T> = WrapWeak<Mark, RecordHooks, T>;
+
+/// A single recorded operation, with the id of the allocation it applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    New(u32),
+    /// Start of a `new_cyclic` construction: the allocation has a weak pointer
+    /// but no strong one, until the matching [Op::New].
+    Cyclic(u32),
+    Clone(u32),
+    Drop(u32),
+    Downgrade(u32),
+    CloneWeak(u32),
+    DropWeak(u32),
+    /// An upgrade attempt, and whether it succeeded.
+    Upgrade(u32, bool),
+}
+
+impl Op {
+    fn id(self) -> u32 {
+        match self {
+            Op::New(id)
+            | Op::Cyclic(id)
+            | Op::Clone(id)
+            | Op::Drop(id)
+            | Op::Downgrade(id)
+            | Op::CloneWeak(id)
+            | Op::DropWeak(id)
+            | Op::Upgrade(id, _) => id,
+        }
+    }
+}
+
+struct Recorder {
+    ids: HashMap<usize, u32>,
+    next_id: u32,
+    ops: Vec<Op>,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Starts a new recording, discarding any running one.
+pub fn start_recording() {
+    *RECORDER.lock().unwrap() = Some(Recorder {
+        ids: HashMap::new(),
+        next_id: 0,
+        ops: Vec::new(),
+    });
+}
+
+/// Stops the running recording and returns it.
+pub fn stop_recording() -> Recording {
+    let recorder = RECORDER.lock().unwrap().take();
+    Recording {
+        ops: recorder.map(|r| r.ops).unwrap_or_default(),
+    }
+}
+
+/// Registers a new allocation. A reused address gets a fresh id.
+fn record_new(addr: *const (), op: fn(u32) -> Op) {
+    if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+        let id = recorder.next_id;
+        recorder.next_id += 1;
+        recorder.ids.insert(addr as usize, id);
+        recorder.ops.push(op(id));
+    }
+}
+
+fn record(addr: *const (), op: impl FnOnce(u32) -> Op) {
+    if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+        if let Some(&id) = recorder.ids.get(&(addr as usize)) {
+            recorder.ops.push(op(id));
+        }
+    }
+}
+
+/// The [WrapHooks] of [RecordMark], appending to the running recording.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordHooks;
+
+impl WrapHooks for RecordHooks {
+    fn on_new(addr: *const (), _counts: RefCounts) {
+        record_new(addr, Op::New)
+    }
+    fn on_cyclic_start(addr: *const (), _counts: RefCounts) {
+        record_new(addr, Op::Cyclic)
+    }
+    fn on_cyclic_end(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::New)
+    }
+    fn on_clone(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::Clone)
+    }
+    fn on_drop(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::Drop)
+    }
+    fn on_downgrade(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::Downgrade)
+    }
+    fn on_clone_weak(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::CloneWeak)
+    }
+    fn on_drop_weak(addr: *const (), _counts: RefCounts) {
+        record(addr, Op::DropWeak)
+    }
+    fn on_upgrade(addr: *const (), upgraded: bool, _counts: RefCounts) {
+        record(addr, |id| Op::Upgrade(id, upgraded))
+    }
+}
+
+/// A log of pointer operations, as returned by [stop_recording].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    ops: Vec<Op>,
+}
+
+/// The operation at `index` could not be replayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayError {
+    pub index: usize,
+    pub op: Op,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot replay operation {}: {:?}", self.index, self.op)
+    }
+}
+
+impl Error for ReplayError {}
+
+impl Recording {
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Checks the log against a model keeping strong and weak counts per allocation.
+    pub fn replay_model(&self) -> Result<(), ReplayError> {
+        #[derive(Default)]
+        struct Counts {
+            strong: usize,
+            weak: usize,
+        }
+        let mut allocations: Vec<Counts> = Vec::new();
+        for (index, &op) in self.ops.iter().enumerate() {
+            let id = op.id() as usize;
+            if id == allocations.len() && matches!(op, Op::New(_) | Op::Cyclic(_)) {
+                allocations.push(Counts::default());
+            }
+            let ok = match allocations.get_mut(id) {
+                None => false,
+                Some(counts) => match op {
+                    Op::Cyclic(_) => {
+                        counts.weak += 1;
+                        true
+                    }
+                    Op::New(_) if counts.strong == 0 => {
+                        // Ends a cyclic construction, if any.
+                        counts.weak = counts.weak.saturating_sub(1);
+                        counts.strong = 1;
+                        true
+                    }
+                    Op::Clone(_) if counts.strong > 0 => {
+                        counts.strong += 1;
+                        true
+                    }
+                    Op::Downgrade(_) if counts.strong > 0 => {
+                        counts.weak += 1;
+                        true
+                    }
+                    Op::CloneWeak(_) if counts.weak > 0 => {
+                        counts.weak += 1;
+                        true
+                    }
+                    Op::Drop(_) if counts.strong > 0 => {
+                        counts.strong -= 1;
+                        true
+                    }
+                    Op::DropWeak(_) if counts.weak > 0 => {
+                        counts.weak -= 1;
+                        true
+                    }
+                    Op::Upgrade(_, upgraded) if upgraded == (counts.strong > 0) => {
+                        counts.strong += upgraded as usize;
+                        true
+                    }
+                    _ => false,
+                },
+            };
+            if !ok {
+                return Err(ReplayError { index, op });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays the log with `Mark` pointers to unit values, checking that each
+    /// upgrade has the recorded outcome.
+    pub fn replay<Mark: RefCountFamily>(&self) -> Result<(), ReplayError> {
+        let mut replayer = Replayer::<Mark> {
+            ops: &self.ops,
+            next: 0,
+            strong: Vec::new(),
+            weak: Vec::new(),
+        };
+        replayer.run(None)
+    }
+}
+
+struct Replayer<'a, Mark: RefCountFamily> {
+    ops: &'a [Op],
+    next: usize,
+    strong: Vec<Vec<Mark::Pointer<()>>>,
+    weak: Vec<Vec<Mark::WeakPointer<()>>>,
+}
+
+impl<Mark: RefCountFamily> Replayer<'_, Mark> {
+    /// Runs the operations, until the end of the log or until the allocation
+    /// `constructing` gets its first strong pointer.
+    fn run(&mut self, constructing: Option<usize>) -> Result<(), ReplayError> {
+        while let Some(&op) = self.ops.get(self.next) {
+            let index = self.next;
+            self.next += 1;
+            let id = op.id() as usize;
+            let error = ReplayError { index, op };
+            match op {
+                Op::New(_) if constructing == Some(id) => return Ok(()),
+                Op::New(_) if id == self.strong.len() => {
+                    self.strong.push(vec![Mark::new(())]);
+                    self.weak.push(Vec::new());
+                }
+                Op::Cyclic(_) if id == self.strong.len() => {
+                    self.strong.push(Vec::new());
+                    self.weak.push(Vec::new());
+                    let mut result = Ok(());
+                    let pointer = Mark::Pointer::new_cyclic(|weak| {
+                        self.weak[id].push(weak.clone());
+                        result = self.run(Some(id));
+                        if !self.weak[id].is_empty() {
+                            self.weak[id].remove(0);
+                        }
+                    });
+                    result?;
+                    self.strong[id].push(pointer);
+                }
+                Op::New(_) | Op::Cyclic(_) => return Err(error),
+                Op::Clone(_) | Op::Drop(_) | Op::Downgrade(_) => {
+                    let strong = self.strong.get_mut(id).ok_or(error)?;
+                    let pointer = strong.pop().ok_or(error)?;
+                    match op {
+                        Op::Clone(_) => strong.extend([pointer.clone(), pointer]),
+                        Op::Downgrade(_) => {
+                            self.weak[id].push(Mark::Pointer::downgrade(&pointer));
+                            strong.push(pointer);
+                        }
+                        _ => drop(pointer),
+                    }
+                }
+                Op::CloneWeak(_) | Op::DropWeak(_) | Op::Upgrade(..) => {
+                    let weak = self.weak.get_mut(id).ok_or(error)?;
+                    let pointer = weak.last().ok_or(error)?.clone();
+                    match op {
+                        Op::CloneWeak(_) => weak.push(pointer),
+                        Op::DropWeak(_) => drop(weak.pop()),
+                        _ => {
+                            let upgraded = pointer.upgrade();
+                            if upgraded.is_some() != matches!(op, Op::Upgrade(_, true)) {
+                                return Err(error);
+                            }
+                            self.strong[id].extend(upgraded);
+                        }
+                    }
+                }
+            }
+        }
+        match constructing {
+            None => Ok(()),
+            Some(id) => Err(ReplayError {
+                index: self.ops.len(),
+                op: Op::New(id as u32),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_record_replay() {
+        fn actual_test<Mark: RefCountFamily>() {
+            struct Node<Mark: RefCountFamily> {
+                me: <RecordMark<Mark> as RefCountFamily>::WeakPointer<Self>,
+            }
+
+            start_recording();
+            let a = RecordMark::<Mark>::new(1);
+            let b = a.clone();
+            let weak = Recorded::downgrade(&b);
+            assert_eq!(RefCounted::release(a), 1);
+            let c = weak.upgrade().unwrap();
+            drop((b, c));
+            assert!(weak.upgrade().is_none());
+            let node = Recorded::<Mark, Node<Mark>>::new_cyclic(|me| Node { me: me.clone() });
+            assert!(node.me.upgrade().is_some());
+            drop(node);
+            let recording = stop_recording();
+
+            assert_eq!(
+                &recording.ops()[..4],
+                [Op::New(0), Op::Clone(0), Op::Downgrade(0), Op::Drop(0)]
+            );
+            assert!(recording.ops().contains(&Op::Cyclic(1)));
+            assert_eq!(recording.replay_model(), Ok(()));
+            assert_eq!(recording.replay::<RcMark>(), Ok(()));
+            assert_eq!(recording.replay::<ArcMark>(), Ok(()));
+
+            let mut ops = recording.ops().to_vec();
+            ops.insert(1, Op::Drop(0));
+            let broken = Recording { ops };
+            assert!(broken.replay_model().is_err());
+            assert!(broken.replay::<RcMark>().is_err());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,229 @@
+//! [rkyv](https://docs.rs/rkyv) archiving of family pointers, for use with
+//! `#[rkyv(with = cark_ref_counted::rkyv_pointer::AsShared<R>)]`, `R` being the family.
+//!
+//! Pointers are archived as an `ArchivedRc`, laid out exactly like an `Rc` would be,
+//! so the archive can be validated with `rkyv::access` and read without
+//! deserializing. Its [MarkFlavor] tells the families apart, so validation rejects
+//! archives sharing a value between pointers of different families.
+//! Sharing is preserved in both directions: pointers to the same allocation are
+//! archived once, and deserialize to clones of the same new pointer.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! use cark_ref_counted::rkyv_pointer::AsShared;
+//! use rkyv::{rancor::Error, Archive, Deserialize, Serialize};
+//! #[derive(Archive, Serialize, Deserialize)]
+//! struct Pair<R: RefCountFamily + 'static> {
+//!     #[rkyv(with = AsShared<R>)]
+//!     left: R::Pointer<str>,
+//!     #[rkyv(with = AsShared<R>)]
+//!     right: R::Pointer<str>,
+//! }
+//! let name = ArcMark::new_str("John Doe");
+//! let pair = Pair::<ArcMark> { left: name.clone(), right: name };
+//! let bytes = rkyv::to_bytes::<Error>(&pair).unwrap();
+//! let archived = rkyv::access::<ArchivedPair<ArcMark>, Error>(&bytes).unwrap();
+//! assert_eq!(archived.left.get(), "John Doe");
+//! let pair: Pair<ArcMark> = rkyv::deserialize::<_, Error>(archived).unwrap();
+//! assert!(std::sync::Arc::ptr_eq(&pair.left, &pair.right));
+//! ```
+
+use crate::traits::*;
+use rkyv::{
+    de::{FromMetadata, Metadata, Pooling, PoolingExt, SharedPointer},
+    ptr_meta::{self, Pointee},
+    rancor::{Fallible, Source},
+    rc::{ArchivedRc, Flavor, RcResolver},
+    ser::{Sharing, Writer},
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    ArchiveUnsized, DeserializeUnsized, Place, SerializeUnsized,
+};
+use std::{
+    alloc::{alloc, handle_alloc_error, LayoutError},
+    marker::PhantomData,
+    ops::Deref,
+    ptr,
+};
+
+/// Archives a pointer of `Mark` as an `ArchivedRc`.
+pub struct AsShared<Mark>(PhantomData<Mark>);
+
+/// The flavor of the `ArchivedRc`s of `Mark`.
+///
+/// Validation rejects archives where pointers of different flavors share a value,
+/// so a value archived for one family can't be deserialized as a pointer of
+/// another one, nor as a plain `Rc` or `Arc`.
+pub struct MarkFlavor<Mark>(PhantomData<Mark>);
+
+impl<Mark: 'static> Flavor for MarkFlavor<Mark> {
+    const ALLOW_CYCLES: bool = false;
+}
+
+impl<Mark, P> ArchiveWith<P> for AsShared<Mark>
+where
+    P: Deref,
+    P::Target: ArchiveUnsized,
+{
+    type Archived = ArchivedRc<<P::Target as ArchiveUnsized>::Archived, MarkFlavor<Mark>>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(field: &P, resolver: RcResolver, out: Place<Self::Archived>) {
+        ArchivedRc::resolve_from_ref(&**field, resolver, out);
+    }
+}
+
+impl<Mark, P, S> SerializeWith<P, S> for AsShared<Mark>
+where
+    P: Deref,
+    P::Target: SerializeUnsized<S> + 'static,
+    S: Fallible + Writer + Sharing + ?Sized,
+    S::Error: Source,
+{
+    fn serialize_with(field: &P, serializer: &mut S) -> Result<RcResolver, S::Error> {
+        ArchivedRc::<<P::Target as ArchiveUnsized>::Archived, MarkFlavor<Mark>>::serialize_from_ref(
+            &**field, serializer,
+        )
+    }
+}
+
+impl<Mark, P, D>
+    DeserializeWith<ArchivedRc<<P::Target as ArchiveUnsized>::Archived, MarkFlavor<Mark>>, P, D>
+    for AsShared<Mark>
+where
+    Mark: RefCountFamily<Pointer<P::Target> = P>,
+    P: Deref,
+    P::Target: ArchiveUnsized + LayoutRaw + Pointee + 'static,
+    <P::Target as Pointee>::Metadata: Into<Metadata> + FromMetadata,
+    <P::Target as ArchiveUnsized>::Archived: DeserializeUnsized<P::Target, D>,
+    D: Fallible + Pooling + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<<P::Target as ArchiveUnsized>::Archived, MarkFlavor<Mark>>,
+        deserializer: &mut D,
+    ) -> Result<P, D::Error> {
+        let raw = deserializer.deserialize_shared::<P::Target, Pooled<Mark>>(field.get())?;
+        // SAFETY: the pool keeps a strong pointer made by `Pooled::from_value`.
+        Ok(unsafe { Pooled::<Mark>::share(raw) })
+    }
+}
+
+/// Makes the pointers of `Mark` for the deserializer pool.
+struct Pooled<Mark>(PhantomData<Mark>);
+
+impl<Mark: RefCountFamily> Pooled<Mark> {
+    /// Returns a new strong pointer to a value of the pool.
+    ///
+    /// # Safety
+    /// `raw` must come from [SharedPointer::from_value], and still be in the pool.
+    unsafe fn share<T: ?Sized>(raw: *mut T) -> Mark::Pointer<T> {
+        Mark::Pointer::increment_strong_count(raw);
+        RefCounted::from_raw(raw)
+    }
+}
+
+unsafe impl<Mark: RefCountFamily, T: LayoutRaw + Pointee + ?Sized> SharedPointer<T>
+    for Pooled<Mark>
+{
+    fn alloc(metadata: T::Metadata) -> Result<*mut T, LayoutError> {
+        let layout = T::layout_raw(metadata)?;
+        let data_address = if layout.size() > 0 {
+            let ptr = unsafe { alloc(layout) };
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            ptr
+        } else {
+            ptr::without_provenance_mut(layout.align())
+        };
+        Ok(ptr_meta::from_raw_parts_mut(data_address.cast(), metadata))
+    }
+
+    unsafe fn from_value(ptr: *mut T) -> *mut T {
+        let pointer = Mark::from_box(Box::from_raw(ptr));
+        Mark::Pointer::into_raw(pointer).cast_mut()
+    }
+
+    unsafe fn drop(ptr: *mut T) {
+        drop(Mark::Pointer::<T>::from_raw(ptr));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use rkyv::{rancor::Error, Archive, Deserialize, Serialize};
+
+    #[derive(Archive, Serialize, Deserialize)]
+    struct Foo<R: RefCountFamily + 'static> {
+        #[rkyv(with = AsShared<R>)]
+        name: R::Pointer<str>,
+        #[rkyv(with = AsShared<R>)]
+        values: R::Pointer<[u32]>,
+        #[rkyv(with = AsShared<R>)]
+        first: R::Pointer<u32>,
+        #[rkyv(with = AsShared<R>)]
+        again: R::Pointer<u32>,
+    }
+
+    #[test]
+    fn test_rkyv() {
+        fn actual_test<R: RefCountFamily + 'static>() {
+            let first = R::new(7);
+            let foo = Foo::<R> {
+                name: R::new_str("a"),
+                values: R::new_slice(vec![1, 2]),
+                first: first.clone(),
+                again: first,
+            };
+            let bytes = rkyv::to_bytes::<Error>(&foo).unwrap();
+            let archived = rkyv::access::<ArchivedFoo<R>, Error>(&bytes).unwrap();
+            assert_eq!(archived.name.get(), "a");
+            assert_eq!(archived.values.get(), [1u32, 2]);
+            assert_eq!(*archived.first.get(), 7);
+            let foo: Foo<R> = rkyv::deserialize::<_, Error>(archived).unwrap();
+            assert_eq!(
+                (&*foo.name, &*foo.values, *foo.first),
+                ("a", &[1, 2][..], 7)
+            );
+            assert!(R::Pointer::ptr_eq(&foo.first, &foo.again));
+            assert_eq!(R::Pointer::strong_count(&foo.first), 2);
+            assert!(rkyv::access::<ArchivedFoo<R>, Error>(&bytes[1..]).is_err());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_rkyv_rejects_mixed_families() {
+        #[derive(Archive, Serialize)]
+        struct Twins<R: RefCountFamily + 'static> {
+            #[rkyv(with = AsShared<R>)]
+            left: R::Pointer<u32>,
+            #[rkyv(with = AsShared<R>)]
+            right: R::Pointer<u32>,
+        }
+
+        // Only its archived form is used.
+        #[allow(dead_code)]
+        #[derive(Archive, Serialize)]
+        struct Mixed {
+            #[rkyv(with = AsShared<RcMark>)]
+            left: std::rc::Rc<u32>,
+            #[rkyv(with = AsShared<ArcMark>)]
+            right: std::sync::Arc<u32>,
+        }
+
+        let value = RcMark::new(7);
+        let twins = Twins::<RcMark> {
+            left: value.clone(),
+            right: value,
+        };
+        // Same layout as ArchivedMixed, with both pointers to the same value.
+        let bytes = rkyv::to_bytes::<Error>(&twins).unwrap();
+        assert!(rkyv::access::<ArchivedTwins<RcMark>, Error>(&bytes).is_ok());
+        assert!(rkyv::access::<ArchivedMixed, Error>(&bytes).is_err());
+    }
+}
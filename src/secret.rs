@@ -0,0 +1,126 @@
+use crate::traits::*;
+#[cfg(feature = "secrecy")]
+use secrecy::{ExposeSecret, ExposeSecretMut, SecretBox};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A shared secret, zeroized when the last strong pointer to it is dropped.
+///
+/// The payload is stored as `Mark::Pointer<Zeroizing<T>>`, so clones are as cheap
+/// as cloning the underlying pointer, and the value is wiped right before the
+/// allocation releases it. The [fmt::Debug] implementation never prints the payload.
+///
+/// With the `secrecy` feature, it implements `secrecy::ExposeSecret` and can be
+/// built from a `secrecy::SecretBox`.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let password = SecretShared::<ArcMark, String>::new("hunter2".to_owned());
+/// let other = password.clone();
+/// drop(password);
+/// assert_eq!(other.expose(), "hunter2");
+/// assert_eq!(format!("{other:?}"), "SecretShared([REDACTED])");
+/// ```
+pub struct SecretShared<Mark: RefCountFamily, T: Zeroize>(Mark::Pointer<Zeroizing<T>>);
+
+impl<Mark: RefCountFamily, T: Zeroize> SecretShared<Mark, T> {
+    pub fn new(value: T) -> Self {
+        Self(Mark::new(Zeroizing::new(value)))
+    }
+
+    /// Gives access to the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        Mark::Pointer::strong_count(&this.0)
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<Mark: RefCountFamily, T: Zeroize> Clone for SecretShared<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: Zeroize> From<T> for SecretShared<Mark, T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(feature = "secrecy")]
+impl<Mark: RefCountFamily, T: Zeroize> ExposeSecret<T> for SecretShared<Mark, T> {
+    fn expose_secret(&self) -> &T {
+        self.expose()
+    }
+}
+
+/// The value is moved out of the box, leaving a default value behind
+/// to be zeroized when the box is dropped.
+#[cfg(feature = "secrecy")]
+impl<Mark: RefCountFamily, T: Zeroize + Default> From<SecretBox<T>> for SecretShared<Mark, T> {
+    fn from(mut secret: SecretBox<T>) -> Self {
+        Self::new(std::mem::take(secret.expose_secret_mut()))
+    }
+}
+
+impl<Mark: RefCountFamily, T: Zeroize> fmt::Debug for SecretShared<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretShared([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Key([u8; 4]);
+
+    impl Zeroize for Key {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+            ZEROIZED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_zeroize_on_last_drop() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let before = ZEROIZED.load(Ordering::SeqCst);
+            let secret = SecretShared::<Mark, _>::new(Key([1, 2, 3, 4]));
+            let other = secret.clone();
+            assert!(SecretShared::ptr_eq(&secret, &other));
+            assert_eq!(SecretShared::strong_count(&secret), 2);
+            drop(secret);
+            assert_eq!(ZEROIZED.load(Ordering::SeqCst), before);
+            assert_eq!(other.expose().0, [1, 2, 3, 4]);
+            drop(other);
+            assert_eq!(ZEROIZED.load(Ordering::SeqCst), before + 1);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[cfg(feature = "secrecy")]
+    #[test]
+    fn test_from_secret_box() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let boxed = SecretBox::new(Box::new(String::from("hunter2")));
+            let secret = SecretShared::<Mark, String>::from(boxed);
+            assert_eq!(secret.expose_secret(), "hunter2");
+            assert_eq!(format!("{:?}", secret), "SecretShared([REDACTED])");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,258 @@
+//! Serialization of family pointers preserving sharing, for use with
+//! `#[serde(with = "cark_ref_counted::serde_dedup")]`.
+//!
+//! Unlike [crate::serde_pointer], each allocation is written once. The first
+//! occurrence of a pointer is written as `[id, value]`, the following ones as `[id]`,
+//! and deserialization hands out clones of the same pointer for each id. DAG shaped
+//! documents keep their sharing after a round trip.
+//!
+//! Ids are tracked by a session, started by serializing through [Dedup] and by
+//! deserializing through [DedupSeed]. Pointer fields met outside of a session fail.
+//! The pointers are kept type-erased during deserialization, so the family and the
+//! pointed type must be `'static`.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! # use cark_ref_counted::serde_dedup::{Dedup, DedupSeed};
+//! # use ::serde::{de::DeserializeSeed, Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize)]
+//! #[serde(bound = "")]
+//! struct Pair<R: RefCountFamily + 'static> {
+//!     #[serde(with = "cark_ref_counted::serde_dedup")]
+//!     left: R::Pointer<str>,
+//!     #[serde(with = "cark_ref_counted::serde_dedup")]
+//!     right: R::Pointer<str>,
+//! }
+//! let name = RcMark::new_str("John Doe");
+//! let pair = Pair::<RcMark> { left: name.clone(), right: name };
+//! let json = serde_json::to_string(&Dedup(&pair)).unwrap();
+//! assert_eq!(json, r#"{"left":[0,"John Doe"],"right":[0]}"#);
+//! let mut deserializer = serde_json::Deserializer::from_str(&json);
+//! let pair: Pair<RcMark> = DedupSeed::new().deserialize(&mut deserializer).unwrap();
+//! assert!(std::rc::Rc::ptr_eq(&pair.left, &pair.right));
+//! ```
+
+use crate::{serde_pointer::DeserializeShared, traits::*};
+use ::serde::{
+    de::{self, DeserializeSeed, SeqAccess, Visitor},
+    ser::{self, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    marker::PhantomData,
+    thread::LocalKey,
+};
+
+thread_local! {
+    static SERIALIZING: RefCell<Option<HashMap<*const (), u64>>> = const { RefCell::new(None) };
+    static DESERIALIZING: RefCell<Option<HashMap<u64, Box<dyn Any>>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous session of a thread local when dropped.
+pub(crate) struct Session<S: 'static> {
+    key: &'static LocalKey<RefCell<Option<S>>>,
+    previous: Option<S>,
+}
+
+impl<S: 'static> Session<S> {
+    pub(crate) fn start(key: &'static LocalKey<RefCell<Option<S>>>, session: S) -> Self {
+        let previous = key.with(|current| current.replace(Some(session)));
+        Self { key, previous }
+    }
+}
+
+impl<S: 'static> Drop for Session<S> {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        self.key.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Serializes the wrapped value in a new session.
+pub struct Dedup<'a, T: ?Sized>(pub &'a T);
+
+impl<T: ?Sized + Serialize> Serialize for Dedup<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _session = Session::start(&SERIALIZING, HashMap::new());
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserializes a `T` in a new session.
+pub struct DedupSeed<T>(PhantomData<fn() -> T>);
+
+impl<T> DedupSeed<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for DedupSeed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for DedupSeed<T> {
+    type Value = T;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        let _session = Session::start(&DESERIALIZING, HashMap::new());
+        T::deserialize(deserializer)
+    }
+}
+
+/// Serializes `pointer` as `[id, value]` the first time it is met in the session,
+/// and as `[id]` afterwards.
+pub fn serialize<P, T, S>(pointer: &P, serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: RefCounted<T>,
+    T: ?Sized + Serialize,
+    S: Serializer,
+{
+    let addr = P::as_ptr(pointer) as *const ();
+    let seen = SERIALIZING.with(|ids| {
+        let mut ids = ids.borrow_mut();
+        let ids = ids.as_mut()?;
+        let next = ids.len() as u64;
+        Some(match ids.entry(addr) {
+            Entry::Occupied(entry) => (*entry.get(), true),
+            Entry::Vacant(entry) => (*entry.insert(next), false),
+        })
+    });
+    let Some((id, seen)) = seen else {
+        return Err(ser::Error::custom(
+            "shared pointer serialized outside of Dedup",
+        ));
+    };
+    let mut seq = serializer.serialize_seq(Some(if seen { 1 } else { 2 }))?;
+    seq.serialize_element(&id)?;
+    if !seen {
+        seq.serialize_element(&**pointer)?;
+    }
+    seq.end()
+}
+
+/// Deserializes a pointer written by [serialize], reusing the pointer of its id
+/// when it was already met in the session.
+pub fn deserialize<'de, P, T, D>(deserializer: D) -> Result<P, D::Error>
+where
+    P: RefCounted<T>,
+    P::Mark: 'static,
+    T: ?Sized + DeserializeShared<'de> + 'static,
+    D: Deserializer<'de>,
+{
+    let pointer = deserializer.deserialize_seq(NodeVisitor::<P::Mark, T>(PhantomData))?;
+    // SAFETY: `P` is the pointer of its family for `T`.
+    Ok(unsafe { P::from_raw(RefCounted::into_raw(pointer)) })
+}
+
+struct NodeVisitor<Mark: RefCountFamily, T: ?Sized>(PhantomData<Mark::Pointer<T>>);
+
+impl<'de, Mark, T> Visitor<'de> for NodeVisitor<Mark, T>
+where
+    Mark: RefCountFamily + 'static,
+    T: ?Sized + DeserializeShared<'de> + 'static,
+{
+    type Value = Mark::Pointer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a shared pointer as [id, value] or [id]")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let id: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let defined = seq.next_element_seed(PointerSeed::<Mark, T>(PhantomData))?;
+        DESERIALIZING.with(|pointers| {
+            let mut pointers = pointers.borrow_mut();
+            let Some(pointers) = pointers.as_mut() else {
+                return Err(de::Error::custom(
+                    "shared pointer deserialized outside of DedupSeed",
+                ));
+            };
+            match defined {
+                Some(pointer) => {
+                    pointers.insert(id, Box::new(pointer.clone()));
+                    Ok(pointer)
+                }
+                None => match pointers.get(&id) {
+                    None => Err(de::Error::custom(format_args!("unknown shared id {id}"))),
+                    Some(pointer) => pointer
+                        .downcast_ref::<Mark::Pointer<T>>()
+                        .cloned()
+                        .ok_or_else(|| {
+                            de::Error::custom(format_args!("shared id {id} has another type"))
+                        }),
+                },
+            }
+        })
+    }
+}
+
+struct PointerSeed<Mark: RefCountFamily, T: ?Sized>(PhantomData<Mark::Pointer<T>>);
+
+impl<'de, Mark: RefCountFamily, T: ?Sized + DeserializeShared<'de>> DeserializeSeed<'de>
+    for PointerSeed<Mark, T>
+{
+    type Value = Mark::Pointer<T>;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        T::deserialize_in::<Mark, D>(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(bound = "")]
+    struct Node<R: RefCountFamily + 'static> {
+        value: i32,
+        #[serde(with = "crate::serde_dedup")]
+        children: R::Pointer<[Child<R>]>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(bound = "")]
+    struct Child<R: RefCountFamily + 'static>(
+        #[serde(with = "crate::serde_dedup")] R::Pointer<Node<R>>,
+    );
+
+    #[test]
+    fn test_serde_dedup() {
+        fn actual_test<R: RefCountFamily + 'static>() {
+            let leaf = R::new(Node::<R> {
+                value: 1,
+                children: R::new_slice(vec![]),
+            });
+            let root = Node::<R> {
+                value: 0,
+                children: R::new_slice(vec![Child(leaf.clone()), Child(leaf)]),
+            };
+            let json = serde_json::to_string(&Dedup(&root)).unwrap();
+            assert_eq!(
+                json,
+                r#"{"value":0,"children":[0,[[1,{"value":1,"children":[2,[]]}],[1]]]}"#
+            );
+            let mut deserializer = serde_json::Deserializer::from_str(&json);
+            let root: Node<R> = DedupSeed::new().deserialize(&mut deserializer).unwrap();
+            let [a, b] = &*root.children else { panic!() };
+            assert!(R::Pointer::ptr_eq(&a.0, &b.0));
+            assert_eq!((a.0.value, R::Pointer::strong_count(&a.0)), (1, 2));
+
+            assert!(serde_json::to_string(&root).is_err());
+            let unknown = r#"{"value":0,"children":[0,[[5]]]}"#;
+            let mut deserializer = serde_json::Deserializer::from_str(unknown);
+            let error = DedupSeed::<Node<R>>::new().deserialize(&mut deserializer);
+            assert!(error.is_err());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
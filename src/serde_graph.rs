@@ -0,0 +1,393 @@
+//! Serialization of pointer graphs with weak back edges, for use with
+//! `#[serde(with = "cark_ref_counted::serde_graph")]` on strong pointers and
+//! `#[serde(with = "cark_ref_counted::serde_graph::weak")]` on weak pointers.
+//!
+//! Structures built with [RefCounted::new_cyclic], like trees with strong children
+//! and weak parents, can't go through [crate::serde_dedup]: a weak pointer has no
+//! value of its own. Here, every node gets an id. Strong edges are written like
+//! [crate::serde_dedup] does, `[id, value]` the first time and `[id]` afterwards,
+//! and weak edges are written as the id of the node they point to, or `null` when
+//! it is gone.
+//!
+//! Nodes are rebuilt with [RefCounted::new_cyclic], and a weak pointer to a node
+//! is available as soon as its definition starts. A weak edge must therefore point
+//! to a node whose definition comes first in the document, or encloses the edge, as
+//! parent pointers and back edges do. Weak edges to nodes defined later fail to
+//! deserialize.
+//!
+//! When the value of a node fails to deserialize, its allocation is filled with
+//! the [Default] value of the node type and dropped, and the error is returned.
+//! Node types must therefore implement [Default].
+//!
+//! Ids are tracked by a session, started by serializing through [Graph] and by
+//! deserializing through [GraphSeed]. The family and the pointed types must be
+//! `'static`.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! # use cark_ref_counted::serde_graph::{Graph, GraphSeed};
+//! # use ::serde::{de::DeserializeSeed, Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize)]
+//! #[serde(bound = "")]
+//! struct Node<R: RefCountFamily + 'static> {
+//!     name: String,
+//!     #[serde(with = "cark_ref_counted::serde_graph::weak")]
+//!     parent: R::WeakPointer<Node<R>>,
+//!     children: Vec<Edge<R>>,
+//! }
+//! impl<R: RefCountFamily> Default for Node<R> {
+//!     fn default() -> Self {
+//!         Node { name: String::new(), parent: R::WeakPointer::new(), children: vec![] }
+//!     }
+//! }
+//! #[derive(Serialize, Deserialize)]
+//! #[serde(bound = "")]
+//! struct Edge<R: RefCountFamily + 'static>(
+//!     #[serde(with = "cark_ref_counted::serde_graph")] R::Pointer<Node<R>>,
+//! );
+//! let root = std::rc::Rc::new_cyclic(|root| Node::<RcMark> {
+//!     name: "root".to_owned(),
+//!     parent: std::rc::Weak::new(),
+//!     children: vec![Edge(RcMark::new(Node {
+//!         name: "leaf".to_owned(),
+//!         parent: root.clone(),
+//!         children: vec![],
+//!     }))],
+//! });
+//! let json = serde_json::to_string(&Graph(&Edge::<RcMark>(root))).unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"[0,{"name":"root","parent":null,"children":[[1,{"name":"leaf","parent":0,"children":[]}]]}]"#
+//! );
+//! let mut deserializer = serde_json::Deserializer::from_str(&json);
+//! let Edge(root): Edge<RcMark> = GraphSeed::new().deserialize(&mut deserializer).unwrap();
+//! let leaf = &root.children[0].0;
+//! assert!(std::rc::Rc::ptr_eq(&leaf.parent.upgrade().unwrap(), &root));
+//! ```
+
+use crate::{serde_dedup::Session, traits::*};
+use ::serde::{
+    de::{self, DeserializeSeed, SeqAccess, Visitor},
+    ser::{self, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{any::Any, cell::RefCell, collections::HashMap, fmt, marker::PhantomData};
+
+thread_local! {
+    static SERIALIZING: RefCell<Option<Ids>> = const { RefCell::new(None) };
+    static DESERIALIZING: RefCell<Option<Nodes>> = const { RefCell::new(None) };
+}
+
+/// The ids of the nodes met while serializing, and whether their value was written.
+#[derive(Default)]
+struct Ids(HashMap<*const (), (u64, bool)>);
+
+impl Ids {
+    /// Returns the id of the node at `addr`, assigning a new one if needed, and
+    /// whether its value was already written. Marks it written if `write` is set.
+    fn get(&mut self, addr: *const (), write: bool) -> (u64, bool) {
+        let next = self.0.len() as u64;
+        let (id, written) = self.0.entry(addr).or_insert((next, false));
+        let was_written = *written;
+        *written |= write;
+        (*id, was_written)
+    }
+}
+
+/// The nodes met while deserializing, as type-erased pointers. A node has a weak
+/// pointer as soon as its definition starts, and a strong one once it is done.
+#[derive(Default)]
+struct Nodes {
+    strong: HashMap<u64, Box<dyn Any>>,
+    weak: HashMap<u64, Box<dyn Any>>,
+}
+
+/// Serializes the wrapped value in a new session.
+pub struct Graph<'a, T: ?Sized>(pub &'a T);
+
+impl<T: ?Sized + Serialize> Serialize for Graph<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _session = Session::start(&SERIALIZING, Ids::default());
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserializes a `T` in a new session.
+pub struct GraphSeed<T>(PhantomData<fn() -> T>);
+
+impl<T> GraphSeed<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for GraphSeed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for GraphSeed<T> {
+    type Value = T;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        let _session = Session::start(&DESERIALIZING, Nodes::default());
+        T::deserialize(deserializer)
+    }
+}
+
+fn node_id<E: ser::Error>(addr: *const (), write: bool) -> Result<(u64, bool), E> {
+    SERIALIZING
+        .with(|ids| Some(ids.borrow_mut().as_mut()?.get(addr, write)))
+        .ok_or_else(|| E::custom("graph pointer serialized outside of Graph"))
+}
+
+/// Serializes a strong edge as `[id, value]` the first time its node is written in
+/// the session, and as `[id]` afterwards.
+pub fn serialize<P, T, S>(pointer: &P, serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: RefCounted<T>,
+    T: Serialize,
+    S: Serializer,
+{
+    let (id, written) = node_id(P::as_ptr(pointer) as *const (), true)?;
+    let mut seq = serializer.serialize_seq(Some(if written { 1 } else { 2 }))?;
+    seq.serialize_element(&id)?;
+    if !written {
+        seq.serialize_element(&**pointer)?;
+    }
+    seq.end()
+}
+
+/// Deserializes a strong edge written by [serialize], building its node with
+/// [RefCounted::new_cyclic] the first time it is met in the session.
+pub fn deserialize<'de, P, T, D>(deserializer: D) -> Result<P, D::Error>
+where
+    P: RefCounted<T>,
+    P::Mark: 'static,
+    T: Deserialize<'de> + Default + 'static,
+    D: Deserializer<'de>,
+{
+    let pointer = deserializer.deserialize_seq(NodeVisitor::<P::Mark, T>(PhantomData))?;
+    // SAFETY: `P` is the pointer of its family for `T`.
+    Ok(unsafe { P::from_raw(RefCounted::into_raw(pointer)) })
+}
+
+fn with_nodes<R, E: de::Error>(f: impl FnOnce(&mut Nodes) -> Result<R, E>) -> Result<R, E> {
+    DESERIALIZING.with(|nodes| match nodes.borrow_mut().as_mut() {
+        Some(nodes) => f(nodes),
+        None => Err(E::custom("graph pointer deserialized outside of GraphSeed")),
+    })
+}
+
+struct NodeVisitor<Mark: RefCountFamily, T>(PhantomData<Mark::Pointer<T>>);
+
+impl<'de, Mark, T> Visitor<'de> for NodeVisitor<Mark, T>
+where
+    Mark: RefCountFamily + 'static,
+    T: Deserialize<'de> + Default + 'static,
+{
+    type Value = Mark::Pointer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a graph node as [id, value] or [id]")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let id: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let defined = seq.next_element_seed(NodeSeed::<Mark, T>(id, PhantomData))?;
+        with_nodes(|nodes| match defined {
+            Some(pointer) => {
+                nodes.strong.insert(id, Box::new(pointer.clone()));
+                Ok(pointer)
+            }
+            None => match nodes.strong.get(&id) {
+                None if nodes.weak.contains_key(&id) => Err(de::Error::custom(format_args!(
+                    "strong cycle through graph node {id}"
+                ))),
+                None => Err(de::Error::custom(format_args!("unknown graph node {id}"))),
+                Some(pointer) => pointer
+                    .downcast_ref::<Mark::Pointer<T>>()
+                    .cloned()
+                    .ok_or_else(|| {
+                        de::Error::custom(format_args!("graph node {id} has another type"))
+                    }),
+            },
+        })
+    }
+}
+
+struct NodeSeed<Mark: RefCountFamily, T>(u64, PhantomData<Mark::Pointer<T>>);
+
+impl<'de, Mark, T> DeserializeSeed<'de> for NodeSeed<Mark, T>
+where
+    Mark: RefCountFamily + 'static,
+    T: Deserialize<'de> + Default + 'static,
+{
+    type Value = Mark::Pointer<T>;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let id = self.0;
+        let mut error = None;
+        let pointer = Mark::Pointer::<T>::new_cyclic(|weak| {
+            with_nodes(|nodes| {
+                nodes.weak.insert(id, Box::new(weak.clone()));
+                Ok(())
+            })
+            .and_then(|()| T::deserialize(deserializer))
+            .unwrap_or_else(|e| {
+                error = Some(e);
+                T::default()
+            })
+        });
+        match error {
+            None => Ok(pointer),
+            Some(e) => {
+                // The placeholder is dropped with its only strong pointer, and the
+                // node is forgotten.
+                drop(pointer);
+                with_nodes(|nodes| {
+                    nodes.weak.remove(&id);
+                    Ok(())
+                })?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Serialization of weak edges, for use with
+/// `#[serde(with = "cark_ref_counted::serde_graph::weak")]`.
+pub mod weak {
+    use super::*;
+
+    /// Serializes a weak edge as the id of its node, or `null` when it is gone.
+    pub fn serialize<W, T, S>(weak: &W, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        W: WeakPointer<T>,
+        S: Serializer,
+    {
+        match weak.upgrade() {
+            None => serializer.serialize_none(),
+            Some(strong) => {
+                let (id, _) = node_id(RefCounted::as_ptr(&strong) as *const (), false)?;
+                serializer.serialize_some(&id)
+            }
+        }
+    }
+
+    /// Deserializes a weak edge written by [serialize]. Its node must already be
+    /// defined, or being defined.
+    pub fn deserialize<'de, W, T, D>(deserializer: D) -> Result<W, D::Error>
+    where
+        W: WeakPointer<T> + 'static,
+        D: Deserializer<'de>,
+    {
+        let Some(id) = Option::<u64>::deserialize(deserializer)? else {
+            return Ok(W::new());
+        };
+        with_nodes(|nodes| match nodes.weak.get(&id) {
+            None => Err(de::Error::custom(format_args!(
+                "weak edge to graph node {id} before its definition"
+            ))),
+            Some(weak) => weak
+                .downcast_ref::<W>()
+                .cloned()
+                .ok_or_else(|| de::Error::custom(format_args!("graph node {id} has another type"))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(bound = "")]
+    struct Node<R: RefCountFamily + 'static> {
+        value: i32,
+        #[serde(with = "crate::serde_graph::weak")]
+        parent: R::WeakPointer<Node<R>>,
+        children: Vec<Child<R>>,
+    }
+
+    impl<R: RefCountFamily> Default for Node<R> {
+        fn default() -> Self {
+            Node {
+                value: 0,
+                parent: R::WeakPointer::new(),
+                children: vec![],
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(bound = "")]
+    struct Child<R: RefCountFamily + 'static>(
+        #[serde(with = "crate::serde_graph")] R::Pointer<Node<R>>,
+    );
+
+    #[test]
+    fn test_serde_graph() {
+        fn actual_test<R: RefCountFamily + 'static>() {
+            let root = R::Pointer::<Node<R>>::new_cyclic(|root| {
+                let leaf = R::new(Node::<R> {
+                    value: 1,
+                    parent: root.clone(),
+                    children: vec![],
+                });
+                Node {
+                    value: 0,
+                    parent: R::WeakPointer::new(),
+                    children: vec![Child(leaf.clone()), Child(leaf)],
+                }
+            });
+            let json = serde_json::to_string(&Graph(&Child::<R>(root))).unwrap();
+            assert_eq!(
+                json,
+                r#"[0,{"value":0,"parent":null,"children":[[1,{"value":1,"parent":0,"children":[]}],[1]]}]"#
+            );
+            let mut deserializer = serde_json::Deserializer::from_str(&json);
+            let Child(root): Child<R> = GraphSeed::new().deserialize(&mut deserializer).unwrap();
+            let [a, b] = &root.children[..] else { panic!() };
+            assert!(R::Pointer::ptr_eq(&a.0, &b.0));
+            assert_eq!((a.0.value, R::Pointer::strong_count(&a.0)), (1, 2));
+            assert!(R::Pointer::ptr_eq(&a.0.parent.upgrade().unwrap(), &root));
+            assert!(root.parent.upgrade().is_none());
+            assert_eq!(R::Pointer::strong_count(&root), 1);
+
+            assert!(serde_json::to_string(&Child::<R>(root)).is_err());
+            let forward = r#"[0,{"value":0,"parent":1,"children":[]}]"#;
+            let mut deserializer = serde_json::Deserializer::from_str(forward);
+            let error = GraphSeed::<Child<R>>::new().deserialize(&mut deserializer);
+            assert!(error.is_err());
+            let cycle = r#"[0,{"value":0,"parent":null,"children":[[0]]}]"#;
+            let mut deserializer = serde_json::Deserializer::from_str(cycle);
+            let error = GraphSeed::<Child<R>>::new().deserialize(&mut deserializer);
+            assert!(error.is_err());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_serde_graph_invalid() {
+        fn actual_test<R: RefCountFamily + 'static>() {
+            let invalid = [
+                r#"[0,{"value":0,"parent":null,"children":[[1,{"value":1,"parent":0"#,
+                r#"[0,{"value":0,"parent":null,"children":[[1,{"value":"one"}]]}]"#,
+                r#"[0,{"value":0,"parent":null,"children":[[1,{"value":1,"parent":2,"children":[]}]]}]"#,
+                r#"[0,{"value":0,"parent":null,"children":[[1]]}]"#,
+                r#"[0]"#,
+            ];
+            for json in invalid {
+                let mut deserializer = serde_json::Deserializer::from_str(json);
+                let result = GraphSeed::<Child<R>>::new().deserialize(&mut deserializer);
+                assert!(result.is_err(), "{json}");
+            }
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
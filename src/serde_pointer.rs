@@ -0,0 +1,151 @@
+//! Serialization of family pointers, for use with
+//! `#[serde(with = "cark_ref_counted::serde")]`, which re-exports this module.
+//!
+//! A pointer is serialized as the value it points to, and deserialized into a new
+//! allocation. Sharing is not preserved: two pointers to the same value come back
+//! as two allocations.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! # use ::serde::{Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize)]
+//! struct Person<R: RefCountFamily> {
+//!     #[serde(with = "cark_ref_counted::serde")]
+//!     name: R::Pointer<str>,
+//!     #[serde(with = "cark_ref_counted::serde")]
+//!     scores: R::Pointer<Vec<u32>>,
+//! }
+//! let person = Person::<RcMark> {
+//!     name: RcMark::new_str("John Doe"),
+//!     scores: RcMark::new(vec![1, 2]),
+//! };
+//! let json = serde_json::to_string(&person).unwrap();
+//! assert_eq!(json, r#"{"name":"John Doe","scores":[1,2]}"#);
+//! let person: Person<ArcMark> = serde_json::from_str(&json).unwrap();
+//! assert_eq!(&*person.name, "John Doe");
+//! ```
+
+use crate::{traits::*, Shared, SharedStr};
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Deref;
+
+/// Values that can be deserialized into a new family pointer.
+///
+/// Implemented for all sized `Deserialize` types, `str` and slices.
+pub trait DeserializeShared<'de> {
+    fn deserialize_in<Mark: RefCountFamily, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Mark::Pointer<Self>, D::Error>;
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeShared<'de> for T {
+    fn deserialize_in<Mark: RefCountFamily, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Mark::Pointer<T>, D::Error> {
+        T::deserialize(deserializer).map(Mark::new)
+    }
+}
+
+impl<'de> DeserializeShared<'de> for str {
+    fn deserialize_in<Mark: RefCountFamily, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Mark::Pointer<str>, D::Error> {
+        String::deserialize(deserializer).map(Mark::from_string)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeShared<'de> for [T] {
+    fn deserialize_in<Mark: RefCountFamily, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Mark::Pointer<[T]>, D::Error> {
+        Vec::deserialize(deserializer).map(Mark::new_slice)
+    }
+}
+
+/// Serializes the value `pointer` points to.
+pub fn serialize<P, S>(pointer: &P, serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: Deref,
+    P::Target: Serialize,
+    S: Serializer,
+{
+    (**pointer).serialize(serializer)
+}
+
+/// Deserializes a value into a new pointer.
+pub fn deserialize<'de, P, T, D>(deserializer: D) -> Result<P, D::Error>
+where
+    P: RefCounted<T>,
+    T: ?Sized + DeserializeShared<'de>,
+    D: Deserializer<'de>,
+{
+    let pointer = T::deserialize_in::<P::Mark, D>(deserializer)?;
+    // SAFETY: `P` is the pointer of its family for `T`.
+    Ok(unsafe { P::from_raw(RefCounted::into_raw(pointer)) })
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + Serialize> Serialize for Shared<Mark, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, Mark: RefCountFamily, T: ?Sized + DeserializeShared<'de>> Deserialize<'de>
+    for Shared<Mark, T>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_in::<Mark, D>(deserializer).map(Shared::from_pointer)
+    }
+}
+
+impl<Mark: RefCountFamily> Serialize for SharedStr<Mark> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de, Mark: RefCountFamily> Deserialize<'de> for SharedStr<Mark> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        str::deserialize_in::<Mark, D>(deserializer).map(SharedStr::from_pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArcMark, RcMark, RefCountFamily, Shared, SharedStr};
+    use ::serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(bound = "")]
+    struct Foo<R: RefCountFamily> {
+        #[serde(with = "crate::serde")]
+        name: R::Pointer<str>,
+        #[serde(with = "crate::serde")]
+        values: R::Pointer<[i32]>,
+        #[serde(with = "crate::serde")]
+        count: R::Pointer<u8>,
+        tags: Shared<R, [String]>,
+        label: SharedStr<R>,
+    }
+
+    #[test]
+    fn test_serde() {
+        fn actual_test<R: RefCountFamily>() {
+            let json = r#"{"name":"a","values":[1,2],"count":3,"tags":["x"],"label":"y"}"#;
+            let foo: Foo<R> = serde_json::from_str(json).unwrap();
+            assert_eq!(
+                (&*foo.name, &*foo.values, *foo.count),
+                ("a", &[1, 2][..], 3)
+            );
+            assert_eq!(
+                (&*foo.tags, foo.label.as_str()),
+                (&["x".to_owned()][..], "y")
+            );
+            assert_eq!(serde_json::to_string(&foo).unwrap(), json);
+            let shared: Shared<R, str> = serde_json::from_str(r#""b""#).unwrap();
+            assert_eq!(&*shared, "b");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
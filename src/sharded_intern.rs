@@ -0,0 +1,231 @@
+use crate::{
+    intern::{Interner, Retention},
+    traits::*,
+};
+use std::{
+    borrow::Borrow,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hash},
+    sync::{Mutex, MutexGuard},
+};
+
+/// An [Interner] usable from many threads, split into shards locked separately,
+/// so threads interning values with different hashes rarely wait for each other.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// use std::{sync::Arc, thread};
+/// let symbols = ShardedInterner::<ArcMark, str>::new();
+/// let handles: Vec<_> = thread::scope(|scope| {
+///     let workers: Vec<_> = (0..4)
+///         .map(|_| scope.spawn(|| symbols.intern_str("main")))
+///         .collect();
+///     workers.into_iter().map(|w| w.join().unwrap()).collect()
+/// });
+/// assert!(handles.iter().all(|h| Arc::ptr_eq(h, &handles[0])));
+/// assert_eq!(symbols.len(), 1);
+/// ```
+pub struct ShardedInterner<Mark: ThreadSafeFamily, T: ?Sized, S = RandomState> {
+    shards: Box<[Mutex<Interner<Mark, T, ()>>]>,
+    hasher: S,
+}
+
+impl<Mark: ThreadSafeFamily, T: ?Sized> ShardedInterner<Mark, T> {
+    /// Creates an interner with [Retention::Strong] and four shards per available
+    /// thread.
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        Self::with_shards(threads * 4, Retention::Strong)
+    }
+
+    /// # Panics
+    /// If `shards` is 0.
+    pub fn with_shards(shards: usize, retention: Retention) -> Self {
+        Self::with_shards_and_hasher(shards, retention, RandomState::new())
+    }
+}
+
+impl<Mark: ThreadSafeFamily, T: ?Sized, S> ShardedInterner<Mark, T, S> {
+    /// # Panics
+    /// If `shards` is 0.
+    pub fn with_shards_and_hasher(shards: usize, retention: Retention, hasher: S) -> Self {
+        assert!(shards > 0, "ShardedInterner needs at least one shard");
+        Self {
+            shards: (0..shards)
+                .map(|_| Mutex::new(Interner::with_retention_and_hasher(retention, ())))
+                .collect(),
+            hasher,
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.lock(0).retention()
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    /// Returns the number of entries, including the dead ones not pruned yet with
+    /// [Retention::Weak]. Other threads may change it meanwhile.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|i| self.lock(i).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the dead entries.
+    pub fn prune(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).prune();
+        }
+    }
+
+    pub fn clear(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).clear();
+        }
+    }
+
+    /// Locks a shard, ignoring poisoning since the interners stay consistent when
+    /// a comparison panics.
+    fn lock(&self, shard: usize) -> MutexGuard<'_, Interner<Mark, T, ()>> {
+        self.shards[shard]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn shard(&self, hash: u64) -> MutexGuard<'_, Interner<Mark, T, ()>> {
+        self.lock((hash % self.shards.len() as u64) as usize)
+    }
+}
+
+impl<Mark: ThreadSafeFamily, T: ?Sized + Hash + Eq, S: BuildHasher> ShardedInterner<Mark, T, S> {
+    /// Returns the interned value equal to `key`, if any.
+    pub fn get<Q>(&self, key: &Q) -> Option<Mark::Pointer<T>>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        self.shard(hash).find_hashed(hash, key)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the interned value equal to `value`, interning it first if needed.
+    pub fn intern(&self, value: T) -> Mark::Pointer<T>
+    where
+        T: Sized,
+    {
+        let hash = self.hasher.hash_one(&value);
+        let mut shard = self.shard(hash);
+        match shard.find_hashed(hash, &value) {
+            Some(pointer) => pointer,
+            None => shard.insert_hashed(hash, Mark::new(value)),
+        }
+    }
+
+    /// Returns the interned value equal to `*pointer`, interning `pointer` first if
+    /// needed.
+    pub fn intern_pointer(&self, pointer: Mark::Pointer<T>) -> Mark::Pointer<T> {
+        let hash = self.hasher.hash_one(&*pointer);
+        let mut shard = self.shard(hash);
+        match shard.find_hashed(hash, &*pointer) {
+            Some(pointer) => pointer,
+            None => shard.insert_hashed(hash, pointer),
+        }
+    }
+
+    /// Returns the interned value equal to `key`, interning `make(key)` first if
+    /// needed, which must be equal to `key`. The shard of `key` stays locked while
+    /// `make` runs.
+    pub fn intern_with<Q, F>(&self, key: &Q, make: F) -> Mark::Pointer<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&Q) -> Mark::Pointer<T>,
+    {
+        let hash = self.hasher.hash_one(key);
+        let mut shard = self.shard(hash);
+        match shard.find_hashed(hash, key) {
+            Some(pointer) => pointer,
+            None => shard.insert_hashed(hash, make(key)),
+        }
+    }
+}
+
+impl<Mark: ThreadSafeFamily, S: BuildHasher> ShardedInterner<Mark, str, S> {
+    /// Returns the interned string equal to `s`, interning a copy first if needed.
+    pub fn intern_str(&self, s: &str) -> Mark::Pointer<str> {
+        self.intern_with(s, Mark::new_str)
+    }
+}
+
+impl<Mark: ThreadSafeFamily, T: ?Sized> Default for ShardedInterner<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: ThreadSafeFamily, T: ?Sized, S> fmt::Debug for ShardedInterner<Mark, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedInterner")
+            .field("shards", &self.shards.len())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArcMark;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sharded_interner() {
+        let interner = ShardedInterner::<ArcMark, String>::with_shards(4, Retention::Weak);
+        let pointers: Vec<Vec<_>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..100)
+                            .map(|i| interner.intern(i.to_string()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        assert_eq!(interner.len(), 100);
+        for worker in &pointers[1..] {
+            assert!(worker
+                .iter()
+                .zip(&pointers[0])
+                .all(|(a, b)| Arc::ptr_eq(a, b)));
+        }
+        assert!(Arc::ptr_eq(&pointers[0][7], &interner.get("7").unwrap()));
+
+        drop(pointers);
+        assert!(!interner.contains("7"));
+        interner.prune();
+        assert_eq!(
+            (interner.is_empty(), interner.retention()),
+            (true, Retention::Weak)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! [DynFamily] and [Shareable], choosing the trait objects shared by each family.
+//!
+//! The pointers of a thread-safe family are `Send` and `Sync` when their value
+//! is, so the closures and other trait objects it shares must be `Send` and
+//! `Sync` as well. Those of a single-threaded family need not, so they can hold
+//! values like `Rc`. [DynFamily] gives the trait objects of each family, and
+//! [Shareable] boxes a value into them.
+//!
+//! ```
+//! # use cark_ref_counted::*;
+//! fn share<R: DynFamily, F: Fn(i32) -> i32 + Shareable<R>>(f: F) -> R::Pointer<R::DynFn<i32, i32>> {
+//!     R::from_box(f.into_dyn_fn())
+//! }
+//! let offset = std::rc::Rc::new(1);
+//! let local = share::<RcMark, _>(move |x| x + *offset);
+//! let sendable = share::<ArcMark, _>(|x| x * 2);
+//! assert_eq!(std::thread::spawn(move || sendable(2)).join().unwrap(), local(3));
+//! ```
+
+use std::{any::Any, error::Error};
+
+use crate::{traits::*, ArcMark, RcMark, WrapHooks, WrapMark};
+
+/// A [RefCountFamily] with the trait objects it shares: `Send + Sync` ones when it
+/// is thread-safe, unbounded ones otherwise.
+pub trait DynFamily: RefCountFamily {
+    type DynFn<Args, Out>: ?Sized + Fn(Args) -> Out;
+    type DynFnMut<Args, Out>: ?Sized + FnMut(Args) -> Out;
+    type DynCallback<E>: ?Sized + Fn(&E);
+    type DynAny: ?Sized + Any;
+    type DynError: ?Sized + Error + 'static;
+
+    /// Boxes a `Send + Sync` callback, which every family can share, for code
+    /// generic over the family.
+    fn box_sync_callback<E, F: Fn(&E) + Send + Sync + 'static>(f: F) -> Box<Self::DynCallback<E>>;
+    /// Boxes a `Send + Sync` value, which every family can share, for code generic
+    /// over the family.
+    fn box_sync_any<T: Any + Send + Sync>(value: T) -> Box<Self::DynAny>;
+}
+
+/// The values a family can share: all of them for a single-threaded family,
+/// `Send + Sync` ones for a thread-safe family.
+///
+/// It is sealed, so a value shared by a thread-safe family is always `Send` and
+/// `Sync`.
+pub trait Shareable<Mark: DynFamily>: sealed::Sealed<Mark> + Sized + 'static {
+    fn into_dyn_fn<Args, Out>(self) -> Box<Mark::DynFn<Args, Out>>
+    where
+        Self: Fn(Args) -> Out;
+    fn into_dyn_fn_mut<Args, Out>(self) -> Box<Mark::DynFnMut<Args, Out>>
+    where
+        Self: FnMut(Args) -> Out;
+    fn into_dyn_callback<E>(self) -> Box<Mark::DynCallback<E>>
+    where
+        Self: Fn(&E);
+}
+
+mod sealed {
+    pub trait Sealed<Mark> {}
+}
+
+impl DynFamily for RcMark {
+    type DynFn<Args, Out> = dyn Fn(Args) -> Out;
+    type DynFnMut<Args, Out> = dyn FnMut(Args) -> Out;
+    type DynCallback<E> = dyn Fn(&E);
+    type DynAny = dyn Any;
+    type DynError = dyn Error;
+
+    fn box_sync_callback<E, F: Fn(&E) + Send + Sync + 'static>(f: F) -> Box<dyn Fn(&E)> {
+        Box::new(f)
+    }
+
+    fn box_sync_any<T: Any + Send + Sync>(value: T) -> Box<dyn Any> {
+        Box::new(value)
+    }
+}
+
+impl<T: 'static> sealed::Sealed<RcMark> for T {}
+
+impl<T: 'static> Shareable<RcMark> for T {
+    fn into_dyn_fn<Args, Out>(self) -> Box<dyn Fn(Args) -> Out>
+    where
+        T: Fn(Args) -> Out,
+    {
+        Box::new(self)
+    }
+
+    fn into_dyn_fn_mut<Args, Out>(self) -> Box<dyn FnMut(Args) -> Out>
+    where
+        T: FnMut(Args) -> Out,
+    {
+        Box::new(self)
+    }
+
+    fn into_dyn_callback<E>(self) -> Box<dyn Fn(&E)>
+    where
+        T: Fn(&E),
+    {
+        Box::new(self)
+    }
+}
+
+impl DynFamily for ArcMark {
+    type DynFn<Args, Out> = dyn Fn(Args) -> Out + Send + Sync;
+    type DynFnMut<Args, Out> = dyn FnMut(Args) -> Out + Send + Sync;
+    type DynCallback<E> = dyn Fn(&E) + Send + Sync;
+    type DynAny = dyn Any + Send + Sync;
+    type DynError = dyn Error + Send + Sync;
+
+    fn box_sync_callback<E, F: Fn(&E) + Send + Sync + 'static>(
+        f: F,
+    ) -> Box<dyn Fn(&E) + Send + Sync> {
+        Box::new(f)
+    }
+
+    fn box_sync_any<T: Any + Send + Sync>(value: T) -> Box<dyn Any + Send + Sync> {
+        Box::new(value)
+    }
+}
+
+impl<T: Send + Sync + 'static> sealed::Sealed<ArcMark> for T {}
+
+impl<T: Send + Sync + 'static> Shareable<ArcMark> for T {
+    fn into_dyn_fn<Args, Out>(self) -> Box<dyn Fn(Args) -> Out + Send + Sync>
+    where
+        T: Fn(Args) -> Out,
+    {
+        Box::new(self)
+    }
+
+    fn into_dyn_fn_mut<Args, Out>(self) -> Box<dyn FnMut(Args) -> Out + Send + Sync>
+    where
+        T: FnMut(Args) -> Out,
+    {
+        Box::new(self)
+    }
+
+    fn into_dyn_callback<E>(self) -> Box<dyn Fn(&E) + Send + Sync>
+    where
+        T: Fn(&E),
+    {
+        Box::new(self)
+    }
+}
+
+impl<Mark: DynFamily, H: WrapHooks, Tag> DynFamily for WrapMark<Mark, H, Tag> {
+    type DynFn<Args, Out> = Mark::DynFn<Args, Out>;
+    type DynFnMut<Args, Out> = Mark::DynFnMut<Args, Out>;
+    type DynCallback<E> = Mark::DynCallback<E>;
+    type DynAny = Mark::DynAny;
+    type DynError = Mark::DynError;
+
+    fn box_sync_callback<E, F: Fn(&E) + Send + Sync + 'static>(f: F) -> Box<Mark::DynCallback<E>> {
+        Mark::box_sync_callback(f)
+    }
+
+    fn box_sync_any<T: Any + Send + Sync>(value: T) -> Box<Mark::DynAny> {
+        Mark::box_sync_any(value)
+    }
+}
+
+impl<Mark: DynFamily, H: WrapHooks, Tag, T: Shareable<Mark>> sealed::Sealed<WrapMark<Mark, H, Tag>>
+    for T
+{
+}
+
+impl<Mark: DynFamily, H: WrapHooks, Tag, T: Shareable<Mark>> Shareable<WrapMark<Mark, H, Tag>>
+    for T
+{
+    fn into_dyn_fn<Args, Out>(self) -> Box<Mark::DynFn<Args, Out>>
+    where
+        T: Fn(Args) -> Out,
+    {
+        Shareable::<Mark>::into_dyn_fn(self)
+    }
+
+    fn into_dyn_fn_mut<Args, Out>(self) -> Box<Mark::DynFnMut<Args, Out>>
+    where
+        T: FnMut(Args) -> Out,
+    {
+        Shareable::<Mark>::into_dyn_fn_mut(self)
+    }
+
+    fn into_dyn_callback<E>(self) -> Box<Mark::DynCallback<E>>
+    where
+        T: Fn(&E),
+    {
+        Shareable::<Mark>::into_dyn_callback(self)
+    }
+}
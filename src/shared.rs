@@ -0,0 +1,192 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// A [RefCountFamily] pointer carrying its mark in its type.
+///
+/// The methods of [RefCounted] are inherent methods here, and since the mark is
+/// part of the type, constructors don't need a turbofish once the type is known.
+/// Comparisons, hashing and formatting go through the pointed value.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Foo<R: RefCountFamily> {
+///     name: Shared<R, String>,
+/// }
+/// let mut foo = Foo::<RcMark> {
+///     name: Shared::new("John".to_owned()),
+/// };
+/// let other = foo.name.clone();
+/// assert_eq!(foo.name.strong_count(), 2);
+/// foo.name.make_mut().push_str(" Doe");
+/// assert_eq!(*foo.name, "John Doe");
+/// assert_eq!(*other, "John");
+/// ```
+pub struct Shared<Mark: RefCountFamily, T: ?Sized>(Mark::Pointer<T>);
+
+impl<Mark: RefCountFamily, T> Shared<Mark, T> {
+    pub fn new(value: T) -> Self {
+        Self(Mark::new(value))
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Shared<Mark, T> {
+    pub fn from_pointer(pointer: Mark::Pointer<T>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_pointer(self) -> Mark::Pointer<T> {
+        self.0
+    }
+
+    /// Returns a reference to the wrapped pointer.
+    pub fn pointer(&self) -> &Mark::Pointer<T> {
+        &self.0
+    }
+
+    pub fn downgrade(&self) -> Mark::WeakPointer<T> {
+        Mark::Pointer::downgrade(&self.0)
+    }
+
+    pub fn strong_count(&self) -> usize {
+        Mark::Pointer::strong_count(&self.0)
+    }
+
+    pub fn weak_count(&self) -> usize {
+        Mark::Pointer::weak_count(&self.0)
+    }
+
+    /// Returns `true` if both point to the same allocation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn make_mut(&mut self) -> &mut T
+    where
+        T: Clone,
+    {
+        Mark::Pointer::make_mut(&mut self.0)
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        Mark::Pointer::get_mut(&mut self.0)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for Shared<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Deref for Shared<Mark, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Borrow<T> for Shared<Mark, T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> AsRef<T> for Shared<Mark, T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily, T: Default> Default for Shared<Mark, T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<Mark: RefCountFamily, T> From<T> for Shared<Mark, T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + fmt::Debug> fmt::Debug for Shared<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + fmt::Display> fmt::Display for Shared<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> fmt::Pointer for Shared<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.0, f)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + PartialEq> PartialEq for Shared<Mark, T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + Eq> Eq for Shared<Mark, T> {}
+
+impl<Mark: RefCountFamily, T: ?Sized + PartialOrd> PartialOrd for Shared<Mark, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + Ord> Ord for Shared<Mark, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + Hash> Hash for Shared<Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_shared() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let mut a: Shared<Mark, i32> = Shared::new(1);
+            assert_eq!(a.get_mut(), Some(&mut 1));
+            let b = a.clone();
+            assert!(a.ptr_eq(&b));
+            assert!(a.get_mut().is_none());
+            let weak = a.downgrade();
+            assert_eq!((a.strong_count(), a.weak_count()), (2, 1));
+            *a.make_mut() += 1;
+            assert!(!a.ptr_eq(&b));
+            assert!(a > b);
+            assert_eq!(b, Shared::from(1));
+            let set: HashSet<_> = [a, b.clone(), Shared::new(1)].into_iter().collect();
+            assert_eq!(set.len(), 2);
+            drop(set);
+            assert!(weak.upgrade().is_some());
+            drop(b);
+            assert!(weak.upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{shared_slice::SharedSlice, traits::*};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, RangeBounds},
+};
+
+/// A shared byte buffer of any family, viewed through a window that can be
+/// narrowed without copying, like `bytes::Bytes`.
+///
+/// Clones and sub-slices share the buffer. Comparisons and hashing go through the
+/// bytes of the window. It is a [SharedSlice] of bytes, formatted as a byte string.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn parse_line<R: RefCountFamily>(input: &mut SharedBytes<R>) -> Option<SharedBytes<R>> {
+///     let end = input.iter().position(|&b| b == b'\n')?;
+///     let line = input.split_to(end + 1);
+///     Some(line.slice(..end))
+/// }
+/// let mut input = SharedBytes::<ArcMark>::from(&b"GET /\nHost: a\n"[..]);
+/// assert_eq!(parse_line(&mut input).unwrap(), b"GET /"[..]);
+/// assert_eq!(parse_line(&mut input).unwrap(), b"Host: a"[..]);
+/// assert!(input.is_empty());
+/// ```
+pub struct SharedBytes<Mark: RefCountFamily>(SharedSlice<Mark, u8>);
+
+impl<Mark: RefCountFamily> SharedBytes<Mark> {
+    /// Views the whole of `data`.
+    pub fn from_pointer(data: Mark::Pointer<[u8]>) -> Self {
+        Self(SharedSlice::from_pointer(data))
+    }
+
+    /// Returns the whole shared buffer, ignoring the window.
+    pub fn pointer(&self) -> &Mark::Pointer<[u8]> {
+        self.0.pointer()
+    }
+
+    /// Returns the window as a [SharedSlice].
+    pub fn as_slice(&self) -> &SharedSlice<Mark, u8> {
+        &self.0
+    }
+
+    pub fn into_slice(self) -> SharedSlice<Mark, u8> {
+        self.0
+    }
+
+    /// Returns a view of `range` within this one, sharing the buffer.
+    ///
+    /// # Panics
+    /// If the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        Self(self.0.slice(range))
+    }
+
+    /// Splits off the bytes before `at`, which are returned, keeping the rest.
+    ///
+    /// # Panics
+    /// If `at` is greater than the length.
+    pub fn split_to(&mut self, at: usize) -> Self {
+        Self(self.0.split_to(at))
+    }
+
+    /// Splits off the bytes from `at` on, which are returned, keeping the rest.
+    ///
+    /// # Panics
+    /// If `at` is greater than the length.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        Self(self.0.split_off(at))
+    }
+
+    /// Shortens the view to `len` bytes. Does nothing if it is shorter already.
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len)
+    }
+
+    /// Returns `true` if both view the same bytes of the same buffer.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<Mark: RefCountFamily> Clone for SharedBytes<Mark> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily> Deref for SharedBytes<Mark> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily> Borrow<[u8]> for SharedBytes<Mark> {
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<Mark: RefCountFamily> AsRef<[u8]> for SharedBytes<Mark> {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<Mark: RefCountFamily> Default for SharedBytes<Mark> {
+    fn default() -> Self {
+        Self(SharedSlice::default())
+    }
+}
+
+impl<Mark: RefCountFamily> From<&[u8]> for SharedBytes<Mark> {
+    fn from(bytes: &[u8]) -> Self {
+        Self::from_pointer(Mark::from_iter(bytes.iter().copied()))
+    }
+}
+
+impl<Mark: RefCountFamily> From<Vec<u8>> for SharedBytes<Mark> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::from_pointer(Mark::new_slice(bytes))
+    }
+}
+
+impl<Mark: RefCountFamily> From<SharedSlice<Mark, u8>> for SharedBytes<Mark> {
+    fn from(slice: SharedSlice<Mark, u8>) -> Self {
+        Self(slice)
+    }
+}
+
+impl<Mark: RefCountFamily> From<String> for SharedBytes<Mark> {
+    fn from(s: String) -> Self {
+        Self::from(s.into_bytes())
+    }
+}
+
+impl<Mark: RefCountFamily> fmt::Debug for SharedBytes<Mark> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        for &byte in &**self {
+            fmt::Display::fmt(&byte.escape_ascii(), f)?;
+        }
+        f.write_str("\"")
+    }
+}
+
+impl<Mark: RefCountFamily> PartialEq for SharedBytes<Mark> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily> Eq for SharedBytes<Mark> {}
+
+impl<Mark: RefCountFamily> PartialEq<[u8]> for SharedBytes<Mark> {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl<Mark: RefCountFamily> PartialOrd for SharedBytes<Mark> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Mark: RefCountFamily> Ord for SharedBytes<Mark> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily> Hash for SharedBytes<Mark> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_shared_bytes() {
+        fn actual_test<R: RefCountFamily>() {
+            let mut bytes = SharedBytes::<R>::from(b"hello world".to_vec());
+            let hello = bytes.split_to(5);
+            assert_eq!(hello, b"hello"[..]);
+            assert_eq!(bytes, b" world"[..]);
+            assert!(R::Pointer::ptr_eq(hello.pointer(), bytes.pointer()));
+            let world = bytes.slice(1..);
+            assert_eq!(world.slice(1..=2), b"or"[..]);
+            assert_eq!(R::Pointer::strong_count(world.pointer()), 3);
+            let mut tail = world.clone();
+            let ld = tail.split_off(3);
+            assert_eq!((&*tail, &*ld), (&b"wor"[..], &b"ld"[..]));
+            tail.truncate(1);
+            tail.truncate(5);
+            assert_eq!(tail, b"w"[..]);
+            assert!(world.ptr_eq(&world.clone()));
+            assert!(!world.ptr_eq(&SharedBytes::from(&b"world"[..])));
+            assert_eq!(world, SharedBytes::from(&b"world"[..]));
+            assert!(hello < world);
+            assert_eq!(
+                format!("{:?}", SharedBytes::<R>::from(&b"a\n"[..])),
+                r#"b"a\n""#
+            );
+            assert!(SharedBytes::<R>::default().is_empty());
+            assert!(SharedBytes::from(world.clone().into_slice()).ptr_eq(&world));
+            let out_of_bounds = std::panic::AssertUnwindSafe(|| world.slice(2..6));
+            assert!(std::panic::catch_unwind(out_of_bounds).is_err());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    lock::{AsyncLockFamily, LockFamily},
+    traits::*,
+};
+use std::fmt;
+
+/// A shared mutable value: a `Mark::Pointer` to the lock of the [LockFamily],
+/// as in `Rc<RefCell<T>>` and `Arc<RwLock<T>>`.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn push<R: LockFamily>(list: &SharedCell<R, Vec<i32>>, value: i32) {
+///     list.write().push(value);
+/// }
+/// let list = SharedCell::<ArcMark, _>::new(vec![]);
+/// let weak = list.downgrade();
+/// push(&list, 1);
+/// assert_eq!(*weak.upgrade().unwrap().read(), [1]);
+/// ```
+pub struct SharedCell<Mark: LockFamily, T>(Mark::Pointer<Mark::Lock<T>>);
+
+impl<Mark: LockFamily, T> SharedCell<Mark, T> {
+    pub fn new(value: T) -> Self {
+        Self(Mark::new(Mark::new_lock(value)))
+    }
+
+    pub fn from_pointer(pointer: Mark::Pointer<Mark::Lock<T>>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_pointer(self) -> Mark::Pointer<Mark::Lock<T>> {
+        self.0
+    }
+
+    /// Returns a reference to the wrapped pointer.
+    pub fn pointer(&self) -> &Mark::Pointer<Mark::Lock<T>> {
+        &self.0
+    }
+
+    /// Locks the value for reading. See [LockFamily::read].
+    pub fn read(&self) -> Mark::ReadGuard<'_, T> {
+        Mark::read(&self.0)
+    }
+
+    /// Locks the value for writing. See [LockFamily::write].
+    pub fn write(&self) -> Mark::WriteGuard<'_, T> {
+        Mark::write(&self.0)
+    }
+
+    /// Locks the value for reading without blocking the executor. See
+    /// [AsyncLockFamily::read_async].
+    pub async fn read_async(&self) -> Mark::ReadGuard<'_, T>
+    where
+        Mark: AsyncLockFamily,
+    {
+        Mark::read_async(&self.0).await
+    }
+
+    /// Locks the value for writing without blocking the executor. See
+    /// [AsyncLockFamily::write_async].
+    pub async fn write_async(&self) -> Mark::WriteGuard<'_, T>
+    where
+        Mark: AsyncLockFamily,
+    {
+        Mark::write_async(&self.0).await
+    }
+
+    pub fn try_read(&self) -> Option<Mark::ReadGuard<'_, T>> {
+        Mark::try_read(&self.0)
+    }
+
+    pub fn try_write(&self) -> Option<Mark::WriteGuard<'_, T>> {
+        Mark::try_write(&self.0)
+    }
+
+    /// Returns the value if this is the only strong pointer to it.
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        Mark::Pointer::try_unwrap(self.0)
+            .map(Mark::lock_into_inner)
+            .map_err(Self)
+    }
+
+    pub fn downgrade(&self) -> WeakCell<Mark, T> {
+        WeakCell(Mark::Pointer::downgrade(&self.0))
+    }
+
+    pub fn strong_count(&self) -> usize {
+        Mark::Pointer::strong_count(&self.0)
+    }
+
+    pub fn weak_count(&self) -> usize {
+        Mark::Pointer::weak_count(&self.0)
+    }
+
+    /// Returns `true` if both point to the same allocation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: LockFamily, T> Clone for SharedCell<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily, T: Default> Default for SharedCell<Mark, T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<Mark: LockFamily, T> From<T> for SharedCell<Mark, T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<Mark: LockFamily, T: fmt::Debug> fmt::Debug for SharedCell<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("SharedCell");
+        match self.try_read() {
+            Some(value) => tuple.field(&*value),
+            None => tuple.field(&format_args!("<locked>")),
+        };
+        tuple.finish()
+    }
+}
+
+/// A weak pointer to the value of a [SharedCell].
+pub struct WeakCell<Mark: LockFamily, T>(Mark::WeakPointer<Mark::Lock<T>>);
+
+impl<Mark: LockFamily, T> WeakCell<Mark, T> {
+    /// Creates a weak pointer that never upgrades.
+    pub fn new() -> Self {
+        Self(Mark::WeakPointer::new())
+    }
+
+    pub fn upgrade(&self) -> Option<SharedCell<Mark, T>> {
+        self.0.upgrade().map(SharedCell)
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.0.strong_count()
+    }
+
+    pub fn weak_count(&self) -> usize {
+        self.0.weak_count()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<Mark: LockFamily, T> Clone for WeakCell<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily, T> Default for WeakCell<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: LockFamily, T> fmt::Debug for WeakCell<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WeakCell(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_shared_cell() {
+        fn actual_test<R: LockFamily>() {
+            let cell = SharedCell::<R, _>::new(vec![1]);
+            let clone = cell.clone();
+            assert!(cell.ptr_eq(&clone));
+            assert!(!cell.ptr_eq(&SharedCell::new(vec![1])));
+            clone.write().push(2);
+            assert_eq!(*cell.read(), [1, 2]);
+            {
+                let _guard = cell.write();
+                assert!(clone.try_read().is_none());
+                assert_eq!(format!("{clone:?}"), "SharedCell(<locked>)");
+            }
+            assert_eq!(format!("{clone:?}"), "SharedCell([1, 2])");
+
+            let weak = cell.downgrade();
+            assert_eq!((cell.strong_count(), cell.weak_count()), (2, 1));
+            let cell = cell.try_unwrap().unwrap_err();
+            drop(clone);
+            assert_eq!(cell.try_unwrap().ok(), Some(vec![1, 2]));
+            assert!(weak.upgrade().is_none());
+            assert!(WeakCell::<R, i32>::new().upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
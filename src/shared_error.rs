@@ -0,0 +1,175 @@
+use crate::{shareable::DynFamily, traits::*};
+use std::{error::Error, fmt};
+
+/// A cheaply cloned error of any family, to fail many waiters with the same
+/// cause.
+///
+/// `E` defaults to the error trait object of the family, see [DynFamily]:
+/// `dyn Error + Send + Sync` for thread-safe families, `dyn Error` for
+/// single-threaded ones, which may then hold errors that are not `Send`.
+///
+/// Since `SharedError` is itself an [Error], a blanket `From<impl Error>` would
+/// overlap with `From<Self>`; errors are wrapped with [SharedError::new] instead,
+/// for instance in `map_err(SharedError::new)`.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn fail_all(waiters: usize, cause: std::io::Error) -> Vec<Result<(), SharedError<ArcMark>>> {
+///     let error = SharedError::new(cause).context("connection lost");
+///     vec![Err(error); waiters]
+/// }
+/// let results = fail_all(3, std::io::ErrorKind::BrokenPipe.into());
+/// let error = results[2].as_ref().unwrap_err();
+/// assert_eq!(error.to_string(), "connection lost");
+/// assert_eq!(std::error::Error::source(error).unwrap().to_string(), "broken pipe");
+/// ```
+pub struct SharedError<Mark: DynFamily, E: ?Sized + Error + 'static = <Mark as DynFamily>::DynError>(
+    Mark::Pointer<E>,
+);
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> SharedError<Mark, E> {
+    /// Wraps `error`, boxing it first to erase its type.
+    pub fn new<X: Error>(error: X) -> Self
+    where
+        Box<E>: From<X>,
+    {
+        Self(Mark::from_box(Box::from(error)))
+    }
+
+    pub fn from_pointer(pointer: Mark::Pointer<E>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_pointer(self) -> Mark::Pointer<E> {
+        self.0
+    }
+
+    /// Returns a new error displaying `context`, whose source is this error.
+    pub fn context<C>(self, context: C) -> Self
+    where
+        Mark: 'static,
+        C: fmt::Display + fmt::Debug + 'static,
+        Box<E>: From<ContextError<Mark, E, C>>,
+    {
+        Self::new(ContextError {
+            context,
+            source: self,
+        })
+    }
+
+    /// Returns `true` if both share the same error.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::addr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> Clone for SharedError<Mark, E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> AsRef<E> for SharedError<Mark, E> {
+    fn as_ref(&self) -> &E {
+        &self.0
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> From<Box<E>> for SharedError<Mark, E> {
+    fn from(error: Box<E>) -> Self {
+        Self(Mark::from_box(error))
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> fmt::Display for SharedError<Mark, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> fmt::Debug for SharedError<Mark, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static> Error for SharedError<Mark, E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// The error made by [SharedError::context].
+pub struct ContextError<Mark: DynFamily, E: ?Sized + Error + 'static, C> {
+    context: C,
+    source: SharedError<Mark, E>,
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static, C> ContextError<Mark, E, C> {
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static, C: fmt::Display> fmt::Display
+    for ContextError<Mark, E, C>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.context.fmt(f)
+    }
+}
+
+impl<Mark: DynFamily, E: ?Sized + Error + 'static, C: fmt::Debug> fmt::Debug
+    for ContextError<Mark, E, C>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextError")
+            .field("context", &self.context)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl<Mark: DynFamily + 'static, E: ?Sized + Error + 'static, C: fmt::Display + fmt::Debug> Error
+    for ContextError<Mark, E, C>
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::io;
+
+    #[test]
+    fn test_shared_error() {
+        let error = SharedError::<ArcMark>::new(io::Error::other("disk full"));
+        let copy = error.clone();
+        assert!(copy.ptr_eq(&error) && copy.source().is_none());
+        let error = error.context("saving").context("closing");
+        assert_eq!(error.to_string(), "closing");
+        let mut chain = vec![];
+        let mut cause: Option<&dyn Error> = Some(&error);
+        while let Some(e) = cause {
+            chain.push(e.to_string());
+            cause = e.source();
+        }
+        assert_eq!(chain, ["closing", "saving", "disk full"]);
+        assert!(format!("{error:?}").starts_with(r#"ContextError { context: "closing""#));
+        let boxed: Box<dyn Error + Send + Sync> = "boxed".into();
+        assert_eq!(
+            SharedError::<ArcMark>::from(boxed).as_ref().to_string(),
+            "boxed"
+        );
+
+        let local = SharedError::<RcMark>::new(io::Error::other("local"));
+        let local = local.context(std::rc::Rc::new("reading"));
+        assert_eq!(
+            (local.to_string(), local.source().unwrap().to_string()),
+            ("reading".to_owned(), "local".to_owned())
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! [SharedFn] and [SharedFnMut], shared closures of any family built on stable.
+//!
+//! The closure is boxed first, and the box moved into the pointer with
+//! [RefCountFamily::from_box], so no `CoerceUnsized` bound is needed. The boxed
+//! closure is one of the [DynFamily] trait objects, so it is `Send + Sync` with
+//! thread-safe families. With the `nightly` feature, [SharedFn] also implements
+//! the `Fn` traits.
+
+use crate::{lock::LockFamily, shareable::*, shared_cell::SharedCell, traits::*};
+use std::fmt;
+
+/// A shared `Mark::Pointer<Mark::DynFn<Args, Out>>`, a `dyn Fn(Args) -> Out`.
+///
+/// Several arguments are passed as a tuple.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Button<R: DynFamily> {
+///     on_click: SharedFn<R, (i32, i32), String>,
+/// }
+/// let offset = 10;
+/// let button = Button::<RcMark> {
+///     on_click: SharedFn::new(move |(x, y)| format!("{}, {}", x + offset, y)),
+/// };
+/// let handler = button.on_click.clone();
+/// assert_eq!(handler.call((1, 2)), "11, 2");
+/// ```
+pub struct SharedFn<Mark: DynFamily, Args, Out = ()>(Mark::Pointer<Mark::DynFn<Args, Out>>);
+
+impl<Mark: DynFamily, Args, Out> SharedFn<Mark, Args, Out> {
+    pub fn new<F: Fn(Args) -> Out + Shareable<Mark>>(f: F) -> Self {
+        Self(Mark::from_box(f.into_dyn_fn()))
+    }
+
+    pub fn from_pointer(pointer: Mark::Pointer<Mark::DynFn<Args, Out>>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_pointer(self) -> Mark::Pointer<Mark::DynFn<Args, Out>> {
+        self.0
+    }
+
+    pub fn call(&self, args: Args) -> Out {
+        (self.0)(args)
+    }
+
+    /// Returns `true` if both point to the same closure.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::addr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: DynFamily, Args, Out> Clone for SharedFn<Mark, Args, Out> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: DynFamily, Args, Out> fmt::Debug for SharedFn<Mark, Args, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedFn(..)")
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<Mark: DynFamily, Args, Out> FnOnce<(Args,)> for SharedFn<Mark, Args, Out> {
+    type Output = Out;
+    extern "rust-call" fn call_once(self, (args,): (Args,)) -> Out {
+        (self.0)(args)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<Mark: DynFamily, Args, Out> FnMut<(Args,)> for SharedFn<Mark, Args, Out> {
+    extern "rust-call" fn call_mut(&mut self, (args,): (Args,)) -> Out {
+        (self.0)(args)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<Mark: DynFamily, Args, Out> Fn<(Args,)> for SharedFn<Mark, Args, Out> {
+    extern "rust-call" fn call(&self, (args,): (Args,)) -> Out {
+        (self.0)(args)
+    }
+}
+
+/// A shared `FnMut(Args) -> Out`, kept in the lock of the [LockFamily].
+///
+/// Calling it again from inside the closure panics with [crate::RcMark], and
+/// deadlocks with [crate::ArcMark].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let mut count = 0;
+/// let counter = SharedFnMut::<ArcMark, (), i32>::new(move |()| {
+///     count += 1;
+///     count
+/// });
+/// let clone = counter.clone();
+/// counter.call(());
+/// assert_eq!(clone.call(()), 2);
+/// ```
+pub struct SharedFnMut<Mark: LockFamily + DynFamily, Args, Out = ()>(
+    SharedCell<Mark, Box<Mark::DynFnMut<Args, Out>>>,
+);
+
+impl<Mark: LockFamily + DynFamily, Args, Out> SharedFnMut<Mark, Args, Out> {
+    pub fn new<F: FnMut(Args) -> Out + Shareable<Mark>>(f: F) -> Self {
+        Self(SharedCell::new(f.into_dyn_fn_mut()))
+    }
+
+    pub fn call(&self, args: Args) -> Out {
+        (self.0.write())(args)
+    }
+
+    /// Returns `true` if both point to the same closure.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, Args, Out> Clone for SharedFnMut<Mark, Args, Out> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, Args, Out> fmt::Debug for SharedFnMut<Mark, Args, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedFnMut(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_shared_fn() {
+        fn actual_test<R: LockFamily + DynFamily>(
+            add: impl Fn((i32, i32)) -> i32 + Clone + Shareable<R>,
+            sum: impl FnMut(i32) -> i32 + Shareable<R>,
+        ) {
+            let shared = SharedFn::<R, (i32, i32), i32>::new(add.clone());
+            let clone = shared.clone();
+            assert!(shared.ptr_eq(&clone));
+            assert!(!shared.ptr_eq(&SharedFn::new(add)));
+            assert_eq!(clone.call((1, 2)), 3);
+            assert_eq!(R::Pointer::strong_count(&shared.into_pointer()), 2);
+
+            let sum = SharedFnMut::<R, i32, i32>::new(sum);
+            let clone = sum.clone();
+            assert!(sum.ptr_eq(&clone));
+            sum.call(1);
+            assert_eq!(clone.call(2), 3);
+        }
+        fn summer() -> impl FnMut(i32) -> i32 + Send + Sync {
+            let mut total = 0;
+            move |x| {
+                total += x;
+                total
+            }
+        }
+        actual_test::<RcMark>(|(a, b)| a + b, summer());
+        actual_test::<ArcMark>(|(a, b)| a + b, summer());
+
+        // Not Send, which only single-threaded families accept.
+        let local = std::rc::Rc::new(1);
+        let f = SharedFn::<RcMark, i32, i32>::new(move |x| x + *local);
+        assert_eq!(f.call(1), 2);
+    }
+
+    #[test]
+    fn test_shared_fn_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedFn<ArcMark, i32, i32>>();
+        assert_send_sync::<SharedFnMut<ArcMark, i32, i32>>();
+        let f = SharedFn::<ArcMark, i32, i32>::new(|x| x * 2);
+        assert_eq!(std::thread::spawn(move || f.call(2)).join().unwrap(), 4);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_shared_fn_traits() {
+        let double = SharedFn::<RcMark, i32, i32>::new(|x| x * 2);
+        assert_eq!(double(2), 4);
+        assert_eq!([1, 2].map(double), [2, 4]);
+    }
+}
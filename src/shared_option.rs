@@ -0,0 +1,157 @@
+use crate::traits::*;
+use std::{fmt, mem::size_of};
+
+/// An optional slot holding a [RefCountFamily] pointer.
+///
+/// This is a thin wrapper around `Option<Mark::Pointer<T>>` with the helpers
+/// graph-like structures need for their optional shared links.
+///
+/// Constructing a slot checks at compile time that `Option<Mark::Pointer<T>>`
+/// is the same size as `Mark::Pointer<T>`, that is that the family keeps the
+/// null pointer niche.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Node<R: RefCountFamily> {
+///     value: i32,
+///     next: SharedOption<R, Node<R>>,
+/// }
+/// let mut head = Node::<RcMark> {
+///     value: 1,
+///     next: SharedOption::new(),
+/// };
+/// let next = head.next.get_or_insert_with(|| {
+///     RcMark::new(Node {
+///         value: 2,
+///         next: SharedOption::new(),
+///     })
+/// });
+/// assert_eq!(next.value, 2);
+/// assert_eq!(head.next.take().unwrap().value, 2);
+/// assert!(head.next.is_none());
+/// ```
+pub struct SharedOption<Mark: RefCountFamily, T: ?Sized>(Option<Mark::Pointer<T>>);
+
+impl<Mark: RefCountFamily, T: ?Sized> SharedOption<Mark, T> {
+    /// Creates an empty slot.
+    pub fn new() -> Self {
+        Self::from_option(None)
+    }
+
+    /// Creates a slot holding `pointer`.
+    pub fn some(pointer: Mark::Pointer<T>) -> Self {
+        Self::from_option(Some(pointer))
+    }
+
+    fn from_option(option: Option<Mark::Pointer<T>>) -> Self {
+        const {
+            assert!(
+                size_of::<Option<Mark::Pointer<T>>>() == size_of::<Mark::Pointer<T>>(),
+                "the pointer family doesn't keep the Option niche"
+            )
+        };
+        Self(option)
+    }
+
+    pub fn is_some(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns a reference to the pointer, if any.
+    pub fn get(&self) -> Option<&Mark::Pointer<T>> {
+        self.0.as_ref()
+    }
+
+    /// Returns a reference to the pointed value, if any.
+    pub fn as_deref(&self) -> Option<&T> {
+        self.0.as_deref()
+    }
+
+    /// Takes the pointer out of the slot, leaving it empty.
+    pub fn take(&mut self) -> Option<Mark::Pointer<T>> {
+        self.0.take()
+    }
+
+    /// Puts `pointer` in the slot, returning the previous one if any.
+    pub fn replace(&mut self, pointer: Mark::Pointer<T>) -> Option<Mark::Pointer<T>> {
+        self.0.replace(pointer)
+    }
+
+    /// Returns the pointer, first filling an empty slot with the result of `f`.
+    pub fn get_or_insert_with<F>(&mut self, f: F) -> &Mark::Pointer<T>
+    where
+        F: FnOnce() -> Mark::Pointer<T>,
+    {
+        self.0.get_or_insert_with(f)
+    }
+
+    pub fn into_option(self) -> Option<Mark::Pointer<T>> {
+        self.0
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Default for SharedOption<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for SharedOption<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> From<Option<Mark::Pointer<T>>> for SharedOption<Mark, T> {
+    fn from(option: Option<Mark::Pointer<T>>) -> Self {
+        Self::from_option(option)
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> fmt::Debug for SharedOption<Mark, T>
+where
+    Mark::Pointer<T>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedOption").field(&self.0).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_take_replace() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let mut slot = SharedOption::<Mark, i32>::new();
+            assert!(slot.is_none());
+            assert!(slot.replace(Mark::new(1)).is_none());
+            assert_eq!(slot.as_deref(), Some(&1));
+            assert_eq!(slot.replace(Mark::new(2)).as_deref(), Some(&1));
+            assert_eq!(**slot.get_or_insert_with(|| Mark::new(3)), 2);
+            assert_eq!(slot.take().as_deref(), Some(&2));
+            assert_eq!(**slot.get_or_insert_with(|| Mark::new(3)), 3);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_niche() {
+        fn actual_test<Mark: RefCountFamily>() {
+            assert_eq!(size_of::<SharedOption<Mark, i32>>(), size_of::<usize>());
+            assert_eq!(
+                size_of::<SharedOption<Mark, str>>(),
+                size_of::<Mark::Pointer<str>>()
+            );
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
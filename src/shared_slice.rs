@@ -0,0 +1,283 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Bound, Deref, Range, RangeBounds},
+    slice,
+};
+
+/// A view of a range of a shared slice of any family.
+///
+/// Views can be narrowed and split without copying, so many of them can share one
+/// allocation, as the pieces of a rope or the rows of a tensor do. Comparisons and
+/// hashing go through the viewed elements. [crate::SharedBytes] builds on it for
+/// bytes.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn rows<R: RefCountFamily>(data: SharedSlice<R, f32>, width: usize) -> Vec<SharedSlice<R, f32>> {
+///     (0..data.len() / width)
+///         .map(|row| data.slice(row * width..(row + 1) * width))
+///         .collect()
+/// }
+/// let data = SharedSlice::<RcMark, _>::from(vec![1.0, 2.0, 3.0, 4.0]);
+/// let rows = rows(data, 2);
+/// assert_eq!(rows[1], [3.0, 4.0][..]);
+/// assert_eq!(rows[1].range(), 2..4);
+/// assert!(std::rc::Rc::ptr_eq(rows[0].pointer(), rows[1].pointer()));
+/// ```
+pub struct SharedSlice<Mark: RefCountFamily, T> {
+    data: Mark::Pointer<[T]>,
+    start: usize,
+    end: usize,
+}
+
+/// Returns the bounds of `range` in a window of `len` elements.
+///
+/// # Panics
+/// If the range is decreasing, or ends past `len`.
+fn window(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("range start overflow"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("range end overflow"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "range start {start} is past its end {end}");
+    assert!(
+        end <= len,
+        "range end {end} is out of bounds of length {len}"
+    );
+    (start, end)
+}
+
+impl<Mark: RefCountFamily, T> SharedSlice<Mark, T> {
+    /// Views the whole of `data`.
+    pub fn from_pointer(data: Mark::Pointer<[T]>) -> Self {
+        let end = data.len();
+        Self {
+            data,
+            start: 0,
+            end,
+        }
+    }
+
+    /// Returns the whole shared slice, ignoring the window.
+    pub fn pointer(&self) -> &Mark::Pointer<[T]> {
+        &self.data
+    }
+
+    /// Returns the viewed elements as a pointer: the shared slice itself if the
+    /// window covers it, a new one with clones of the elements otherwise.
+    pub fn into_pointer(self) -> Mark::Pointer<[T]>
+    where
+        T: Clone,
+    {
+        if (self.start, self.end) == (0, self.data.len()) {
+            self.data
+        } else {
+            Mark::from_iter(self.iter().cloned())
+        }
+    }
+
+    /// Returns the range of the window within the shared slice.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns a view of `range` within this one, sharing the slice.
+    ///
+    /// # Panics
+    /// If the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (start, end) = window(range, self.len());
+        Self {
+            data: self.data.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// Splits off the elements before `at`, which are returned, keeping the rest.
+    ///
+    /// # Panics
+    /// If `at` is greater than the length.
+    pub fn split_to(&mut self, at: usize) -> Self {
+        let head = self.slice(..at);
+        self.start += at;
+        head
+    }
+
+    /// Splits off the elements from `at` on, which are returned, keeping the rest.
+    ///
+    /// # Panics
+    /// If `at` is greater than the length.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let tail = self.slice(at..);
+        self.end = self.start + at;
+        tail
+    }
+
+    /// Shortens the view to `len` elements. Does nothing if it is shorter already.
+    pub fn truncate(&mut self, len: usize) {
+        self.end = self.start + len.min(self.len());
+    }
+
+    /// Returns `true` if both view the same elements of the same slice.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.data, &other.data)
+            && (self.start, self.end) == (other.start, other.end)
+    }
+}
+
+impl<Mark: RefCountFamily, T> Clone for SharedSlice<Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T> Deref for SharedSlice<Mark, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        &self.data[self.start..self.end]
+    }
+}
+
+impl<Mark: RefCountFamily, T> Borrow<[T]> for SharedSlice<Mark, T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<Mark: RefCountFamily, T> AsRef<[T]> for SharedSlice<Mark, T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T> IntoIterator for &'a SharedSlice<Mark, T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<Mark: RefCountFamily, T> Default for SharedSlice<Mark, T> {
+    fn default() -> Self {
+        Self::from_pointer(Mark::new_slice(Vec::new()))
+    }
+}
+
+impl<Mark: RefCountFamily, T: Clone> From<&[T]> for SharedSlice<Mark, T> {
+    fn from(slice: &[T]) -> Self {
+        Self::from_pointer(Mark::from_iter(slice.iter().cloned()))
+    }
+}
+
+impl<Mark: RefCountFamily, T> From<Vec<T>> for SharedSlice<Mark, T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_pointer(Mark::new_slice(vec))
+    }
+}
+
+impl<Mark: RefCountFamily, T> FromIterator<T> for SharedSlice<Mark, T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_pointer(Mark::from_iter(iter))
+    }
+}
+
+impl<Mark: RefCountFamily, T: fmt::Debug> fmt::Debug for SharedSlice<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily, T: PartialEq> PartialEq for SharedSlice<Mark, T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily, T: Eq> Eq for SharedSlice<Mark, T> {}
+
+impl<Mark: RefCountFamily, T: PartialEq> PartialEq<[T]> for SharedSlice<Mark, T> {
+    fn eq(&self, other: &[T]) -> bool {
+        **self == *other
+    }
+}
+
+impl<Mark: RefCountFamily, T: PartialOrd> PartialOrd for SharedSlice<Mark, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, T: Ord> Ord for SharedSlice<Mark, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily, T: Hash> Hash for SharedSlice<Mark, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_shared_slice() {
+        fn actual_test<R: RefCountFamily>() {
+            let full = SharedSlice::<R, String>::from_iter(["a", "b", "c", "d"].map(String::from));
+            let mut middle = full.slice(1..3);
+            assert_eq!(
+                (middle.range(), middle.get(1)),
+                (1..3, Some(&"c".to_owned()))
+            );
+            assert_eq!(middle.iter().map(String::as_str).collect::<String>(), "bc");
+            let c = middle.split_off(1);
+            assert_eq!(
+                (&*middle, &*c),
+                (&["b".to_owned()][..], &["c".to_owned()][..])
+            );
+            assert!(c.ptr_eq(&full.slice(2..3)));
+            assert_eq!(c, SharedSlice::from(&["c".to_owned()][..]));
+            let mut count = 0;
+            for s in &full {
+                count += s.len();
+            }
+            assert_eq!(count, 4);
+
+            let copy = c.into_pointer();
+            assert!(!R::Pointer::ptr_eq(&copy, full.pointer()));
+            assert_eq!(R::Pointer::strong_count(full.pointer()), 2);
+            drop(middle);
+            let whole = full.clone().into_pointer();
+            assert!(R::Pointer::ptr_eq(&whole, full.pointer()));
+            assert!(SharedSlice::<R, i32>::default().is_empty());
+
+            let mut tail = full.slice(1..);
+            tail.truncate(usize::MAX);
+            assert_eq!(tail.range(), 1..4);
+            tail.truncate(1);
+            assert_eq!(tail.range(), 1..2);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,177 @@
+use crate::traits::*;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// A shared string of any family, like `Rc<str>` and `Arc<str>`.
+///
+/// Clones share the string. Comparisons, hashing and formatting go through the
+/// string, and it can be looked up by `&str` in maps and sets.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::collections::HashMap;
+/// fn count<R: RefCountFamily>(words: &[&str]) -> HashMap<SharedStr<R>, usize> {
+///     let mut counts = HashMap::new();
+///     for word in words {
+///         *counts.entry(SharedStr::from(*word)).or_default() += 1;
+///     }
+///     counts
+/// }
+/// let counts = count::<RcMark>(&["a", "b", "a"]);
+/// assert_eq!(counts["a"], 2);
+/// ```
+pub struct SharedStr<Mark: RefCountFamily>(Mark::Pointer<str>);
+
+impl<Mark: RefCountFamily> SharedStr<Mark> {
+    pub fn new(s: &str) -> Self {
+        Self(Mark::new_str(s))
+    }
+
+    pub fn from_pointer(pointer: Mark::Pointer<str>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_pointer(self) -> Mark::Pointer<str> {
+        self.0
+    }
+
+    /// Returns a reference to the wrapped pointer.
+    pub fn pointer(&self) -> &Mark::Pointer<str> {
+        &self.0
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if both point to the same allocation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: RefCountFamily> Clone for SharedStr<Mark> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: RefCountFamily> Deref for SharedStr<Mark> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily> Borrow<str> for SharedStr<Mark> {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily> AsRef<str> for SharedStr<Mark> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<Mark: RefCountFamily> Default for SharedStr<Mark> {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl<Mark: RefCountFamily> From<&str> for SharedStr<Mark> {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl<Mark: RefCountFamily> From<String> for SharedStr<Mark> {
+    fn from(s: String) -> Self {
+        Self(Mark::from_string(s))
+    }
+}
+
+impl<Mark: RefCountFamily> fmt::Debug for SharedStr<Mark> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily> fmt::Display for SharedStr<Mark> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<Mark: RefCountFamily> PartialEq for SharedStr<Mark> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily> Eq for SharedStr<Mark> {}
+
+impl<Mark: RefCountFamily> PartialEq<str> for SharedStr<Mark> {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+
+impl<Mark: RefCountFamily> PartialEq<&str> for SharedStr<Mark> {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl<Mark: RefCountFamily> PartialOrd for SharedStr<Mark> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Mark: RefCountFamily> Ord for SharedStr<Mark> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<Mark: RefCountFamily> Hash for SharedStr<Mark> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_shared_str() {
+        fn actual_test<R: RefCountFamily>() {
+            let a = SharedStr::<R>::from("b");
+            let b = a.clone();
+            assert!(a.ptr_eq(&b));
+            let c = SharedStr::<R>::from("a".to_owned());
+            assert!(c < a);
+            assert_eq!(a, "b");
+            assert_eq!(a, SharedStr::new("b"));
+            assert_eq!((a.len(), a.as_str()), (1, "b"));
+            assert_eq!(format!("{a} {a:?}"), r#"b "b""#);
+            let set: BTreeSet<_> = [a, b, c].into_iter().collect();
+            assert!(set.contains("a"));
+            assert_eq!(set.len(), 2);
+            assert_eq!(&*SharedStr::<R>::default(), "");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
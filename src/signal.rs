@@ -0,0 +1,270 @@
+use crate::{
+    lock::LockFamily,
+    observe::{Subject, Subscription},
+    shareable::*,
+    traits::*,
+};
+use std::fmt;
+
+/// A shared value notifying its subscribers and derived signals each time it is
+/// set.
+///
+/// Derived signals, made with [Signal::map] and [Signal::combine], hold their
+/// sources weakly: they keep their last value once the sources are dropped, and
+/// the sources stop updating them once they are dropped. They are updated
+/// eagerly, in the order they were derived.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let (width, height) = (Signal::<RcMark, u32>::new(2), Signal::new(3));
+/// let area = Signal::combine(&width, &height, |w, h| w * h);
+/// let label = area.map(|a| format!("{a} m²"));
+/// width.set(4);
+/// assert_eq!((area.get(), label.get()), (12, "12 m²".to_owned()));
+/// ```
+pub struct Signal<Mark: LockFamily + DynFamily, T>(Mark::Pointer<SignalNode<Mark, T>>);
+
+struct SignalNode<Mark: LockFamily + DynFamily, T> {
+    value: Mark::Lock<T>,
+    changed: Subject<Mark, T>,
+    /// The subscriptions to the sources of a derived signal, `Send + Sync` for
+    /// thread-safe families.
+    _sources: Vec<Box<Mark::DynAny>>,
+}
+
+type SignalWeakPointer<Mark, T> = <Mark as RefCountFamily>::WeakPointer<SignalNode<Mark, T>>;
+
+impl<Mark: LockFamily + DynFamily, T> Signal<Mark, T> {
+    pub fn new(value: T) -> Self {
+        Self(Mark::new(SignalNode {
+            value: Mark::new_lock(value),
+            changed: Subject::new(),
+            _sources: Vec::new(),
+        }))
+    }
+
+    /// Calls `f` with the current value.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&Mark::read(&self.0.value))
+    }
+
+    /// Returns the number of subscribers and derived signals still alive.
+    pub fn subscriber_count(&self) -> usize {
+        self.0.changed.len()
+    }
+
+    /// Returns `true` if both are handles to the same signal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, T: Clone> Signal<Mark, T> {
+    pub fn get(&self) -> T {
+        self.with(T::clone)
+    }
+
+    /// Replaces the value and notifies the subscribers.
+    pub fn set(&self, value: T) {
+        *Mark::write(&self.0.value) = value.clone();
+        self.0.changed.emit(&value);
+    }
+
+    /// Modifies the value in place and notifies the subscribers.
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let value = {
+            let mut value = Mark::write(&self.0.value);
+            f(&mut value);
+            value.clone()
+        };
+        self.0.changed.emit(&value);
+    }
+}
+
+impl<Mark: LockFamily + DynFamily + 'static, T: 'static> Signal<Mark, T> {
+    /// Calls `f` with each new value until the subscription is dropped.
+    pub fn subscribe<F: Fn(&T) + Shareable<Mark>>(&self, f: F) -> Subscription<Mark, T> {
+        self.0.changed.subscribe(f)
+    }
+
+    /// Returns a signal holding `f` of the value of this one.
+    pub fn map<U, F>(&self, f: F) -> Signal<Mark, U>
+    where
+        U: Clone + Shareable<Mark>,
+        F: Fn(&T) -> U + Shareable<Mark>,
+    {
+        let initial = self.with(&f);
+        Signal::<Mark, U>::derive(initial, |weak| {
+            let state = Derived((weak.clone(), f));
+            let subscription =
+                self.0
+                    .changed
+                    .subscribe_pointer(Mark::from_box(Mark::box_sync_callback(
+                        move |value: &T| {
+                            let (weak, f) = state.get();
+                            if let Some(node) = weak.upgrade() {
+                                Signal::<Mark, U>(node).set(f(value));
+                            }
+                        },
+                    )));
+            vec![Mark::box_sync_any(Derived(subscription))]
+        })
+    }
+
+    /// Returns a signal holding `f` of the values of `a` and `b`.
+    pub fn combine<U, V, F>(a: &Self, b: &Signal<Mark, U>, f: F) -> Signal<Mark, V>
+    where
+        T: Shareable<Mark>,
+        U: Shareable<Mark>,
+        V: Clone + Shareable<Mark>,
+        F: Fn(&T, &U) -> V + Shareable<Mark>,
+    {
+        let initial = a.with(|a| b.with(|b| f(a, b)));
+        let f = Mark::new(f);
+        Signal::<Mark, V>::derive(initial, |weak| {
+            let on_a = Derived((weak.clone(), Mark::Pointer::downgrade(&b.0), f.clone()));
+            let on_a =
+                a.0.changed
+                    .subscribe_pointer(Mark::from_box(Mark::box_sync_callback(move |a: &T| {
+                        let (weak, weak_b, f) = on_a.get();
+                        if let (Some(node), Some(b)) = (weak.upgrade(), weak_b.upgrade()) {
+                            let value = Signal::<Mark, U>(b).with(|b| f(a, b));
+                            Signal::<Mark, V>(node).set(value);
+                        }
+                    })));
+            let on_b = Derived((weak.clone(), Mark::Pointer::downgrade(&a.0), f));
+            let on_b =
+                b.0.changed
+                    .subscribe_pointer(Mark::from_box(Mark::box_sync_callback(move |b: &U| {
+                        let (weak, weak_a, f) = on_b.get();
+                        if let (Some(node), Some(a)) = (weak.upgrade(), weak_a.upgrade()) {
+                            let value = Signal::<Mark, T>(a).with(|a| f(a, b));
+                            Signal::<Mark, V>(node).set(value);
+                        }
+                    })));
+            vec![
+                Mark::box_sync_any(Derived(on_a)),
+                Mark::box_sync_any(Derived(on_b)),
+            ]
+        })
+    }
+}
+
+/// The state of the callbacks updating a derived signal.
+struct Derived<S>(S);
+
+// SAFETY: `Derived` holds pointers to signal nodes of the signal's family,
+// subscriptions to them, and the functions and values given to `map` and
+// `combine`, which are all `Shareable<Mark>`. As `Shareable` is sealed, they are
+// `Send + Sync` when the family is thread-safe, and so are the nodes and the
+// subscriptions. When the family is single-threaded, the callbacks and
+// subscriptions are boxed into trait objects without auto traits, so they never
+// leave their thread.
+unsafe impl<S> Send for Derived<S> {}
+unsafe impl<S> Sync for Derived<S> {}
+
+impl<S> Derived<S> {
+    /// Returns the state, so that closures calling it capture the whole `Derived`.
+    fn get(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, T> Signal<Mark, T> {
+    /// Makes a signal holding `value` and the subscriptions returned by
+    /// `subscribe`, which is given a weak pointer to it.
+    fn derive<F>(value: T, subscribe: F) -> Self
+    where
+        F: FnOnce(&SignalWeakPointer<Mark, T>) -> Vec<Box<Mark::DynAny>>,
+    {
+        Self(Mark::Pointer::new_cyclic(|weak| SignalNode {
+            value: Mark::new_lock(value),
+            changed: Subject::new(),
+            _sources: subscribe(weak),
+        }))
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, T> Clone for Signal<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, T: Default> Default for Signal<Mark, T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<Mark: LockFamily + DynFamily, T: fmt::Debug> fmt::Debug for Signal<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with(|value| f.debug_tuple("Signal").field(value).finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_signal() {
+        fn actual_test<R: LockFamily + DynFamily + 'static>(
+            double: impl Fn(&i32) -> i32 + Shareable<R>,
+            add: impl Fn(&i32, &i32) -> i32 + Shareable<R>,
+            count: impl Fn(&i32) + Shareable<R>,
+            calls: &AtomicUsize,
+        ) where
+            i32: Shareable<R>,
+        {
+            let a = Signal::<R, i32>::new(1);
+            let b = Signal::<R, i32>::new(10);
+            let doubled = a.map(double);
+            let sum = Signal::combine(&doubled, &b, add);
+            assert_eq!(sum.get(), 12);
+            a.set(2);
+            b.update(|b| *b += 10);
+            assert_eq!((doubled.get(), sum.get()), (4, 24));
+            assert_eq!(format!("{sum:?}"), "Signal(24)");
+
+            let subscription = sum.subscribe(count);
+            a.set(3);
+            b.set(0);
+            drop(subscription);
+            a.set(4);
+            assert_eq!((calls.load(Ordering::SeqCst), sum.get()), (2, 8));
+
+            drop(sum);
+            a.set(5);
+            assert_eq!((a.subscriber_count(), b.subscriber_count()), (1, 0));
+            drop(a);
+            assert_eq!(doubled.get(), 10);
+        }
+        fn counter(calls: &Arc<AtomicUsize>) -> impl Fn(&i32) + Send + Sync {
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let calls = Arc::new(AtomicUsize::new(0));
+        actual_test::<RcMark>(|a| a * 2, |d, b| d + b, counter(&calls), &calls);
+        let calls = Arc::new(AtomicUsize::new(0));
+        actual_test::<ArcMark>(|a| a * 2, |d, b| d + b, counter(&calls), &calls);
+    }
+
+    #[test]
+    fn test_signal_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Signal<ArcMark, i32>>();
+        let a = Signal::<ArcMark, i32>::new(1);
+        let doubled = a.map(|a| a * 2);
+        let sum = Signal::combine(&a, &doubled, |a, d| a + d);
+        std::thread::spawn(move || a.set(2)).join().unwrap();
+        assert_eq!((doubled.get(), sum.get()), (4, 6));
+    }
+}
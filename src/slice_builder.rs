@@ -0,0 +1,185 @@
+use crate::traits::*;
+use std::{
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
+
+/// Builds a `Mark::Pointer<[T]>` in place, element by element.
+///
+/// The slice is allocated once with its capacity, and frozen with
+/// [SliceBuilder::finish] without copying when it was filled, where collecting
+/// into a [Vec] first moves every element a second time.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn table<R: UninitConstruct>(len: usize) -> R::Pointer<[u64]> {
+///     let mut builder = SliceBuilder::<R, u64>::with_capacity(len);
+///     builder.push(1);
+///     builder.extend((1..len as u64).map(|i| i * i));
+///     builder.finish()
+/// }
+/// assert_eq!(&*table::<ArcMark>(4), [1, 1, 4, 9]);
+/// ```
+pub struct SliceBuilder<Mark: UninitConstruct, T> {
+    buffer: Mark::Pointer<[MaybeUninit<T>]>,
+    len: usize,
+}
+
+impl<Mark: UninitConstruct, T> SliceBuilder<Mark, T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Mark::new_uninit_slice(capacity),
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    fn slots(&mut self) -> &mut [MaybeUninit<T>] {
+        Mark::Pointer::get_mut(&mut self.buffer).expect("the buffer is never shared")
+    }
+
+    /// Appends `value`, returning it back if the builder is full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        let len = self.len;
+        self.slots()[len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends `value`.
+    ///
+    /// # Panics
+    /// If the builder is full.
+    pub fn push(&mut self, value: T) {
+        if self.try_push(value).is_err() {
+            panic!("SliceBuilder is full at capacity {}", self.capacity());
+        }
+    }
+
+    /// Freezes the elements pushed so far into a shared slice.
+    ///
+    /// This does not copy if the builder is full; otherwise the elements are moved
+    /// to a slice of their exact length.
+    pub fn finish(mut self) -> Mark::Pointer<[T]> {
+        let len = std::mem::replace(&mut self.len, 0);
+        if len == self.capacity() {
+            let buffer = std::mem::replace(&mut self.buffer, Mark::new_uninit_slice(0));
+            // SAFETY: every element was written, and `self` no longer owns them.
+            return unsafe { Mark::assume_init_slice(buffer) };
+        }
+        let slots = self.slots();
+        // SAFETY: the first `len` elements were written, and `self` no longer owns
+        // them, so each is read exactly once.
+        Mark::from_iter(
+            slots[..len]
+                .iter()
+                .map(|slot| unsafe { slot.assume_init_read() }),
+        )
+    }
+}
+
+impl<Mark: UninitConstruct, T> Deref for SliceBuilder<Mark, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first `len` elements were written.
+        unsafe { &*(&self.buffer[..self.len] as *const [MaybeUninit<T>] as *const [T]) }
+    }
+}
+
+impl<Mark: UninitConstruct, T> DerefMut for SliceBuilder<Mark, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        let len = self.len;
+        let slots = &mut self.slots()[..len];
+        // SAFETY: the first `len` elements were written.
+        unsafe { &mut *(slots as *mut [MaybeUninit<T>] as *mut [T]) }
+    }
+}
+
+impl<Mark: UninitConstruct, T> Extend<T> for SliceBuilder<Mark, T> {
+    /// # Panics
+    /// If the builder fills up before `iter` ends.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<Mark: UninitConstruct, T> Drop for SliceBuilder<Mark, T> {
+    fn drop(&mut self) {
+        let len = self.len;
+        for slot in &mut self.slots()[..len] {
+            // SAFETY: the first `len` elements were written, and are dropped once.
+            unsafe { slot.assume_init_drop() };
+        }
+    }
+}
+
+impl<Mark: UninitConstruct, T: fmt::Debug> fmt::Debug for SliceBuilder<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SliceBuilder")
+            .field("elements", &&**self)
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_slice_builder() {
+        fn actual_test<R: UninitConstruct>() {
+            let mut builder = SliceBuilder::<R, String>::with_capacity(3);
+            builder.push("a".to_owned());
+            builder.extend(["b".to_owned()]);
+            builder[1].push('c');
+            assert_eq!(builder[..], ["a", "bc"]);
+            assert!(!builder.is_full());
+            assert_eq!(
+                format!("{builder:?}"),
+                r#"SliceBuilder { elements: ["a", "bc"], capacity: 3 }"#
+            );
+            let partial = builder.finish();
+            assert_eq!(&*partial, ["a", "bc"]);
+
+            let mut builder = SliceBuilder::<R, i32>::with_capacity(2);
+            builder.extend([1, 2]);
+            assert_eq!(builder.try_push(3), Err(3));
+            assert_eq!(&*builder.finish(), [1, 2]);
+            assert!(SliceBuilder::<R, i32>::with_capacity(0).finish().is_empty());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_slice_builder_drop() {
+        let value = Rc::new(0);
+        let mut builder = SliceBuilder::<ArcMark, Rc<i32>>::with_capacity(3);
+        builder.extend([value.clone(), value.clone()]);
+        drop(builder);
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        let mut builder = SliceBuilder::<RcMark, Rc<i32>>::with_capacity(3);
+        builder.push(value.clone());
+        let frozen = builder.finish();
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(frozen);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! Support for [stable_deref_trait](https://docs.rs/stable_deref_trait).
+//!
+//! With the `stable_deref_trait` feature, [Wrap](crate::Wrap) and
+//! [Shared](crate::Shared) implement `CloneStableDeref` when the pointer they wrap
+//! does, as `Rc` and `Arc` do, so they can be used as owners by crates like `ouroboros`, `owning_ref` or
+//! `elsa`. Code generic over the family asks for it with a
+//! `where R::Pointer<T>: CloneStableDeref` bound.
+
+#[cfg(feature = "stable_deref_trait")]
+mod impls {
+    use crate::{traits::*, Shared, Wrap, WrapHooks};
+    use stable_deref_trait::{CloneStableDeref, StableDeref};
+
+    // A Wrap derefs to the value of the pointer it wraps, and so does a Shared, so
+    // they are as stable as that pointer.
+    unsafe impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> StableDeref
+        for Wrap<Mark, H, T, Tag>
+    where
+        Mark::Pointer<T>: StableDeref,
+    {
+    }
+    unsafe impl<Mark: RefCountFamily, H: WrapHooks, T: ?Sized, Tag> CloneStableDeref
+        for Wrap<Mark, H, T, Tag>
+    where
+        Mark::Pointer<T>: CloneStableDeref,
+    {
+    }
+    unsafe impl<Mark: RefCountFamily, T: ?Sized> StableDeref for Shared<Mark, T> where
+        Mark::Pointer<T>: StableDeref
+    {
+    }
+    unsafe impl<Mark: RefCountFamily, T: ?Sized> CloneStableDeref for Shared<Mark, T> where
+        Mark::Pointer<T>: CloneStableDeref
+    {
+    }
+}
+
+#[cfg(all(test, feature = "stable_deref_trait"))]
+mod tests {
+    use crate::{ArcMark, RcMark, RefCountFamily, Shared, WrapHooks, WrapMark};
+    use stable_deref_trait::CloneStableDeref;
+
+    // Keeps a view into its owner, as self-referential crates do.
+    struct Owned<P: CloneStableDeref<Target = str>> {
+        owner: P,
+        first_word: *const str,
+    }
+
+    impl<P: CloneStableDeref<Target = str>> Owned<P> {
+        fn new(owner: P) -> Self {
+            let first_word = owner.split(' ').next().unwrap() as *const str;
+            Self { owner, first_word }
+        }
+
+        fn first_word(&self) -> &str {
+            unsafe { &*self.first_word }
+        }
+    }
+
+    struct NoHooks;
+    impl WrapHooks for NoHooks {}
+
+    #[test]
+    fn test_stable_deref() {
+        fn actual_test<R: RefCountFamily>()
+        where
+            R::Pointer<str>: CloneStableDeref<Target = str>,
+        {
+            let owned = Owned::new(R::new_str("John Doe"));
+            let moved = Box::new(owned);
+            assert_eq!(moved.first_word(), "John");
+            let clone = Owned::new(moved.owner.clone());
+            assert_eq!(clone.first_word, moved.first_word);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        actual_test::<WrapMark<RcMark, NoHooks>>();
+        let shared = Shared::<ArcMark, String>::new("John Doe".to_owned());
+        fn assert_stable<P: CloneStableDeref>(_: &P) {}
+        assert_stable(&shared);
+    }
+}
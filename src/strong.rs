@@ -0,0 +1,143 @@
+//! The part of the abstraction that doesn't involve weak pointers.
+//!
+//! Some pointer types, like [triomphe's Arc](https://docs.rs/triomphe), save the weak
+//! count and can't be a [RefCountFamily]. They implement [StrongFamily] instead, which
+//! every [RefCountFamily] also implements. Code that never downgrades can bound on
+//! [StrongFamily] and accept both.
+//!
+//! [StrongFamily] is not re-exported at the crate root: its functions have the same
+//! names as the [RefCountFamily] ones, which would make calls like `RcMark::new`
+//! ambiguous.
+//!
+//! ```
+//! # use cark_ref_counted::strong::*;
+//! # use cark_ref_counted::RcMark;
+//! struct Foo<S: StrongFamily> {
+//!     name: S::Pointer<str>,
+//! }
+//! let foo = Foo::<RcMark> {
+//!     name: RcMark::new_str("John Doe"),
+//! };
+//! assert_eq!(&*foo.name, "John Doe");
+//! ```
+
+use crate::traits::*;
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+/// A family of reference counted pointers, possibly without weak pointers.
+///
+/// Like [crate::PinFamily], the pointer operations are functions of the family.
+/// See [RefCounted] for what they do.
+pub trait StrongFamily {
+    type Pointer<T: ?Sized>: Deref<Target = T> + Clone + Borrow<T> + AsRef<T> + fmt::Pointer;
+    /// See [RefCountFamily::IS_THREAD_SAFE].
+    const IS_THREAD_SAFE: bool;
+    fn new<T>(value: T) -> Self::Pointer<T>;
+    /// Moves the elements of `vec` into a new shared slice.
+    fn new_slice<T>(vec: Vec<T>) -> Self::Pointer<[T]>;
+    /// Copies `s` into a new shared string.
+    fn new_str(s: &str) -> Self::Pointer<str>;
+    /// Moves the contents of `s` into a new shared string.
+    fn from_string(s: String) -> Self::Pointer<str>;
+    /// Collects the items of `iter` into a new shared slice.
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Self::Pointer<[T]>;
+    fn as_ptr<T: ?Sized>(this: &Self::Pointer<T>) -> *const T;
+    fn ptr_eq<T: ?Sized>(this: &Self::Pointer<T>, other: &Self::Pointer<T>) -> bool;
+    fn strong_count<T: ?Sized>(this: &Self::Pointer<T>) -> usize;
+    fn get_mut<T: ?Sized>(this: &mut Self::Pointer<T>) -> Option<&mut T>;
+    fn make_mut<T: Clone>(this: &mut Self::Pointer<T>) -> &mut T;
+    fn into_raw<T: ?Sized>(this: Self::Pointer<T>) -> *const T;
+    /// # Safety
+    /// see [std::rc::Rc::from_raw]
+    unsafe fn from_raw<T: ?Sized>(ptr: *const T) -> Self::Pointer<T>;
+    fn try_unwrap<T>(this: Self::Pointer<T>) -> Result<T, Self::Pointer<T>>;
+    fn unwrap_or_clone<T: Clone>(this: Self::Pointer<T>) -> T;
+}
+
+impl<Mark: RefCountFamily> StrongFamily for Mark {
+    type Pointer<T: ?Sized> = Mark::Pointer<T>;
+    const IS_THREAD_SAFE: bool = Mark::IS_THREAD_SAFE;
+    fn new<T>(value: T) -> Mark::Pointer<T> {
+        <Mark as RefCountFamily>::new(value)
+    }
+    fn new_slice<T>(vec: Vec<T>) -> Mark::Pointer<[T]> {
+        <Mark as RefCountFamily>::new_slice(vec)
+    }
+    fn new_str(s: &str) -> Mark::Pointer<str> {
+        <Mark as RefCountFamily>::new_str(s)
+    }
+    fn from_string(s: String) -> Mark::Pointer<str> {
+        <Mark as RefCountFamily>::from_string(s)
+    }
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Mark::Pointer<[T]> {
+        <Mark as RefCountFamily>::from_iter(iter)
+    }
+
+    fn as_ptr<T: ?Sized>(this: &Mark::Pointer<T>) -> *const T {
+        Mark::Pointer::as_ptr(this)
+    }
+
+    fn ptr_eq<T: ?Sized>(this: &Mark::Pointer<T>, other: &Mark::Pointer<T>) -> bool {
+        Mark::Pointer::ptr_eq(this, other)
+    }
+
+    fn strong_count<T: ?Sized>(this: &Mark::Pointer<T>) -> usize {
+        Mark::Pointer::strong_count(this)
+    }
+
+    fn get_mut<T: ?Sized>(this: &mut Mark::Pointer<T>) -> Option<&mut T> {
+        Mark::Pointer::get_mut(this)
+    }
+
+    fn make_mut<T: Clone>(this: &mut Mark::Pointer<T>) -> &mut T {
+        Mark::Pointer::make_mut(this)
+    }
+
+    fn into_raw<T: ?Sized>(this: Mark::Pointer<T>) -> *const T {
+        Mark::Pointer::into_raw(this)
+    }
+
+    unsafe fn from_raw<T: ?Sized>(ptr: *const T) -> Mark::Pointer<T> {
+        Mark::Pointer::from_raw(ptr)
+    }
+
+    fn try_unwrap<T>(this: Mark::Pointer<T>) -> Result<T, Mark::Pointer<T>> {
+        Mark::Pointer::try_unwrap(this)
+    }
+
+    fn unwrap_or_clone<T: Clone>(this: Mark::Pointer<T>) -> T {
+        Mark::Pointer::unwrap_or_clone(this)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    pub(crate) fn actual_test<S: StrongFamily>() {
+        let mut a = S::new(vec![1]);
+        let b = a.clone();
+        assert!(S::ptr_eq(&a, &b));
+        assert_eq!(S::strong_count(&a), 2);
+        assert!(S::get_mut(&mut a).is_none());
+        S::make_mut(&mut a).push(2);
+        assert_eq!(S::unwrap_or_clone(b), [1]);
+        let raw = S::into_raw(a);
+        let a = unsafe { S::from_raw(raw) };
+        assert_eq!(S::as_ptr(&a), &*a as *const _);
+        assert_eq!(*a, [1, 2]);
+        let a = S::new(vec![3]);
+        assert_eq!(S::try_unwrap(a).ok().unwrap(), [3]);
+        assert_eq!(&*S::new_str("a"), "a");
+        assert_eq!(&*S::from_string("b".to_owned()), "b");
+        assert_eq!(*S::new_slice(vec![1, 2]), [1, 2]);
+        assert_eq!(*S::from_iter(0..2), [0, 1]);
+    }
+
+    #[test]
+    fn test_strong_family() {
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,252 @@
+//! [SwapCell], a shared slot holding a pointer that can be replaced while readers
+//! keep their own snapshot.
+//!
+//! The slot of each family is given by [SwapFamily]. [RcMark] uses a `RefCell`,
+//! only borrowed for the duration of each operation, and [ArcMark] an `RwLock`.
+//! With the `arc-swap` feature, `ArcSwapMark` is an [ArcMark] using a lock-free
+//! `arc_swap::ArcSwap` instead.
+
+use crate::{traits::*, ArcMark, RcMark};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
+
+/// A [RefCountFamily] with a slot type for [SwapCell].
+pub trait SwapFamily: RefCountFamily {
+    type Slot<T>;
+    fn new_slot<T>(pointer: Self::Pointer<T>) -> Self::Slot<T>;
+    fn slot_load<T>(slot: &Self::Slot<T>) -> Self::Pointer<T>;
+    fn slot_swap<T>(slot: &Self::Slot<T>, pointer: Self::Pointer<T>) -> Self::Pointer<T>;
+    /// Replaces the pointer of the slot with `f` of the current one, calling `f`
+    /// again if the slot changed in the meantime. Returns the replaced pointer.
+    fn slot_rcu<T, F>(slot: &Self::Slot<T>, f: F) -> Self::Pointer<T>
+    where
+        F: FnMut(&Self::Pointer<T>) -> Self::Pointer<T>;
+}
+
+/// A shared slot holding a `Mark::Pointer<T>`, like `arc_swap::ArcSwap` does for
+/// [Arc].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn bump<R: SwapFamily>(config: &SwapCell<R, i32>) -> R::Pointer<i32> {
+///     config.rcu(|old| R::new(**old + 1))
+/// }
+/// let config = SwapCell::<RcMark, _>::new(RcMark::new(1));
+/// let snapshot = config.load();
+/// assert_eq!(*bump(&config), 1);
+/// assert_eq!((*snapshot, *config.load()), (1, 2));
+/// ```
+pub struct SwapCell<Mark: SwapFamily, T>(Mark::Slot<T>);
+
+impl<Mark: SwapFamily, T> SwapCell<Mark, T> {
+    pub fn new(pointer: Mark::Pointer<T>) -> Self {
+        Self(Mark::new_slot(pointer))
+    }
+
+    /// Returns a snapshot of the current pointer.
+    pub fn load(&self) -> Mark::Pointer<T> {
+        Mark::slot_load(&self.0)
+    }
+
+    pub fn store(&self, pointer: Mark::Pointer<T>) {
+        drop(self.swap(pointer));
+    }
+
+    /// Stores `pointer`, returning the previous one.
+    pub fn swap(&self, pointer: Mark::Pointer<T>) -> Mark::Pointer<T> {
+        Mark::slot_swap(&self.0, pointer)
+    }
+
+    /// Read-copy-update: stores `f` of the current pointer, calling `f` again if
+    /// another thread stored a pointer in the meantime. Returns the replaced pointer.
+    pub fn rcu<F>(&self, f: F) -> Mark::Pointer<T>
+    where
+        F: FnMut(&Mark::Pointer<T>) -> Mark::Pointer<T>,
+    {
+        Mark::slot_rcu(&self.0, f)
+    }
+}
+
+impl<Mark: SwapFamily, T: fmt::Debug> fmt::Debug for SwapCell<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SwapCell").field(&*self.load()).finish()
+    }
+}
+
+impl SwapFamily for RcMark {
+    type Slot<T> = RefCell<Rc<T>>;
+
+    fn new_slot<T>(pointer: Rc<T>) -> RefCell<Rc<T>> {
+        RefCell::new(pointer)
+    }
+
+    fn slot_load<T>(slot: &RefCell<Rc<T>>) -> Rc<T> {
+        slot.borrow().clone()
+    }
+
+    fn slot_swap<T>(slot: &RefCell<Rc<T>>, pointer: Rc<T>) -> Rc<T> {
+        slot.replace(pointer)
+    }
+
+    fn slot_rcu<T, F>(slot: &RefCell<Rc<T>>, mut f: F) -> Rc<T>
+    where
+        F: FnMut(&Rc<T>) -> Rc<T>,
+    {
+        // The slot is not borrowed while `f` runs, so `f` may use it, and store
+        // another pointer.
+        let mut old = Self::slot_load(slot);
+        loop {
+            let new = f(&old);
+            let mut current = slot.borrow_mut();
+            if Rc::ptr_eq(&current, &old) {
+                return std::mem::replace(&mut current, new);
+            }
+            old = current.clone();
+        }
+    }
+}
+
+impl SwapFamily for ArcMark {
+    type Slot<T> = std::sync::RwLock<Arc<T>>;
+
+    fn new_slot<T>(pointer: Arc<T>) -> std::sync::RwLock<Arc<T>> {
+        std::sync::RwLock::new(pointer)
+    }
+
+    fn slot_load<T>(slot: &std::sync::RwLock<Arc<T>>) -> Arc<T> {
+        slot.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn slot_swap<T>(slot: &std::sync::RwLock<Arc<T>>, pointer: Arc<T>) -> Arc<T> {
+        let mut current = slot.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut current, pointer)
+    }
+
+    fn slot_rcu<T, F>(slot: &std::sync::RwLock<Arc<T>>, mut f: F) -> Arc<T>
+    where
+        F: FnMut(&Arc<T>) -> Arc<T>,
+    {
+        let mut old = Self::slot_load(slot);
+        loop {
+            let new = f(&old);
+            let mut current = slot.write().unwrap_or_else(|e| e.into_inner());
+            if Arc::ptr_eq(&current, &old) {
+                return std::mem::replace(&mut current, new);
+            }
+            old = current.clone();
+        }
+    }
+}
+
+/// The tag of [ArcSwapMark].
+#[cfg(feature = "arc-swap")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ArcSwapSlot;
+
+/// An [ArcMark] whose [SwapCell] is an `arc_swap::ArcSwap`, so loads never wait
+/// for a writer.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// let config = SwapCell::<ArcSwapMark, _>::new(ArcSwapMark::new(1));
+/// config.rcu(|old| ArcSwapMark::new(**old + 1));
+/// assert_eq!(*config.load(), 2);
+/// ```
+#[cfg(feature = "arc-swap")]
+pub type ArcSwapMark = crate::WrapMark<ArcMark, (), ArcSwapSlot>;
+
+#[cfg(feature = "arc-swap")]
+impl SwapFamily for ArcSwapMark {
+    type Slot<T> = arc_swap::ArcSwap<T>;
+
+    fn new_slot<T>(pointer: Self::Pointer<T>) -> arc_swap::ArcSwap<T> {
+        arc_swap::ArcSwap::new(crate::Wrap::into_pointer(pointer))
+    }
+
+    fn slot_load<T>(slot: &arc_swap::ArcSwap<T>) -> Self::Pointer<T> {
+        crate::Wrap::from_pointer(slot.load_full())
+    }
+
+    fn slot_swap<T>(slot: &arc_swap::ArcSwap<T>, pointer: Self::Pointer<T>) -> Self::Pointer<T> {
+        crate::Wrap::from_pointer(slot.swap(crate::Wrap::into_pointer(pointer)))
+    }
+
+    fn slot_rcu<T, F>(slot: &arc_swap::ArcSwap<T>, mut f: F) -> Self::Pointer<T>
+    where
+        F: FnMut(&Self::Pointer<T>) -> Self::Pointer<T>,
+    {
+        let mut old = Self::slot_load(slot);
+        loop {
+            let new = crate::Wrap::into_pointer(f(&old));
+            let current = slot.compare_and_swap(Self::Pointer::as_ptr(&old), new);
+            if std::ptr::eq(Arc::as_ptr(&current), Self::Pointer::as_ptr(&old)) {
+                return old;
+            }
+            old = crate::Wrap::from_pointer(arc_swap::Guard::into_inner(current));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_cell() {
+        fn actual_test<R: SwapFamily>() {
+            let cell = SwapCell::<R, i32>::new(R::new(1));
+            let snapshot = cell.load();
+            cell.store(R::new(2));
+            assert_eq!((*snapshot, *cell.load()), (1, 2));
+            let old = cell.swap(R::new(3));
+            assert_eq!(*old, 2);
+            let mut calls = 0;
+            let old = cell.rcu(|current| {
+                calls += 1;
+                R::new(**current * 10)
+            });
+            assert_eq!((*old, *cell.load(), calls), (3, 30, 1));
+            assert_eq!(R::Pointer::strong_count(&old), 1);
+            assert_eq!(format!("{cell:?}"), "SwapCell(30)");
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        #[cfg(feature = "arc-swap")]
+        actual_test::<ArcSwapMark>();
+    }
+
+    #[test]
+    fn test_swap_cell_rcu_retries() {
+        // `f` stores another pointer the first time, as another thread could.
+        fn actual_test<R: SwapFamily>() {
+            let cell = SwapCell::<R, i32>::new(R::new(1));
+            let mut calls = 0;
+            let old = cell.rcu(|current| {
+                calls += 1;
+                if calls == 1 {
+                    cell.store(R::new(5));
+                }
+                R::new(**current + 1)
+            });
+            assert_eq!((*old, *cell.load(), calls), (5, 6, 2));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+        #[cfg(feature = "arc-swap")]
+        actual_test::<ArcSwapMark>();
+    }
+
+    #[test]
+    fn test_swap_cell_threads() {
+        let cell = SwapCell::<ArcMark, usize>::new(Arc::new(0));
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        cell.rcu(|old| Arc::new(**old + 1));
+                    }
+                });
+            }
+        });
+        assert_eq!(*cell.load(), 400);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::traits::*;
+#[cfg(feature = "nightly")]
+use std::task::LocalWaker;
+use std::{
+    marker::PhantomData,
+    task::{RawWaker, RawWakerVTable, Waker},
+};
+
+/// A task that can be woken through a shared reference, the family generic
+/// counterpart of [std::task::Wake].
+///
+/// A pointer of a [ThreadSafeFamily] to a `WakeRef` can be turned into a
+/// [Waker] with [into_waker], so executors only need the thread-safe marker
+/// where wakers are built.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// struct Task(AtomicUsize);
+/// impl WakeRef for Task {
+///     fn wake_by_ref(&self) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// fn waker_for<R: ThreadSafeFamily>(task: &R::Pointer<Task>) -> std::task::Waker {
+///     into_waker(task.clone())
+/// }
+/// let task = ArcMark::new(Task(AtomicUsize::new(0)));
+/// let waker = waker_for::<ArcMark>(&task);
+/// waker.wake_by_ref();
+/// waker.wake();
+/// assert_eq!(task.0.load(Ordering::Relaxed), 2);
+/// ```
+pub trait WakeRef: Send + Sync + 'static {
+    fn wake_by_ref(&self);
+}
+
+/// Turns `pointer` into a [Waker] that keeps it alive, and calls
+/// [WakeRef::wake_by_ref] on its value when woken.
+pub fn into_waker<P, W>(pointer: P) -> Waker
+where
+    P: RefCounted<W>,
+    P::Mark: ThreadSafeFamily,
+    W: WakeRef,
+{
+    // SAFETY: `P` is the pointer of its family for `W`.
+    let pointer: <P::Mark as RefCountFamily>::Pointer<W> =
+        unsafe { RefCounted::from_raw(P::into_raw(pointer)) };
+    let raw: *const W = RefCounted::into_raw(P::Mark::into_sync(pointer));
+    // SAFETY: the vtable functions are given the raw pointer of a `SyncPointer<W>`,
+    // which is Send and Sync.
+    unsafe {
+        Waker::from_raw(RawWaker::new(
+            raw.cast(),
+            &WakerVTable::<P::Mark, W>::VTABLE,
+        ))
+    }
+}
+
+struct WakerVTable<Mark, W>(PhantomData<(Mark, W)>);
+
+impl<Mark: ThreadSafeFamily, W: WakeRef> WakerVTable<Mark, W> {
+    const VTABLE: RawWakerVTable =
+        RawWakerVTable::new(Self::clone, Self::wake, Self::wake_by_ref, Self::drop);
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        Mark::SyncPointer::<W>::increment_strong_count(data.cast());
+        RawWaker::new(data, &Self::VTABLE)
+    }
+
+    unsafe fn wake(data: *const ()) {
+        let pointer = Mark::SyncPointer::<W>::from_raw(data.cast());
+        pointer.wake_by_ref();
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*data.cast::<W>()).wake_by_ref();
+    }
+
+    unsafe fn drop(data: *const ()) {
+        Mark::SyncPointer::<W>::decrement_strong_count(data.cast());
+    }
+}
+
+/// A task of a single-threaded executor, woken through a shared reference.
+///
+/// Unlike [WakeRef], it needs neither `Send` nor `Sync`: a pointer of any family,
+/// [crate::RcMark] included, can be turned into a [LocalWaker] with
+/// [into_local_waker], without paying for atomic reference counts.
+#[cfg(feature = "nightly")]
+pub trait LocalWakeRef: 'static {
+    fn wake_by_ref(&self);
+}
+
+/// Turns `pointer` into a [LocalWaker] that keeps it alive, and calls
+/// [LocalWakeRef::wake_by_ref] on its value when woken.
+///
+/// ```
+/// #![feature(local_waker)]
+/// # use cark_ref_counted::*;
+/// # use std::cell::Cell;
+/// struct Task(Cell<usize>);
+/// impl LocalWakeRef for Task {
+///     fn wake_by_ref(&self) {
+///         self.0.set(self.0.get() + 1);
+///     }
+/// }
+/// let task = RcMark::new(Task(Cell::new(0)));
+/// let waker = into_local_waker(task.clone());
+/// waker.wake_by_ref();
+/// waker.wake();
+/// assert_eq!(task.0.get(), 2);
+/// ```
+#[cfg(feature = "nightly")]
+pub fn into_local_waker<P, W>(pointer: P) -> LocalWaker
+where
+    P: RefCounted<W>,
+    W: LocalWakeRef,
+{
+    let raw: *const W = P::into_raw(pointer);
+    // SAFETY: a LocalWaker stays on the thread it was made on, and the vtable
+    // functions are given the raw pointer of a `Mark::Pointer<W>`.
+    unsafe {
+        LocalWaker::from_raw(RawWaker::new(
+            raw.cast(),
+            &LocalWakerVTable::<P::Mark, W>::VTABLE,
+        ))
+    }
+}
+
+#[cfg(feature = "nightly")]
+struct LocalWakerVTable<Mark, W>(PhantomData<(Mark, W)>);
+
+#[cfg(feature = "nightly")]
+impl<Mark: RefCountFamily, W: LocalWakeRef> LocalWakerVTable<Mark, W> {
+    const VTABLE: RawWakerVTable =
+        RawWakerVTable::new(Self::clone, Self::wake, Self::wake_by_ref, Self::drop);
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        Mark::Pointer::<W>::increment_strong_count(data.cast());
+        RawWaker::new(data, &Self::VTABLE)
+    }
+
+    unsafe fn wake(data: *const ()) {
+        let pointer = Mark::Pointer::<W>::from_raw(data.cast());
+        pointer.wake_by_ref();
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*data.cast::<W>()).wake_by_ref();
+    }
+
+    unsafe fn drop(data: *const ()) {
+        Mark::Pointer::<W>::decrement_strong_count(data.cast());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArcMark;
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::atomic::{AtomicUsize, Ordering},
+        task::{Context, Poll},
+    };
+
+    struct Counter(AtomicUsize);
+
+    impl WakeRef for Counter {
+        fn wake_by_ref(&self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_into_waker() {
+        fn actual_test<R: ThreadSafeFamily>() {
+            let counter = R::new(Counter(AtomicUsize::new(0)));
+            let waker = into_waker(counter.clone());
+            assert_eq!(R::Pointer::strong_count(&counter), 2);
+            let clone = waker.clone();
+            assert_eq!(R::Pointer::strong_count(&counter), 3);
+            clone.wake();
+            waker.wake_by_ref();
+            assert_eq!(R::Pointer::strong_count(&counter), 2);
+            let mut future = pin!(std::future::pending::<()>());
+            let poll = future.as_mut().poll(&mut Context::from_waker(&waker));
+            assert_eq!(poll, Poll::Pending);
+            std::thread::spawn(move || waker.wake()).join().unwrap();
+            assert_eq!(R::Pointer::strong_count(&counter), 1);
+            assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+        }
+        actual_test::<ArcMark>();
+    }
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_into_local_waker() {
+        use crate::RcMark;
+        use std::{cell::Cell, task::ContextBuilder};
+
+        struct LocalCounter(Cell<usize>);
+
+        impl LocalWakeRef for LocalCounter {
+            fn wake_by_ref(&self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        fn actual_test<R: RefCountFamily>() {
+            let counter = R::new(LocalCounter(Cell::new(0)));
+            let local = into_local_waker(counter.clone());
+            let clone = local.clone();
+            assert_eq!(R::Pointer::strong_count(&counter), 3);
+            clone.wake();
+            let context = ContextBuilder::from_waker(Waker::noop())
+                .local_waker(&local)
+                .build();
+            context.local_waker().wake_by_ref();
+            drop(local);
+            assert_eq!(R::Pointer::strong_count(&counter), 1);
+            assert_eq!(counter.0.get(), 2);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,386 @@
+//! Families for testing code generic over [RefCountFamily].
+//!
+//! [MockMark] behaves like [RcMark] and logs each pointer operation, so tests can
+//! check how a generic library clones, drops and upgrades its pointers.
+//! [FallibleMark] fails allocations on a [FailSchedule], so tests can exercise
+//! allocation failure paths.
+
+use crate::{adapters::*, traits::*, RcMark};
+use std::{cell::RefCell, collections::HashMap};
+
+/// A family behaving like [RcMark], logging the operations on its pointers.
+///
+/// The log is kept per thread, so tests running in parallel don't see each
+/// other's events. Each allocation gets an id in the order it was created.
+///
+/// ```
+/// # use cark_ref_counted::{*, testing::*};
+/// fn share<R: RefCountFamily>(value: &R::Pointer<i32>, times: usize) -> Vec<R::Pointer<i32>> {
+///     vec![value.clone(); times]
+/// }
+/// MockMark::clear_events();
+/// let value = MockMark::new(1);
+/// drop(share::<MockMark>(&value, 2));
+/// let id = MockMark::id_of(&value).unwrap();
+/// assert_eq!(
+///     MockMark::events_of(id),
+///     [MockEvent::New(id), MockEvent::Clone(id), MockEvent::Clone(id), MockEvent::Drop(id), MockEvent::Drop(id)]
+/// );
+/// assert_eq!(MockMark::counts().strong_alive(), 1);
+/// ```
+pub type MockMark = WrapMark<RcMark, MockHooks>;
+
+/// The strong pointer of [MockMark].
+pub type Mock<T> = Wrap<RcMark, MockHooks, T>;
+
+/// The weak pointer of [MockMark].
+pub type MockWeak<T> = WrapWeak<RcMark, MockHooks, T>;
+
+/// An operation on a [MockMark] pointer, with the id of its allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MockEvent {
+    New(u32),
+    /// Start of a `new_cyclic` construction, ended by the matching
+    /// [MockEvent::New].
+    Cyclic(u32),
+    Clone(u32),
+    Drop(u32),
+    Downgrade(u32),
+    CloneWeak(u32),
+    DropWeak(u32),
+    /// An upgrade attempt, and whether it succeeded.
+    Upgrade(u32, bool),
+}
+
+impl MockEvent {
+    pub fn id(self) -> u32 {
+        match self {
+            MockEvent::New(id)
+            | MockEvent::Cyclic(id)
+            | MockEvent::Clone(id)
+            | MockEvent::Drop(id)
+            | MockEvent::Downgrade(id)
+            | MockEvent::CloneWeak(id)
+            | MockEvent::DropWeak(id)
+            | MockEvent::Upgrade(id, _) => id,
+        }
+    }
+}
+
+/// The number of events of each kind, as returned by [MockMark::counts].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MockCounts {
+    pub new: usize,
+    pub clone: usize,
+    pub drop: usize,
+    pub downgrade: usize,
+    pub clone_weak: usize,
+    pub drop_weak: usize,
+    pub upgrade: usize,
+    pub failed_upgrade: usize,
+}
+
+impl MockCounts {
+    /// Returns the number of strong pointers made and not dropped, since the log
+    /// was last cleared.
+    pub fn strong_alive(&self) -> usize {
+        (self.new + self.clone + self.upgrade).saturating_sub(self.drop)
+    }
+
+    /// Returns the number of weak pointers made and not dropped, not counting the
+    /// ones of `new_cyclic` constructions.
+    pub fn weak_alive(&self) -> usize {
+        (self.downgrade + self.clone_weak).saturating_sub(self.drop_weak)
+    }
+}
+
+#[derive(Default)]
+struct MockLog {
+    ids: HashMap<usize, u32>,
+    next_id: u32,
+    events: Vec<MockEvent>,
+}
+
+thread_local! {
+    static LOG: RefCell<MockLog> = RefCell::default();
+}
+
+/// Registers a new allocation. A reused address gets a fresh id.
+fn log_new(addr: *const (), event: fn(u32) -> MockEvent) {
+    LOG.with_borrow_mut(|log| {
+        let id = log.next_id;
+        log.next_id += 1;
+        log.ids.insert(addr as usize, id);
+        log.events.push(event(id));
+    })
+}
+
+fn log(addr: *const (), event: impl FnOnce(u32) -> MockEvent) {
+    LOG.with_borrow_mut(|log| {
+        if let Some(&id) = log.ids.get(&(addr as usize)) {
+            log.events.push(event(id));
+        }
+    })
+}
+
+/// The [WrapHooks] of [MockMark], appending to the log of the current thread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MockHooks;
+
+impl WrapHooks for MockHooks {
+    fn on_new(addr: *const (), _counts: RefCounts) {
+        log_new(addr, MockEvent::New)
+    }
+    fn on_cyclic_start(addr: *const (), _counts: RefCounts) {
+        log_new(addr, MockEvent::Cyclic)
+    }
+    fn on_cyclic_end(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::New)
+    }
+    fn on_clone(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::Clone)
+    }
+    fn on_drop(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::Drop)
+    }
+    fn on_downgrade(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::Downgrade)
+    }
+    fn on_clone_weak(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::CloneWeak)
+    }
+    fn on_drop_weak(addr: *const (), _counts: RefCounts) {
+        log(addr, MockEvent::DropWeak)
+    }
+    fn on_upgrade(addr: *const (), upgraded: bool, _counts: RefCounts) {
+        log(addr, |id| MockEvent::Upgrade(id, upgraded))
+    }
+}
+
+impl WrapMark<RcMark, MockHooks> {
+    /// Returns the events logged on this thread since the last clearing.
+    pub fn events() -> Vec<MockEvent> {
+        LOG.with_borrow(|log| log.events.clone())
+    }
+
+    /// Returns the events logged on this thread and clears them.
+    pub fn take_events() -> Vec<MockEvent> {
+        LOG.with_borrow_mut(|log| std::mem::take(&mut log.events))
+    }
+
+    /// Clears the events logged on this thread. Ids are not reused.
+    pub fn clear_events() {
+        LOG.with_borrow_mut(|log| log.events.clear())
+    }
+
+    /// Returns the events logged on this thread for the allocation `id`.
+    pub fn events_of(id: u32) -> Vec<MockEvent> {
+        LOG.with_borrow(|log| {
+            log.events
+                .iter()
+                .copied()
+                .filter(|event| event.id() == id)
+                .collect()
+        })
+    }
+
+    /// Returns the id of the allocation of `pointer`, if it was made on this
+    /// thread.
+    pub fn id_of<T: ?Sized>(pointer: &Mock<T>) -> Option<u32> {
+        let addr = Mock::as_ptr(pointer) as *const () as usize;
+        LOG.with_borrow(|log| log.ids.get(&addr).copied())
+    }
+
+    /// Counts the events logged on this thread by kind.
+    pub fn counts() -> MockCounts {
+        let mut counts = MockCounts::default();
+        for event in Self::events() {
+            match event {
+                MockEvent::New(_) => counts.new += 1,
+                MockEvent::Cyclic(_) => {}
+                MockEvent::Clone(_) => counts.clone += 1,
+                MockEvent::Drop(_) => counts.drop += 1,
+                MockEvent::Downgrade(_) => counts.downgrade += 1,
+                MockEvent::CloneWeak(_) => counts.clone_weak += 1,
+                MockEvent::DropWeak(_) => counts.drop_weak += 1,
+                MockEvent::Upgrade(_, true) => counts.upgrade += 1,
+                MockEvent::Upgrade(_, false) => counts.failed_upgrade += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// A family behaving like `Mark`, whose allocations fail on the [FailSchedule] set
+/// for the current thread.
+///
+/// Failed allocations make [TryNewFamily::try_new] return an error, and the other
+/// constructors panic. The schedule and its counters are shared by all the
+/// `FallibleMark`s of a thread.
+///
+/// ```
+/// # use cark_ref_counted::{*, testing::*};
+/// fn load<R: TryNewFamily>(lines: &[&str]) -> Option<Vec<R::Pointer<String>>> {
+///     lines.iter().map(|line| R::try_new(line.to_string()).ok()).collect()
+/// }
+/// FallibleMark::<RcMark>::set_schedule(FailSchedule::EveryNth(3));
+/// assert!(load::<FallibleMark<RcMark>>(&["a", "b"]).is_some());
+/// assert!(load::<FallibleMark<RcMark>>(&["c", "d"]).is_none());
+/// assert_eq!(FallibleMark::<RcMark>::failures(), 1);
+/// ```
+pub type FallibleMark<Mark> = WrapMark<Mark, FallibleHooks>;
+
+/// Which allocations of a [FallibleMark] fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailSchedule {
+    #[default]
+    Never,
+    /// The `n`th allocation fails, then the `2n`th, and so on.
+    EveryNth(usize),
+    /// The allocations after the first `n` fail.
+    AfterCount(usize),
+    /// The allocations taking the total size of the successful ones past `n`
+    /// bytes fail.
+    AfterBytes(usize),
+}
+
+#[derive(Default)]
+struct FallibleState {
+    schedule: FailSchedule,
+    attempts: usize,
+    bytes: usize,
+    failures: usize,
+}
+
+thread_local! {
+    static FALLIBLE: RefCell<FallibleState> = RefCell::default();
+}
+
+/// The [WrapHooks] of [FallibleMark], following the schedule of the current
+/// thread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FallibleHooks;
+
+impl WrapHooks for FallibleHooks {
+    fn on_alloc(size: usize) -> bool {
+        FALLIBLE.with_borrow_mut(|state| {
+            state.attempts += 1;
+            let fails = match state.schedule {
+                FailSchedule::Never => false,
+                FailSchedule::EveryNth(n) => n != 0 && state.attempts % n == 0,
+                FailSchedule::AfterCount(n) => state.attempts > n,
+                FailSchedule::AfterBytes(n) => state.bytes + size > n,
+            };
+            if fails {
+                state.failures += 1;
+            } else {
+                state.bytes += size;
+            }
+            !fails
+        })
+    }
+}
+
+impl<Mark: RefCountFamily> WrapMark<Mark, FallibleHooks> {
+    /// Sets the schedule of the current thread, and resets its counters.
+    pub fn set_schedule(schedule: FailSchedule) {
+        FALLIBLE.set(FallibleState {
+            schedule,
+            ..FallibleState::default()
+        })
+    }
+
+    pub fn schedule() -> FailSchedule {
+        FALLIBLE.with_borrow(|state| state.schedule)
+    }
+
+    /// Returns the number of allocations attempted on this thread, failed or not.
+    pub fn allocations() -> usize {
+        FALLIBLE.with_borrow(|state| state.attempts)
+    }
+
+    /// Returns the total size of the successful allocations on this thread.
+    pub fn allocated_bytes() -> usize {
+        FALLIBLE.with_borrow(|state| state.bytes)
+    }
+
+    /// Returns the number of failed allocations on this thread.
+    pub fn failures() -> usize {
+        FALLIBLE.with_borrow(|state| state.failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, SharedCell};
+
+    #[test]
+    fn test_mock_mark() {
+        MockMark::clear_events();
+        let a = MockMark::new(1);
+        let id = MockMark::id_of(&a).unwrap();
+        let weak = Mock::downgrade(&a);
+        let b = weak.upgrade().unwrap();
+        drop((a, b));
+        assert!(weak.clone().upgrade().is_none());
+        drop(weak);
+        assert_eq!(
+            MockMark::take_events(),
+            [
+                MockEvent::New(id),
+                MockEvent::Downgrade(id),
+                MockEvent::Upgrade(id, true),
+                MockEvent::Drop(id),
+                MockEvent::Drop(id),
+                MockEvent::CloneWeak(id),
+                MockEvent::Upgrade(id, false),
+                MockEvent::DropWeak(id),
+                MockEvent::DropWeak(id),
+            ]
+        );
+
+        let cell = SharedCell::<MockMark, _>::new(vec![1]);
+        cell.write().push(2);
+        let other = cell.clone();
+        assert_eq!(*other.read(), [1, 2]);
+        let counts = MockMark::counts();
+        assert_eq!((counts.new, counts.clone, counts.strong_alive()), (1, 1, 2));
+        drop((cell, other));
+        assert_eq!(
+            (
+                MockMark::counts().strong_alive(),
+                MockMark::counts().weak_alive()
+            ),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_fallible_mark() {
+        fn actual_test<R: TryNewFamily>() {
+            type F<R> = FallibleMark<R>;
+            F::<R>::set_schedule(FailSchedule::AfterBytes(16));
+            assert!(F::<R>::try_new(1u64).is_ok() && F::<R>::try_new(2u64).is_ok());
+            assert_eq!(F::<R>::try_new(3u64).err(), Some(3));
+            assert!(F::<R>::try_new(()).is_ok());
+            assert_eq!(
+                (
+                    F::<R>::allocations(),
+                    F::<R>::allocated_bytes(),
+                    F::<R>::failures()
+                ),
+                (4, 16, 1)
+            );
+
+            F::<R>::set_schedule(FailSchedule::AfterCount(1));
+            let _ = F::<R>::new_str("ok");
+            let failed = std::panic::catch_unwind(|| F::<R>::new_slice(vec![1, 2]));
+            assert!(failed.is_err());
+            assert_eq!(F::<R>::schedule(), FailSchedule::AfterCount(1));
+            F::<R>::set_schedule(FailSchedule::Never);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
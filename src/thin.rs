@@ -0,0 +1,98 @@
+use crate::traits::*;
+
+/// A family of one-word pointers to a header followed by a slice.
+///
+/// Pointers to `[T]` are two words wide, the length being stored in the handle.
+/// A thin pointer stores the length with the header instead, so slice-heavy
+/// structures can keep their handles small.
+///
+/// Every [RefCountFamily] implements this with `Mark::Pointer<(H, Box<[T]>)>`: the
+/// handle is one word, but the slice lives in a second allocation. With the
+/// `triomphe` feature, [crate::TriompheMark] uses `triomphe::ThinArc`, which keeps
+/// header, length and slice in a single allocation.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Row<F: ThinFamily> {
+///     cells: F::ThinPointer<&'static str, u32>,
+/// }
+/// let row = Row::<RcMark> {
+///     cells: RcMark::from_header_and_iter("row", [1, 2, 3].into_iter()),
+/// };
+/// assert_eq!(*RcMark::header(&row.cells), "row");
+/// assert_eq!(RcMark::slice(&row.cells), [1, 2, 3]);
+/// assert_eq!(std::mem::size_of_val(&row), std::mem::size_of::<usize>());
+/// ```
+pub trait ThinFamily {
+    type ThinPointer<H, T>: Clone;
+    fn from_header_and_iter<H, T, I: ExactSizeIterator<Item = T>>(
+        header: H,
+        items: I,
+    ) -> Self::ThinPointer<H, T>;
+    /// Moves the elements of `items` into a new thin pointer.
+    fn from_header_and_vec<H, T>(header: H, items: Vec<T>) -> Self::ThinPointer<H, T> {
+        Self::from_header_and_iter(header, items.into_iter())
+    }
+    fn header<'a, H: 'a, T: 'a>(this: &'a Self::ThinPointer<H, T>) -> &'a H;
+    fn slice<'a, H: 'a, T: 'a>(this: &'a Self::ThinPointer<H, T>) -> &'a [T];
+    /// Returns `true` if both point to the same allocation.
+    fn thin_ptr_eq<H, T>(this: &Self::ThinPointer<H, T>, other: &Self::ThinPointer<H, T>) -> bool;
+    fn thin_strong_count<H, T>(this: &Self::ThinPointer<H, T>) -> usize;
+}
+
+impl<Mark: RefCountFamily> ThinFamily for Mark {
+    type ThinPointer<H, T> = Mark::Pointer<(H, Box<[T]>)>;
+    fn from_header_and_iter<H, T, I: ExactSizeIterator<Item = T>>(
+        header: H,
+        items: I,
+    ) -> Mark::Pointer<(H, Box<[T]>)> {
+        Mark::new((header, items.collect()))
+    }
+
+    fn header<'a, H: 'a, T: 'a>(this: &'a Mark::Pointer<(H, Box<[T]>)>) -> &'a H {
+        &this.0
+    }
+
+    fn slice<'a, H: 'a, T: 'a>(this: &'a Mark::Pointer<(H, Box<[T]>)>) -> &'a [T] {
+        &this.1
+    }
+
+    fn thin_ptr_eq<H, T>(
+        this: &Mark::Pointer<(H, Box<[T]>)>,
+        other: &Mark::Pointer<(H, Box<[T]>)>,
+    ) -> bool {
+        Mark::Pointer::ptr_eq(this, other)
+    }
+
+    fn thin_strong_count<H, T>(this: &Mark::Pointer<(H, Box<[T]>)>) -> usize {
+        Mark::Pointer::strong_count(this)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+    use std::mem::size_of;
+
+    pub(crate) fn actual_test<F: ThinFamily>() {
+        let a = F::from_header_and_iter(7u8, (0..3).map(|i| i * 10));
+        assert_eq!(size_of::<F::ThinPointer<u8, u32>>(), size_of::<usize>());
+        let b = a.clone();
+        assert!(F::thin_ptr_eq(&a, &b));
+        assert_eq!(F::thin_strong_count(&a), 2);
+        assert_eq!((*F::header(&b), F::slice(&b)), (7, &[0, 10, 20][..]));
+        let c = F::from_header_and_vec("empty", Vec::<String>::new());
+        assert!(F::slice(&c).is_empty());
+        assert!(!F::thin_ptr_eq(
+            &a,
+            &F::from_header_and_vec(7, vec![0, 10, 20])
+        ));
+    }
+
+    #[test]
+    fn test_thin_family() {
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
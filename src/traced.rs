@@ -0,0 +1,170 @@
+use crate::adapters::*;
+
+/// A [WrapMark] decorator emitting a [tracing] event for each pointer operation
+/// done through it.
+///
+/// `TracedMark<Mark>` behaves like `Mark`. Events are emitted at the `TRACE` level
+/// with the `cark_ref_counted::traced` target, and carry the address of the
+/// allocation with its [RefCounts] after the operation; allocations also carry
+/// the type of their value. Code generic over the family becomes traceable by
+/// swapping its mark for a `TracedMark`.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn share<R: RefCountFamily>(value: String) -> (R::Pointer<String>, R::Pointer<String>) {
+///     let a = R::new(value);
+///     (a.clone(), a)
+/// }
+/// // Emits "new", "clone", then two "drop" events.
+/// let (a, b) = share::<TracedMark<ArcMark>>("traced".to_owned());
+/// assert_eq!(*a, *b);
+/// ```
+pub type TracedMark<Mark> = WrapMark<Mark, TraceHooks>;
+
+/// The strong pointer of [TracedMark].
+pub type Traced<Mark, T> = Wrap<Mark, TraceHooks, T>;
+
+/// The weak pointer of [TracedMark].
+pub type TracedWeak<Mark, T> = WrapWeak<Mark, TraceHooks, T>;
+
+/// Emits `event` with the counts of the allocation, unless no subscriber wants it.
+fn trace(addr: *const (), event: &'static str, counts: RefCounts) {
+    if tracing::enabled!(target: "cark_ref_counted::traced", tracing::Level::TRACE) {
+        let RefCounts { strong, weak } = counts;
+        tracing::trace!(target: "cark_ref_counted::traced", addr = ?addr, strong, weak, "{event}");
+    }
+}
+
+/// The [WrapHooks] of [TracedMark].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TraceHooks;
+
+impl WrapHooks for TraceHooks {
+    fn on_type_name(addr: *const (), type_name: &'static str, counts: RefCounts) {
+        // Emitted here rather than in on_new and on_cyclic_start, to carry the type.
+        if tracing::enabled!(target: "cark_ref_counted::traced", tracing::Level::TRACE) {
+            let RefCounts { strong, weak } = counts;
+            let event = if strong == 0 { "new_cyclic" } else { "new" };
+            tracing::trace!(
+                target: "cark_ref_counted::traced",
+                addr = ?addr,
+                strong,
+                weak,
+                type_name,
+                "{event}"
+            );
+        }
+    }
+    fn on_cyclic_end(addr: *const (), counts: RefCounts) {
+        trace(addr, "cyclic_end", counts)
+    }
+    fn on_clone(addr: *const (), counts: RefCounts) {
+        trace(addr, "clone", counts)
+    }
+    fn on_drop(addr: *const (), counts: RefCounts) {
+        trace(addr, "drop", counts)
+    }
+    fn on_downgrade(addr: *const (), counts: RefCounts) {
+        trace(addr, "downgrade", counts)
+    }
+    fn on_clone_weak(addr: *const (), counts: RefCounts) {
+        trace(addr, "clone_weak", counts)
+    }
+    fn on_drop_weak(addr: *const (), counts: RefCounts) {
+        trace(addr, "drop_weak", counts)
+    }
+    fn on_upgrade(addr: *const (), upgraded: bool, counts: RefCounts) {
+        let event = if upgraded {
+            "upgrade"
+        } else {
+            "upgrade_failed"
+        };
+        trace(addr, event, counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{traits::*, ArcMark, RcMark};
+    use std::{
+        fmt,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Collects the events as "message strong weak".
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<String>>>);
+
+    #[derive(Default)]
+    struct Fields(String, u64, u64);
+
+    impl Visit for Fields {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            match field.name() {
+                "strong" => self.1 = value,
+                "weak" => self.2 = value,
+                _ => {}
+            }
+        }
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "cark_ref_counted::traced"
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let Fields(message, strong, weak) = fields;
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{message} {strong} {weak}"));
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_traced() {
+        fn actual_test<Mark: RefCountFamily>() {
+            let collector = Collector::default();
+            tracing::subscriber::with_default(collector.clone(), || {
+                let a = TracedMark::<Mark>::new(1);
+                let b = a.clone();
+                let weak = Traced::downgrade(&a);
+                drop((a, b));
+                assert!(weak.upgrade().is_none());
+            });
+            assert_eq!(
+                *collector.0.lock().unwrap(),
+                [
+                    "new 1 0",
+                    "clone 2 0",
+                    "downgrade 2 1",
+                    "drop 1 1",
+                    "drop 0 1",
+                    "upgrade_failed 0 0",
+                    "drop_weak 0 0",
+                ]
+            );
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -1,4 +1,10 @@
-use std::{ops::Deref, pin::Pin};
+use std::{
+    borrow::Borrow,
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+};
 
 /// The trait used to abstract over our concrete pointer types.
 ///
@@ -23,13 +29,30 @@ use std::{ops::Deref, pin::Pin};
 /// let foo = Foo::<RcMark>::new("John Doe");
 /// assert_eq!(foo.name(), "John Doe");
 /// ```
+///
+/// The marks implement the standard traits, so derives work on structs holding
+/// `R::Pointer<T>`: the derived impls require `R::Pointer<T>` to implement the
+/// trait, which both [std::rc::Rc] and [std::sync::Arc] do when `T` does.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// struct Foo<R: RefCountFamily> {
+///     name: R::Pointer<str>,
+/// }
+/// let foo = Foo::<ArcMark> { name: ArcMark::new_str("John Doe") };
+/// assert_eq!(foo, foo.clone());
+/// assert_eq!(format!("{foo:?}"), r#"Foo { name: "John Doe" }"#);
+/// ```
 pub trait RefCountFamily {
     type Pointer<T: ?Sized>: RefCounted<
-        T,
-        Mark = Self,
-        //        WeakMark = Self::WeakMark<T>,
-        WeakPointer = Self::WeakPointer<T>,
-    >;
+            T,
+            Mark = Self,
+            //        WeakMark = Self::WeakMark<T>,
+            WeakPointer = Self::WeakPointer<T>,
+        > + Borrow<T>
+        + AsRef<T>
+        + fmt::Pointer;
     type WeakPointer<T: ?Sized>: WeakPointer<
         T,
         //        Mark = Self::WeakMark<T>,
@@ -41,7 +64,188 @@ pub trait RefCountFamily {
     //     StrongPointer<T> = Self::Pointer<T>,
     //     Pointer<T> = Self::WeakPointer<T>,
     // >;
+    /// Whether the pointers of this family use atomic counts, and can be shared
+    /// across threads when `T` can.
+    const IS_THREAD_SAFE: bool;
     fn new<T>(value: T) -> Self::Pointer<T>;
+    /// Creates a weak pointer that never upgrades.
+    fn new_weak<T>() -> Self::WeakPointer<T> {
+        Self::WeakPointer::new()
+    }
+    /// Moves a boxed value into a new pointer. Unlike [RefCountFamily::new],
+    /// this works for unsized values, so a `Box<dyn Trait>` can become a
+    /// `Pointer<dyn Trait>` on stable.
+    fn from_box<T: ?Sized>(value: Box<T>) -> Self::Pointer<T>;
+    /// Moves the elements of `vec` into a new shared slice.
+    fn new_slice<T>(vec: Vec<T>) -> Self::Pointer<[T]>;
+    /// Clones the elements of `slice` into a new shared slice.
+    fn new_slice_clone<T: Clone>(slice: &[T]) -> Self::Pointer<[T]>;
+    /// Copies `s` into a new shared string.
+    fn new_str(s: &str) -> Self::Pointer<str>;
+    /// Moves the contents of `s` into a new shared string.
+    fn from_string(s: String) -> Self::Pointer<str>;
+    /// Collects the items of `iter` into a new shared slice.
+    fn from_iter<T, I: IntoIterator<Item = T>>(iter: I) -> Self::Pointer<[T]>;
+    /// Moves `array` into a new pointer, keeping its length in the type. Use
+    /// [RefCountFamily::unsize_array] to turn the result into a shared slice.
+    ///
+    /// This is [RefCountFamily::new] for arrays: it never panics, and aborts on
+    /// allocation failure like the other constructors.
+    fn new_array<T, const N: usize>(array: [T; N]) -> Self::Pointer<[T; N]> {
+        Self::new(array)
+    }
+    /// Converts a pointer to an array into a pointer to a slice, without copying.
+    fn unsize_array<T, const N: usize>(this: Self::Pointer<[T; N]>) -> Self::Pointer<[T]>;
+}
+
+/// Construction of pointers to uninitialized memory, to be filled in place.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn squares<R: UninitConstruct>(len: usize) -> R::Pointer<[u64]> {
+///     let mut buffer = R::new_uninit_slice(len);
+///     let slots = R::Pointer::get_mut(&mut buffer).unwrap();
+///     for (i, slot) in slots.iter_mut().enumerate() {
+///         slot.write((i * i) as u64);
+///     }
+///     // SAFETY: every element was written above.
+///     unsafe { R::assume_init_slice(buffer) }
+/// }
+/// assert_eq!(&*squares::<ArcMark>(4), [0, 1, 4, 9]);
+/// ```
+pub trait UninitConstruct: RefCountFamily {
+    fn new_uninit<T>() -> Self::Pointer<MaybeUninit<T>>;
+    fn new_uninit_slice<T>(len: usize) -> Self::Pointer<[MaybeUninit<T>]>;
+    /// Like [UninitConstruct::new_uninit], with the memory filled with `0` bytes.
+    fn new_zeroed<T>() -> Self::Pointer<MaybeUninit<T>>;
+    /// Like [UninitConstruct::new_uninit_slice], with the memory filled with `0` bytes.
+    fn new_zeroed_slice<T>(len: usize) -> Self::Pointer<[MaybeUninit<T>]>;
+    /// # Safety
+    /// see [std::rc::Rc::assume_init]
+    unsafe fn assume_init<T>(this: Self::Pointer<MaybeUninit<T>>) -> Self::Pointer<T>;
+    /// # Safety
+    /// see [std::rc::Rc::assume_init]
+    unsafe fn assume_init_slice<T>(this: Self::Pointer<[MaybeUninit<T>]>) -> Self::Pointer<[T]>;
+}
+
+/// A [RefCountFamily] whose allocations can report failure instead of aborting.
+///
+/// [crate::RcMark] and [crate::ArcMark] abort on allocation failure like the
+/// standard library, so their `try_new` always succeeds. Adapters such as
+/// `testing::FallibleMark` fail on purpose, to exercise the failure paths of
+/// generic code.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn boxed_all<R: TryNewFamily>(values: Vec<u32>) -> Result<Vec<R::Pointer<u32>>, u32> {
+///     values.into_iter().map(R::try_new).collect()
+/// }
+/// assert_eq!(boxed_all::<RcMark>(vec![1, 2]).unwrap().len(), 2);
+/// ```
+pub trait TryNewFamily: RefCountFamily {
+    /// Returns `value` back if the allocation fails.
+    fn try_new<T>(value: T) -> Result<Self::Pointer<T>, T>;
+}
+
+/// A [RefCountFamily] whose cyclic constructor may fail.
+///
+/// Like [RefCounted::new_cyclic], with a data function returning a [Result]. On
+/// failure, the allocation is freed without ever having a strong pointer, so the
+/// weak pointers handed out by `data_fn` never upgrade, even from other threads.
+///
+/// [crate::RcMark] implements it. [crate::ArcMark] only does with the `nightly`
+/// feature: on stable, std can't free the allocation of a failed
+/// [std::sync::Arc::new_cyclic] without unwinding out of it.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// struct Node<R: RefCountFamily> {
+///     me: R::WeakPointer<Node<R>>,
+///     value: u32,
+/// }
+/// fn parse<R: TryCyclicFamily>(s: &str) -> Result<R::Pointer<Node<R>>, std::num::ParseIntError> {
+///     R::try_new_cyclic(|me| Ok(Node { me: me.clone(), value: s.parse()? }))
+/// }
+/// assert_eq!(parse::<RcMark>("1").unwrap().value, 1);
+/// assert!(parse::<RcMark>("one").is_err());
+/// ```
+pub trait TryCyclicFamily: RefCountFamily {
+    fn try_new_cyclic<T, F, E>(data_fn: F) -> Result<Self::Pointer<T>, E>
+    where
+        F: FnOnce(&Self::WeakPointer<T>) -> Result<T, E>;
+}
+
+/// A [RefCountFamily] whose pointers can be shared across threads when `T` can.
+///
+/// The bound can't be put on [RefCountFamily::Pointer] for every `T`, so the
+/// thread safe view of a pointer gets its own associated type, along with free
+/// conversions to and from it. Only [crate::ArcMark] implements it.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn sum_in_thread<R: ThreadSafeFamily>(values: R::Pointer<Vec<i32>>) -> i32 {
+///     let values = R::into_sync(values);
+///     std::thread::spawn(move || values.iter().sum()).join().unwrap()
+/// }
+/// assert_eq!(sum_in_thread::<ArcMark>(ArcMark::new(vec![1, 2, 3])), 6);
+/// ```
+pub trait ThreadSafeFamily: RefCountFamily + Send + Sync + 'static {
+    type SyncPointer<T: ?Sized + Send + Sync>: RefCounted<T, Mark = Self> + Send + Sync;
+    fn into_sync<T: ?Sized + Send + Sync>(pointer: Self::Pointer<T>) -> Self::SyncPointer<T>;
+    fn from_sync<T: ?Sized + Send + Sync>(pointer: Self::SyncPointer<T>) -> Self::Pointer<T>;
+}
+
+/// A [RefCountFamily] with a uniquely owned pointer, to build a value in place
+/// before freezing it into a shared pointer.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn build<R: UniqueFamily>() -> R::Pointer<Vec<i32>> {
+///     let mut unique = R::new_unique(Vec::new());
+///     unique.extend([1, 2, 3]);
+///     UniquePointer::into_shared(unique)
+/// }
+/// assert_eq!(*build::<RcMark>(), [1, 2, 3]);
+/// ```
+pub trait UniqueFamily: RefCountFamily {
+    type UniquePointer<T>: UniquePointer<T, Mark = Self>;
+    fn new_unique<T>(value: T) -> Self::UniquePointer<T>;
+}
+
+/// A uniquely owned pointer of a [UniqueFamily]. See [UniqueFamily].
+pub trait UniquePointer<T>: DerefMut<Target = T> {
+    type Mark: UniqueFamily<UniquePointer<T> = Self>;
+    /// Freezes the pointer into a shared one.
+    fn into_shared(this: Self) -> <Self::Mark as RefCountFamily>::Pointer<T>;
+}
+
+/// A [UniqueFamily] whose unique pointers hand out weak pointers before being
+/// frozen.
+///
+/// [crate::RcMark] and [crate::ArcMark] implement it with the `nightly` feature,
+/// with the standard `UniqueRc` and `UniqueArc`.
+pub trait UniqueWeakFamily: UniqueFamily {
+    /// Creates a weak pointer, that only upgrades once `this` has been frozen
+    /// with [UniquePointer::into_shared].
+    fn downgrade_unique<T>(this: &Self::UniquePointer<T>) -> Self::WeakPointer<T>;
+}
+
+/// Construction of pointers to unsized values by copying from a reference.
+///
+/// [crate::RcMark] and [crate::ArcMark] implement it for every `U` their pointer
+/// can be built from with `From<&U>`: `str`, `[T]`, `OsStr`, `CStr`, `Path`...
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::path::Path;
+/// fn share_path<R: FromUnsized<Path>>(path: &Path) -> R::Pointer<Path> {
+///     R::from_unsized(path)
+/// }
+/// let path = share_path::<ArcMark>(Path::new("/tmp"));
+/// assert_eq!(path.to_str(), Some("/tmp"));
+/// ```
+pub trait FromUnsized<U: ?Sized>: RefCountFamily {
+    fn from_unsized(value: &U) -> Self::Pointer<U>;
 }
 
 pub trait RefCounted<T: ?Sized>: Deref<Target = T> + Clone {
@@ -64,7 +268,18 @@ pub trait RefCounted<T: ?Sized>: Deref<Target = T> + Clone {
     fn new<U>(value: U) -> <Self::Mark as RefCountFamily>::Pointer<U> {
         Self::Mark::new(value)
     }
+    fn from_box<U: ?Sized>(value: Box<U>) -> <Self::Mark as RefCountFamily>::Pointer<U> {
+        Self::Mark::from_box(value)
+    }
     fn as_ptr(this: &Self) -> *const T;
+    /// Returns `true` if both pointers point to the same allocation.
+    /// See [std::rc::Rc::ptr_eq].
+    fn ptr_eq(this: &Self, other: &Self) -> bool;
+    /// Returns `true` if both pointers have the same data address,
+    /// ignoring any fat pointer metadata such as vtables.
+    fn addr_eq(this: &Self, other: &Self) -> bool {
+        std::ptr::addr_eq(Self::as_ptr(this), Self::as_ptr(other))
+    }
     fn downgrade(this: &Self) -> Self::WeakPointer;
     fn make_mut(this: &mut Self) -> &mut T
     where
@@ -81,7 +296,38 @@ pub trait RefCounted<T: ?Sized>: Deref<Target = T> + Clone {
     unsafe fn from_raw(ptr: *const T) -> Self;
     fn strong_count(this: &Self) -> usize;
     fn weak_count(this: &Self) -> usize;
+    /// Drops `this`, returning the strong count that remains after the drop.
+    ///
+    /// A result of 0 means `this` was the last strong pointer and the value has
+    /// been dropped. It is exact: like [RefCounted::into_inner], of several threads
+    /// releasing the last pointers concurrently, exactly one gets 0.
+    ///
+    /// With [std::sync::Arc], other threads may clone or drop their own pointers
+    /// at any time, so a non-zero result can be stale by the time it is returned.
+    fn release(this: Self) -> usize
+    where
+        T: Sized,
+    {
+        let strong = Self::strong_count(&this);
+        match Self::into_inner(this) {
+            Some(value) => {
+                drop(value);
+                0
+            }
+            // Another thread may have released the last pointer meanwhile, but it
+            // is the one getting 0.
+            None => (strong - 1).max(1),
+        }
+    }
     fn get_mut(this: &mut Self) -> Option<&mut T>;
+    /// Returns a mutable reference to the value, without checking that no other
+    /// pointer to it exists. With the `nightly` feature, this calls the standard
+    /// `get_mut_unchecked`.
+    ///
+    /// # Safety
+    /// No other pointer to the same allocation may be dereferenced while the
+    /// returned reference is alive, see `std::rc::Rc::get_mut_unchecked`.
+    unsafe fn get_mut_unchecked(this: &mut Self) -> &mut T;
     fn new_cyclic<F>(data_fn: F) -> Self
     where
         F: FnOnce(&Self::WeakPointer) -> T,
@@ -92,6 +338,16 @@ pub trait RefCounted<T: ?Sized>: Deref<Target = T> + Clone {
     fn try_unwrap(this: Self) -> Result<T, Self>
     where
         T: Sized;
+    /// Returns the inner value if `this` is the last strong pointer.
+    /// See [std::sync::Arc::into_inner].
+    fn into_inner(this: Self) -> Option<T>
+    where
+        T: Sized;
+    /// Returns the inner value, cloning it if other strong pointers remain.
+    /// See [std::rc::Rc::unwrap_or_clone].
+    fn unwrap_or_clone(this: Self) -> T
+    where
+        T: Clone;
 }
 
 // pub trait WeakFamily {
@@ -132,6 +388,10 @@ pub trait WeakPointer<T: ?Sized>: Clone {
         //        WeakMark = Self::Mark,
         WeakPointer = Self,
     >;
+    /// Creates a weak pointer that never upgrades. See [std::rc::Weak::new].
+    fn new() -> Self
+    where
+        T: Sized;
     fn as_ptr(&self) -> *const T;
     /// # Safety
     /// see [std::rc::Weak::from_raw]
@@ -140,5 +400,16 @@ pub trait WeakPointer<T: ?Sized>: Clone {
     fn upgrade(&self) -> Option<Self::StrongPointer>;
     fn strong_count(&self) -> usize;
     fn ptr_eq(&self, other: &Self) -> bool;
+    /// Returns `true` if both pointers have the same data address,
+    /// ignoring any fat pointer metadata such as vtables.
+    fn addr_eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self.as_ptr(), other.as_ptr())
+    }
     fn weak_count(&self) -> usize;
 }
+
+/// The strong pointer to a `T` of the `Mark` family.
+pub type Strong<Mark, T> = <Mark as RefCountFamily>::Pointer<T>;
+
+/// The weak pointer to a `T` of the `Mark` family.
+pub type Weak<Mark, T> = <Mark as RefCountFamily>::WeakPointer<T>;
@@ -0,0 +1,286 @@
+use crate::{lock::LockFamily, traits::*};
+use std::{fmt, ops::Deref};
+
+/// A node of a tree of any family, holding its children strongly and its parent
+/// weakly, so dropping the last handle to the root frees the whole tree.
+///
+/// The links are kept in the locks of the [LockFamily], so a tree can be edited
+/// through shared handles. Each edit locks the links of one node at a time, so
+/// concurrent edits of the same tree from several threads must be serialized by
+/// the caller, for instance with a lock around the whole tree: otherwise two
+/// racing [SharedTree::append_child] may both pass their cycle check, or leave a
+/// node listed by two parents.
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// fn path<R: LockFamily>(node: &SharedTree<R, &'static str>) -> String {
+///     let mut names: Vec<_> = node.ancestors().map(|a| *a).collect();
+///     names.reverse();
+///     names.push(node);
+///     names.join("/")
+/// }
+/// let root = SharedTree::<RcMark, _>::new("root");
+/// let dir = SharedTree::new("dir");
+/// let file = SharedTree::new("file");
+/// root.append_child(&dir);
+/// dir.append_child(&file);
+/// assert_eq!(path(&file), "root/dir/file");
+/// file.detach();
+/// assert_eq!(path(&file), "file");
+/// ```
+pub struct SharedTree<Mark: LockFamily, T>(TreeNodePointer<Mark, T>);
+
+/// The shared part of a [SharedTree] node, with its value and links.
+pub struct TreeNode<Mark: LockFamily, T> {
+    value: T,
+    parent: Mark::Lock<Option<TreeNodeWeakPointer<Mark, T>>>,
+    children: Mark::Lock<Vec<TreeNodePointer<Mark, T>>>,
+}
+
+type TreeNodePointer<Mark, T> = <Mark as RefCountFamily>::Pointer<TreeNode<Mark, T>>;
+type TreeNodeWeakPointer<Mark, T> = <Mark as RefCountFamily>::WeakPointer<TreeNode<Mark, T>>;
+
+impl<Mark: LockFamily, T> SharedTree<Mark, T> {
+    /// Creates a node without parent nor children.
+    pub fn new(value: T) -> Self {
+        Self(Mark::new(TreeNode {
+            value,
+            parent: Mark::new_lock(None),
+            children: Mark::new_lock(Vec::new()),
+        }))
+    }
+
+    /// Creates a node without parent nor children, whose value is made by
+    /// `value_fn` from a weak pointer to the node, for values referring to their
+    /// own node.
+    ///
+    /// ```
+    /// # use cark_ref_counted::*;
+    /// # use std::rc::Weak;
+    /// struct Widget {
+    ///     name: &'static str,
+    ///     node: Weak<TreeNode<RcMark, Widget>>,
+    /// }
+    /// impl Widget {
+    ///     fn new(name: &'static str) -> SharedTree<RcMark, Widget> {
+    ///         SharedTree::new_cyclic(|node: &Weak<_>| Widget { name, node: node.clone() })
+    ///     }
+    ///     fn parent_name(&self) -> Option<&'static str> {
+    ///         let node = SharedTree::<RcMark, _>::from_pointer(self.node.upgrade()?);
+    ///         Some(node.parent()?.name)
+    ///     }
+    /// }
+    /// let (window, button) = (Widget::new("window"), Widget::new("button"));
+    /// window.append_child(&button);
+    /// assert_eq!(button.parent_name(), Some("window"));
+    /// ```
+    pub fn new_cyclic(value_fn: impl FnOnce(&TreeNodeWeakPointer<Mark, T>) -> T) -> Self {
+        Self(Mark::Pointer::new_cyclic(|weak| TreeNode {
+            value: value_fn(weak),
+            parent: Mark::new_lock(None),
+            children: Mark::new_lock(Vec::new()),
+        }))
+    }
+
+    /// Returns a handle to the node of `pointer`, as returned by
+    /// [SharedTree::pointer].
+    pub fn from_pointer(pointer: TreeNodePointer<Mark, T>) -> Self {
+        Self(pointer)
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        let parent = Mark::read(&self.0.parent);
+        parent.as_ref().and_then(WeakPointer::upgrade).map(Self)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.parent().is_none()
+    }
+
+    /// Returns handles to the children, in order.
+    pub fn children(&self) -> Vec<Self> {
+        Mark::read(&self.0.children)
+            .iter()
+            .map(|child| Self(child.clone()))
+            .collect()
+    }
+
+    /// Moves `child` to the end of the children of this node, detaching it from its
+    /// parent first.
+    ///
+    /// The cycle check, the detaching and the linking lock the nodes one at a
+    /// time, see [SharedTree] about concurrent edits.
+    ///
+    /// # Panics
+    /// If `child` is this node or one of its ancestors, which would make a cycle.
+    pub fn append_child(&self, child: &Self) {
+        assert!(
+            !child.ptr_eq(self) && !self.ancestors().any(|a| a.ptr_eq(child)),
+            "appending an ancestor of a node to it would make a cycle"
+        );
+        child.detach();
+        *Mark::write(&child.0.parent) = Some(Mark::Pointer::downgrade(&self.0));
+        Mark::write(&self.0.children).push(child.0.clone());
+    }
+
+    /// Removes this node from the children of its parent, making it a root.
+    pub fn detach(&self) {
+        let parent = Mark::write(&self.0.parent).take();
+        if let Some(parent) = parent.as_ref().and_then(WeakPointer::upgrade) {
+            Mark::write(&parent.children).retain(|c| !Mark::Pointer::ptr_eq(c, &self.0));
+        }
+    }
+
+    /// Returns an iterator over the ancestors of this node, from its parent up.
+    pub fn ancestors(&self) -> Ancestors<Mark, T> {
+        Ancestors(self.parent())
+    }
+
+    /// Returns an iterator over the descendants of this node, in depth-first
+    /// pre-order. Each node's children are read when it is reached.
+    pub fn descendants(&self) -> Descendants<Mark, T> {
+        let mut stack = self.children();
+        stack.reverse();
+        Descendants(stack)
+    }
+
+    /// Returns a reference to the wrapped pointer.
+    pub fn pointer(&self) -> &TreeNodePointer<Mark, T> {
+        &self.0
+    }
+
+    /// Returns `true` if both are handles to the same node.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Mark::Pointer::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Mark: LockFamily, T> Drop for TreeNode<Mark, T> {
+    /// Drops the unshared descendants one by one, as the recursive drop of a deep
+    /// tree would overflow the stack.
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(Mark::lock_get_mut(&mut self.children));
+        while let Some(child) = stack.pop() {
+            if let Some(mut child) = Mark::Pointer::into_inner(child) {
+                stack.append(Mark::lock_get_mut(&mut child.children));
+            }
+        }
+    }
+}
+
+impl<Mark: LockFamily, T> Clone for SharedTree<Mark, T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Mark: LockFamily, T> Deref for SharedTree<Mark, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<Mark: LockFamily, T: Default> Default for SharedTree<Mark, T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<Mark: LockFamily, T: fmt::Debug> fmt::Debug for SharedTree<Mark, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedTree")
+            .field("value", &**self)
+            .field("children", &self.children())
+            .finish()
+    }
+}
+
+/// The iterator returned by [SharedTree::ancestors].
+pub struct Ancestors<Mark: LockFamily, T>(Option<SharedTree<Mark, T>>);
+
+impl<Mark: LockFamily, T> Iterator for Ancestors<Mark, T> {
+    type Item = SharedTree<Mark, T>;
+
+    fn next(&mut self) -> Option<SharedTree<Mark, T>> {
+        let node = self.0.take()?;
+        self.0 = node.parent();
+        Some(node)
+    }
+}
+
+/// The iterator returned by [SharedTree::descendants].
+pub struct Descendants<Mark: LockFamily, T>(Vec<SharedTree<Mark, T>>);
+
+impl<Mark: LockFamily, T> Iterator for Descendants<Mark, T> {
+    type Item = SharedTree<Mark, T>;
+
+    fn next(&mut self) -> Option<SharedTree<Mark, T>> {
+        let node = self.0.pop()?;
+        self.0.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_shared_tree() {
+        fn actual_test<R: LockFamily>() {
+            let root = SharedTree::<R, i32>::new(0);
+            let (a, b, c) = (SharedTree::new(1), SharedTree::new(2), SharedTree::new(3));
+            root.append_child(&a);
+            root.append_child(&b);
+            a.append_child(&c);
+            assert!(root.is_root() && c.parent().unwrap().ptr_eq(&a));
+            assert_eq!(
+                root.descendants().map(|n| *n).collect::<Vec<_>>(),
+                [1, 3, 2]
+            );
+            assert_eq!(c.ancestors().map(|n| *n).collect::<Vec<_>>(), [1, 0]);
+            assert_eq!(
+                format!("{a:?}"),
+                "SharedTree { value: 1, children: [SharedTree { value: 3, children: [] }] }"
+            );
+
+            b.append_child(&c);
+            assert_eq!((a.children().len(), *c.parent().unwrap()), (0, 2));
+            let cycle = std::panic::AssertUnwindSafe(|| c.append_child(&root));
+            assert!(std::panic::catch_unwind(cycle).is_err());
+
+            let weak = R::Pointer::downgrade(c.pointer());
+            drop((a, b, c));
+            assert_eq!(root.descendants().count(), 3);
+            drop(root);
+            assert!(weak.upgrade().is_none());
+
+            struct SelfAware<R: LockFamily>(TreeNodeWeakPointer<R, SelfAware<R>>);
+            let node = SharedTree::<R, SelfAware<R>>::new_cyclic(|me| SelfAware(me.clone()));
+            let me = SharedTree::<R, SelfAware<R>>::from_pointer((*node).0.upgrade().unwrap());
+            assert!(me.ptr_eq(&node));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[test]
+    fn test_deep_tree_drop() {
+        fn actual_test<R: LockFamily>() {
+            let leaf = SharedTree::<R, i32>::new(0);
+            let weak = R::Pointer::downgrade(leaf.pointer());
+            let mut root = leaf;
+            for i in 1..100_000 {
+                let parent = SharedTree::new(i);
+                parent.append_child(&root);
+                root = parent;
+            }
+            drop(root);
+            assert!(weak.upgrade().is_none());
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
@@ -0,0 +1,146 @@
+//! [UniqueFamily] implementations for [RcMark] and [ArcMark].
+//!
+//! [UniqueRc] and [UniqueArc] wrap a pointer that is never cloned nor downgraded.
+//! With the `nightly` feature, they wrap the standard `UniqueRc` and `UniqueArc`
+//! instead, and the marks implement [UniqueWeakFamily].
+
+use crate::{traits::*, ArcMark, RcMark};
+use std::{
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    sync::Arc,
+};
+
+/// A uniquely owned [Rc], until it is frozen.
+pub struct UniqueRc<T>(
+    #[cfg(feature = "nightly")] std::rc::UniqueRc<T>,
+    #[cfg(not(feature = "nightly"))] Rc<T>,
+);
+
+/// A uniquely owned [Arc], until it is frozen.
+pub struct UniqueArc<T>(
+    #[cfg(feature = "nightly")] std::sync::UniqueArc<T>,
+    #[cfg(not(feature = "nightly"))] Arc<T>,
+);
+
+impl<T> Deref for UniqueRc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for UniqueRc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        #[cfg(feature = "nightly")]
+        return &mut self.0;
+        #[cfg(not(feature = "nightly"))]
+        return Rc::get_mut(&mut self.0).expect("the pointer is unique");
+    }
+}
+
+impl<T> Deref for UniqueArc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for UniqueArc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        #[cfg(feature = "nightly")]
+        return &mut self.0;
+        #[cfg(not(feature = "nightly"))]
+        return Arc::get_mut(&mut self.0).expect("the pointer is unique");
+    }
+}
+
+impl UniqueFamily for RcMark {
+    type UniquePointer<T> = UniqueRc<T>;
+
+    fn new_unique<T>(value: T) -> UniqueRc<T> {
+        #[cfg(feature = "nightly")]
+        return UniqueRc(std::rc::UniqueRc::new(value));
+        #[cfg(not(feature = "nightly"))]
+        return UniqueRc(Rc::new(value));
+    }
+}
+
+impl<T> UniquePointer<T> for UniqueRc<T> {
+    type Mark = RcMark;
+
+    fn into_shared(this: Self) -> Rc<T> {
+        #[cfg(feature = "nightly")]
+        return std::rc::UniqueRc::into_rc(this.0);
+        #[cfg(not(feature = "nightly"))]
+        return this.0;
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl UniqueWeakFamily for RcMark {
+    fn downgrade_unique<T>(this: &UniqueRc<T>) -> std::rc::Weak<T> {
+        std::rc::UniqueRc::downgrade(&this.0)
+    }
+}
+
+impl UniqueFamily for ArcMark {
+    type UniquePointer<T> = UniqueArc<T>;
+
+    fn new_unique<T>(value: T) -> UniqueArc<T> {
+        #[cfg(feature = "nightly")]
+        return UniqueArc(std::sync::UniqueArc::new(value));
+        #[cfg(not(feature = "nightly"))]
+        return UniqueArc(Arc::new(value));
+    }
+}
+
+impl<T> UniquePointer<T> for UniqueArc<T> {
+    type Mark = ArcMark;
+
+    fn into_shared(this: Self) -> Arc<T> {
+        #[cfg(feature = "nightly")]
+        return std::sync::UniqueArc::into_arc(this.0);
+        #[cfg(not(feature = "nightly"))]
+        return this.0;
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl UniqueWeakFamily for ArcMark {
+    fn downgrade_unique<T>(this: &UniqueArc<T>) -> std::sync::Weak<T> {
+        std::sync::UniqueArc::downgrade(&this.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_shared() {
+        fn actual_test<R: UniqueFamily>() {
+            let mut unique = R::new_unique(1);
+            *unique += 1;
+            let shared = UniquePointer::into_shared(unique);
+            assert_eq!(*shared, 2);
+            assert_eq!(R::Pointer::strong_count(&shared), 1);
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_downgrade_unique() {
+        fn actual_test<R: UniqueWeakFamily>() {
+            let unique = R::new_unique(1);
+            let weak = R::downgrade_unique(&unique);
+            assert!(weak.upgrade().is_none());
+            let shared = UniquePointer::into_shared(unique);
+            assert!(R::Pointer::ptr_eq(&weak.upgrade().unwrap(), &shared));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}
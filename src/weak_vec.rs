@@ -0,0 +1,159 @@
+use crate::traits::*;
+use std::fmt;
+
+/// A list of weak pointers, such as the listeners of an event, yielding only the
+/// values still alive.
+///
+/// The slots of dropped values are compacted once the list has doubled in size
+/// since the last compaction, or by [WeakVec::compact].
+///
+/// ```
+/// # use cark_ref_counted::*;
+/// # use std::cell::Cell;
+/// fn notify<R: RefCountFamily>(listeners: &WeakVec<R, dyn Fn(i32)>, event: i32) {
+///     for listener in listeners.iter() {
+///         listener(event);
+///     }
+/// }
+/// let total = std::rc::Rc::new(Cell::new(0));
+/// let total2 = total.clone();
+/// let listener = RcMark::from_box(Box::new(move |e| total2.set(total2.get() + e)) as Box<dyn Fn(i32)>);
+/// let mut listeners = WeakVec::<RcMark, dyn Fn(i32)>::new();
+/// listeners.push(&listener);
+/// notify(&listeners, 1);
+/// drop(listener);
+/// notify(&listeners, 2);
+/// assert_eq!(total.get(), 1);
+/// ```
+pub struct WeakVec<Mark: RefCountFamily, T: ?Sized> {
+    slots: Vec<Mark::WeakPointer<T>>,
+    /// The number of slots at which to compact.
+    compact_at: usize,
+}
+
+const MIN_COMPACT_AT: usize = 8;
+
+impl<Mark: RefCountFamily, T: ?Sized> WeakVec<Mark, T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            compact_at: MIN_COMPACT_AT,
+        }
+    }
+
+    /// Returns the number of slots, including the ones of dropped values not
+    /// compacted yet.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Adds a weak pointer to the value of `pointer`.
+    pub fn push(&mut self, pointer: &Mark::Pointer<T>) {
+        self.push_weak(Mark::Pointer::downgrade(pointer));
+    }
+
+    pub fn push_weak(&mut self, weak: Mark::WeakPointer<T>) {
+        if self.slots.len() >= self.compact_at {
+            self.compact();
+        }
+        self.slots.push(weak);
+    }
+
+    /// Removes the slots of the value of `pointer`, returning `true` if there
+    /// were any.
+    pub fn remove(&mut self, pointer: &Mark::Pointer<T>) -> bool {
+        let address = Mark::Pointer::as_ptr(pointer);
+        let len = self.slots.len();
+        self.slots
+            .retain(|weak| !std::ptr::addr_eq(weak.as_ptr(), address));
+        self.slots.len() != len
+    }
+
+    /// Returns strong pointers to the values alive, in the order they were pushed.
+    pub fn iter(&self) -> impl Iterator<Item = Mark::Pointer<T>> + '_ {
+        self.slots.iter().filter_map(WeakPointer::upgrade)
+    }
+
+    /// Removes the slots of dropped values.
+    pub fn compact(&mut self) {
+        self.slots.retain(|weak| weak.strong_count() > 0);
+        self.compact_at = (self.slots.len() * 2).max(MIN_COMPACT_AT);
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.compact_at = MIN_COMPACT_AT;
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Clone for WeakVec<Mark, T> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            compact_at: self.compact_at,
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized> Default for WeakVec<Mark, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, Mark: RefCountFamily, T: ?Sized + 'a> Extend<&'a Mark::Pointer<T>> for WeakVec<Mark, T> {
+    fn extend<I: IntoIterator<Item = &'a Mark::Pointer<T>>>(&mut self, iter: I) {
+        for pointer in iter {
+            self.push(pointer);
+        }
+    }
+}
+
+impl<Mark: RefCountFamily, T: ?Sized + fmt::Debug> fmt::Debug for WeakVec<Mark, T> {
+    /// Lists the values alive.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<_> = self.iter().collect();
+        f.debug_list()
+            .entries(values.iter().map(|value| &**value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArcMark, RcMark};
+
+    #[test]
+    fn test_weak_vec() {
+        fn actual_test<R: RefCountFamily>() {
+            let (a, b, c) = (R::new(1), R::new(2), R::new(3));
+            let mut vec = WeakVec::<R, i32>::new();
+            vec.extend([&a, &b, &c]);
+            assert_eq!(format!("{vec:?}"), "[1, 2, 3]");
+            drop(b);
+            assert_eq!(
+                (vec.len(), vec.iter().map(|v| *v).collect::<Vec<_>>()),
+                (3, vec![1, 3])
+            );
+            assert!(vec.remove(&a) && !vec.remove(&a));
+            vec.compact();
+            assert_eq!(vec.len(), 1);
+
+            for i in 0..100 {
+                vec.push(&R::new(i));
+            }
+            assert!(vec.len() <= MIN_COMPACT_AT);
+            assert!(vec.iter().all(|v| R::Pointer::ptr_eq(&v, &c)));
+            let copy = vec.clone();
+            vec.clear();
+            assert_eq!((vec.is_empty(), copy.iter().count()), (true, 1));
+        }
+        actual_test::<RcMark>();
+        actual_test::<ArcMark>();
+    }
+}